@@ -0,0 +1,233 @@
+use crate::{Error, Module};
+use std::{
+    collections::HashMap,
+    path::{Component, Path},
+};
+
+/// Size of tar headers, and the unit file contents are padded to
+const BLOCK_SIZE: usize = 512;
+
+/// A set of modules read from a `tar` archive, by path
+/// Set it as `RuntimeOptions::archive` and imports will resolve against its entries
+/// before the filesystem. Paths are resolved relative to the current working dir,
+/// as with `EmbeddedModuleProvider`
+///
+/// # Example
+///
+/// ```no_run
+/// use rustyscript::{ ArchiveModuleSource, Error, Runtime, RuntimeOptions };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// # tokio_test::block_on(async {
+/// let bytes = std::fs::read("plugin.tar")?;
+/// let archive = ArchiveModuleSource::from_tar(&bytes)?;
+/// let main = archive
+///     .module("plugin/main.js")
+///     .ok_or_else(|| Error::ModuleNotFound("plugin/main.js".to_string()))?;
+///
+/// let mut runtime = Runtime::new(RuntimeOptions {
+///     archive: Some(archive),
+///     ..Default::default()
+/// })?;
+/// runtime.load_module(&main).await?;
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ArchiveModuleSource {
+    files: HashMap<String, String>,
+}
+
+impl ArchiveModuleSource {
+    /// Read the regular files in a `tar` archive
+    /// Both ustar and GNU long filenames are supported, and any other
+    /// entries, such as directories or links, are skipped
+    ///
+    /// # Arguments
+    /// * `bytes` - The uncompressed contents of the archive
+    ///
+    /// # Returns
+    /// A `Result` that is an error if the archive is malformed, contains a file that
+    /// is not valid UTF-8, or a file whose path is absolute or contains `..`, which
+    /// could otherwise shadow modules outside of the working dir
+    pub fn from_tar(bytes: &[u8]) -> Result<Self, Error> {
+        let mut files = HashMap::new();
+        let mut long_name = None;
+        let mut offset = 0;
+
+        while offset + BLOCK_SIZE <= bytes.len() {
+            let header = &bytes[offset..offset + BLOCK_SIZE];
+            if header.iter().all(|b| *b == 0) {
+                break;
+            }
+
+            let size = parse_octal(&header[124..136])?;
+            let start = offset + BLOCK_SIZE;
+            let end = start
+                .checked_add(size)
+                .filter(|end| *end <= bytes.len())
+                .ok_or_else(|| Error::Runtime("invalid archive: truncated entry".to_string()))?;
+            let contents = &bytes[start..end];
+
+            match header[156] {
+                // GNU extension - the next entry's name is stored as this entry's contents
+                b'L' => long_name = Some(header_str(contents).to_string()),
+
+                b'0' | 0 => {
+                    let name = match long_name.take() {
+                        Some(name) => name,
+                        None => match header_str(&header[345..500]) {
+                            prefix if &header[257..262] == b"ustar" && !prefix.is_empty() => {
+                                format!("{prefix}/{}", header_str(&header[0..100]))
+                            }
+                            _ => header_str(&header[0..100]).to_string(),
+                        },
+                    };
+
+                    let escapes = Path::new(&name).components().any(|c| {
+                        matches!(
+                            c,
+                            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+                        )
+                    });
+                    if escapes {
+                        return Err(Error::Runtime(format!(
+                            "invalid archive: {name} is outside of the archive"
+                        )));
+                    }
+
+                    let contents = String::from_utf8(contents.to_vec()).map_err(|_| {
+                        Error::Runtime(format!("invalid archive: {name} is not valid UTF-8"))
+                    })?;
+                    files.insert(name.trim_start_matches("./").to_string(), contents);
+                }
+
+                _ => long_name = None,
+            }
+
+            offset = start + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+        }
+
+        Ok(Self { files })
+    }
+
+    /// Get the contents of the file at the given path
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+
+    /// Get the module at the given path, for loading into a runtime
+    pub fn module(&self, path: &str) -> Option<Module> {
+        Some(Module::new(path, self.get(path)?))
+    }
+
+    /// Returns the number of files in the archive
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if the archive contains no files
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Get the contents of every file, by path
+    pub(crate) fn sources(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.files.iter()
+    }
+}
+
+/// Read a NUL-terminated string from a header field
+fn header_str(field: &[u8]) -> &str {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    std::str::from_utf8(&field[..end]).unwrap_or_default()
+}
+
+/// Read a header field holding an octal number
+fn parse_octal(field: &[u8]) -> Result<usize, Error> {
+    let digits = header_str(field).trim_matches(|c: char| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    usize::from_str_radix(digits, 8)
+        .map_err(|_| Error::Runtime(format!("invalid archive: bad entry size {digits}")))
+}
+
+#[cfg(test)]
+mod test_archive_modules {
+    use super::*;
+    use crate::{json_args, Runtime, RuntimeOptions};
+
+    /// Build an uncompressed ustar archive
+    fn tar(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (name, contents) in files {
+            let mut header = [0u8; BLOCK_SIZE];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[100..107].copy_from_slice(b"0000644");
+            header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+
+            // The checksum is computed with its own field filled with spaces
+            header[148..156].fill(b' ');
+            let checksum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+            header[148..155].copy_from_slice(format!("{checksum:06o}\0").as_bytes());
+
+            bytes.extend_from_slice(&header);
+            bytes.extend_from_slice(contents.as_bytes());
+            bytes.resize(bytes.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+        }
+        bytes.resize(bytes.len() + 2 * BLOCK_SIZE, 0);
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_archive_import() {
+        let bytes = tar(&[
+            (
+                "plugin/main.js",
+                "import { double } from './lib.js'; export const f = () => double(2);",
+            ),
+            ("plugin/lib.js", "export const double = (n) => n * 2;"),
+        ]);
+        let archive = ArchiveModuleSource::from_tar(&bytes).expect("Could not read archive");
+        assert_eq!(2, archive.len());
+        let main = archive
+            .module("plugin/main.js")
+            .expect("Could not find main module");
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            archive: Some(archive),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&main)
+            .await
+            .expect("Could not load module");
+
+        let value: usize = runtime
+            .call_function(&module, "f", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(4, value);
+    }
+
+    #[test]
+    fn test_truncated_archive() {
+        let bytes = tar(&[("main.js", "export const value = 2;")]);
+        ArchiveModuleSource::from_tar(&bytes[..BLOCK_SIZE + 4])
+            .expect_err("Did not detect a truncated archive");
+    }
+
+    #[test]
+    fn test_escaping_paths() {
+        for name in ["/etc/app.js", "../app.js", "plugin/../../app.js"] {
+            let bytes = tar(&[(name, "export const value = 2;")]);
+            ArchiveModuleSource::from_tar(&bytes).expect_err("Accepted a path outside the archive");
+        }
+    }
+}
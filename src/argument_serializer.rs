@@ -0,0 +1,114 @@
+use deno_core::{serde_json, serde_v8, v8};
+
+/// Converts specific argument shapes into richer JS values, such as a real `Date`,
+/// before a function is called. Arguments it does not recognize are passed as JSON
+///
+/// Only the top level of each argument is offered to the serializer
+/// See `TaggedArgumentSerializer` for a ready-made implementation
+pub trait ArgumentSerializer {
+    /// Convert an argument into a JS value
+    ///
+    /// # Arguments
+    /// * `scope` - The scope in which to create the value
+    /// * `value` - The argument passed to the function
+    ///
+    /// # Returns
+    /// The value to pass to JS, or `None` to serialize the argument as JSON
+    fn serialize<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        value: &serde_json::Value,
+    ) -> Option<v8::Local<'s, v8::Value>>;
+}
+
+/// An argument serializer that recognizes tagged objects:
+/// - `{ "$date": 0 }` becomes a `Date`, from milliseconds since the epoch
+/// - `{ "$bytes": [1, 2] }` becomes a `Uint8Array`
+/// - `{ "$map": [["key", "value"]] }` becomes a `Map`
+/// - `{ "$set": ["value"] }` becomes a `Set`
+///
+/// Use `TaggedArgumentSerializer::date`, `bytes`, `map` and `set` to build tagged arguments,
+/// or pass a `JsMap` or `JsSet`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaggedArgumentSerializer;
+
+impl TaggedArgumentSerializer {
+    /// Create an argument that is delivered as a JS `Date`
+    pub fn date(millis_since_epoch: f64) -> serde_json::Value {
+        serde_json::json!({ "$date": millis_since_epoch })
+    }
+
+    /// Create an argument that is delivered as a JS `Uint8Array`
+    pub fn bytes(bytes: &[u8]) -> serde_json::Value {
+        serde_json::json!({ "$bytes": bytes })
+    }
+
+    /// Create an argument that is delivered as a JS `Map`
+    pub fn map<I>(entries: I) -> serde_json::Value
+    where
+        I: IntoIterator<Item = (serde_json::Value, serde_json::Value)>,
+    {
+        let entries: Vec<_> = entries.into_iter().map(|(k, v)| [k, v]).collect();
+        serde_json::json!({ "$map": entries })
+    }
+
+    /// Create an argument that is delivered as a JS `Set`
+    pub fn set<I>(values: I) -> serde_json::Value
+    where
+        I: IntoIterator<Item = serde_json::Value>,
+    {
+        let values: Vec<_> = values.into_iter().collect();
+        serde_json::json!({ "$set": values })
+    }
+}
+
+impl ArgumentSerializer for TaggedArgumentSerializer {
+    fn serialize<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        value: &serde_json::Value,
+    ) -> Option<v8::Local<'s, v8::Value>> {
+        let object = value.as_object().filter(|o| o.len() == 1)?;
+        let (tag, value) = object.iter().next()?;
+
+        match tag.as_str() {
+            "$date" => {
+                let date = v8::Date::new(scope, value.as_f64()?)?;
+                Some(date.into())
+            }
+
+            "$bytes" => {
+                let bytes: Vec<u8> = serde_json::from_value(value.clone()).ok()?;
+                let length = bytes.len();
+                let store = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+                let buffer = v8::ArrayBuffer::with_backing_store(scope, &store);
+                let array = v8::Uint8Array::new(scope, buffer, 0, length)?;
+                Some(array.into())
+            }
+
+            "$map" => {
+                let map = v8::Map::new(scope);
+                for entry in value.as_array()? {
+                    let [k, v] = entry.as_array()?.as_slice() else {
+                        return None;
+                    };
+                    let k = serde_v8::to_v8(scope, k).ok()?;
+                    let v = serde_v8::to_v8(scope, v).ok()?;
+                    map.set(scope, k, v)?;
+                }
+                Some(map.into())
+            }
+
+            "$set" => {
+                let set = v8::Set::new(scope);
+                for value in value.as_array()? {
+                    let value = serde_v8::to_v8(scope, value).ok()?;
+                    set.add(scope, value)?;
+                }
+                Some(set.into())
+            }
+
+            _ => None,
+        }
+    }
+}
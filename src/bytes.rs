@@ -0,0 +1,115 @@
+use deno_core::serde_v8::JsBuffer;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::Deref;
+
+/// Binary data, read directly from a JS `Uint8Array` or `ArrayBuffer`
+///
+/// Deserializing into a `Vec<u8>` goes through an array of numbers instead,
+/// which is slow for large buffers. `Bytes` can only be read from the runtime,
+/// such as with `Runtime::get_value` or `Runtime::call_function`
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ Bytes, Module, Runtime };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// # tokio_test::block_on(async {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("test.js", "export const data = new Uint8Array([1, 2, 3]);");
+/// let module = runtime.load_module(&module).await?;
+/// let data: Bytes = runtime.get_value(&module, "data").await?;
+/// assert_eq!(&[1, 2, 3], &data[..]);
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+    /// Returns the underlying bytes
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Bytes> for Vec<u8> {
+    fn from(bytes: Bytes) -> Self {
+        bytes.0
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let buffer = JsBuffer::deserialize(deserializer)?;
+        Ok(Self(buffer.to_vec()))
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test_bytes {
+    use super::*;
+    use crate::{json_args, Module, Runtime};
+
+    #[tokio::test]
+    async fn test_bytes() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const array = new Uint8Array([0, 1, 127, 255]);
+            export const buffer = array.buffer;
+            export const compress = () => new Uint8Array(1024).fill(7);
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let array: Bytes = runtime
+            .get_value(&module, "array")
+            .await
+            .expect("Could not get value");
+        assert_eq!(vec![0, 1, 127, 255], array.into_inner());
+
+        let buffer: Bytes = runtime
+            .get_value(&module, "buffer")
+            .await
+            .expect("Could not get value");
+        assert_eq!(&[0, 1, 127, 255], &buffer[..]);
+
+        let compressed: Bytes = runtime
+            .call_function(&module, "compress", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(vec![7; 1024], compressed.0);
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+/// A signal used to cancel a runtime's work from outside of it
+/// Clones share the same signal, so one can be kept by the host and triggered
+/// from any thread or task, while the runtime holds another
+/// See `RuntimeOptions::cancel_token`
+///
+/// # Example
+/// ```rust
+/// use rustyscript::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+/// std::thread::spawn(move || handle.cancel()).join().unwrap();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<TokenState>);
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+
+    /// The waker of each future waiting on the token, by registration
+    /// A future replaces its own entry when polled again, and removes it when dropped
+    wakers: Mutex<HashMap<u64, Waker>>,
+    next_id: AtomicU64,
+}
+
+impl CancellationToken {
+    /// Create a new token, which is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel any work using this token
+    /// Work started after this call is also cancelled, until `CancellationToken::reset` is called
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        if let Ok(mut wakers) = self.0.wakers.lock() {
+            wakers.drain().for_each(|(_, waker)| waker.wake());
+        }
+    }
+
+    /// Returns true if the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Clear the cancellation, so the token can be used for new work
+    pub fn reset(&self) {
+        self.0.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Resolves once the token is cancelled
+    pub fn cancelled(&self) -> impl Future<Output = ()> + '_ {
+        Cancelled {
+            token: self,
+            id: self.0.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+struct Cancelled<'a> {
+    token: &'a CancellationToken,
+    id: u64,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        if let Ok(mut wakers) = self.token.0.wakers.lock() {
+            match wakers.get_mut(&self.id) {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => {
+                    wakers.insert(self.id, cx.waker().clone());
+                }
+            }
+        }
+
+        // Cancellation may have happened while the waker was being stored
+        if self.token.is_cancelled() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Cancelled<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut wakers) = self.token.0.wakers.lock() {
+            wakers.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_cancellation {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dropped_waiters() {
+        let token = CancellationToken::new();
+        for _ in 0..10 {
+            let waiter = token.cancelled();
+            tokio::time::timeout(std::time::Duration::from_millis(1), waiter)
+                .await
+                .expect_err("Token was cancelled");
+        }
+
+        // Waiters that gave up leave nothing behind
+        assert!(token.0.wakers.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::Module;
+use std::{borrow::Cow, collections::HashMap};
+
+/// A set of modules embedded in the binary, by path
+/// Populate it from `rust-embed`, `include_dir!` or `include_bytes!`, and set it as
+/// `RuntimeOptions::embedded_modules` - imports will then resolve against it
+/// before the filesystem. Relative paths are resolved to the current working dir
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::EmbeddedModuleProvider;
+///
+/// let mut modules = EmbeddedModuleProvider::default();
+/// modules.insert("main.js", b"import { x } from './lib.js';".as_slice());
+/// modules.insert("lib.js", b"export const x = 2;".as_slice());
+///
+/// let main = modules.module("main.js").expect("main.js is embedded");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EmbeddedModuleProvider {
+    files: HashMap<String, Cow<'static, [u8]>>,
+}
+
+impl EmbeddedModuleProvider {
+    /// Add a module's contents under the given path
+    pub fn insert<P, C>(&mut self, path: P, contents: C)
+    where
+        P: Into<String>,
+        C: Into<Cow<'static, [u8]>>,
+    {
+        self.files.insert(path.into(), contents.into());
+    }
+
+    /// Get the raw contents of the module at the given path
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(AsRef::as_ref)
+    }
+
+    /// Get the module at the given path, for loading into a runtime
+    pub fn module(&self, path: &str) -> Option<Module> {
+        let contents = String::from_utf8_lossy(self.get(path)?);
+        Some(Module::new(path, &contents))
+    }
+
+    /// Returns the number of embedded modules
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if there are no embedded modules
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Get the contents of every module, by path, as text
+    pub(crate) fn sources(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.files
+            .iter()
+            .map(|(path, contents)| (path.clone(), String::from_utf8_lossy(contents).into_owned()))
+    }
+}
+
+impl<P, C> FromIterator<(P, C)> for EmbeddedModuleProvider
+where
+    P: Into<String>,
+    C: Into<Cow<'static, [u8]>>,
+{
+    fn from_iter<I: IntoIterator<Item = (P, C)>>(iter: I) -> Self {
+        let mut provider = Self::default();
+        for (path, contents) in iter {
+            provider.insert(path, contents);
+        }
+        provider
+    }
+}
+
+#[cfg(test)]
+mod test_embedded_modules {
+    use super::*;
+    use crate::{json_args, Runtime, RuntimeOptions};
+
+    #[tokio::test]
+    async fn test_embedded_import() {
+        let embedded: EmbeddedModuleProvider = [
+            (
+                "embedded/main.js",
+                b"import { double } from './lib.js'; export const f = () => double(2);".as_slice(),
+            ),
+            (
+                "embedded/lib.js",
+                b"export const double = (n) => n * 2;".as_slice(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let main = embedded
+            .module("embedded/main.js")
+            .expect("Could not find main module");
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            embedded_modules: embedded,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&main)
+            .await
+            .expect("Could not load module");
+
+        let value: usize = runtime
+            .call_function(&module, "f", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(4, value);
+    }
+}
@@ -1,12 +1,53 @@
 use crate::Module;
+use std::{fmt::Display, rc::Rc, time::Duration};
 use thiserror::Error;
 
+/// The operations a runtime can perform under a timeout
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Operation {
+    /// Loading and evaluating modules
+    LoadModule,
+
+    /// Calling a function and resolving its result
+    CallFunction,
+
+    /// Resolving a value
+    GetValue,
+
+    /// Evaluating an expression
+    Eval,
+
+    /// Any other operation
+    Other,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::LoadModule => write!(f, "Module loading"),
+            Operation::CallFunction => write!(f, "Function call"),
+            Operation::GetValue => write!(f, "Value resolution"),
+            Operation::Eval => write!(f, "Evaluation"),
+            Operation::Other => write!(f, "Operation"),
+        }
+    }
+}
+
 /// Represents the errors that can occur during execution of a module
 #[derive(Error, Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Error {
     /// Triggers when a module has no stated entrypoint (default or registered at runtime)
-    #[error("{0} has no entrypoint. Register one, or add a default to the runtime")]
-    MissingEntrypoint(Module),
+    #[error(
+        "{module} has no entrypoint{}. Register one, or add a default to the runtime",
+        attempted_names(.attempted)
+    )]
+    MissingEntrypoint {
+        /// The module that was missing an entrypoint
+        module: Module,
+
+        /// The names of the default entrypoints that were looked for in its exports
+        attempted: Vec<String>,
+    },
 
     /// Triggers when an attempt to find a value by name fails
     #[error("{0} could not be found in global, or module exports")]
@@ -36,9 +77,64 @@ pub enum Error {
     #[error("{0}")]
     JsError(#[from] deno_core::error::JsError),
 
-    /// Triggers when a module times out before finishing
-    #[error("Module timed out: {0}")]
-    Timeout(String),
+    /// Triggers when an operation times out before finishing
+    #[error("{operation} timed out: {message}")]
+    Timeout {
+        /// The operation that timed out
+        operation: Operation,
+
+        /// A description of the timeout
+        message: String,
+
+        /// How long the operation actually ran for, if known
+        elapsed: Option<Duration>,
+    },
+
+    /// Triggers when an extension could not be loaded, such as when its sources fail to transpile
+    #[error("could not load extension {name}: {source}")]
+    Extension {
+        /// The name of the extension
+        name: String,
+
+        /// The reason it could not be loaded
+        source: Box<Error>,
+    },
+
+    /// Triggers when an operation is stopped with a `CancellationToken`
+    #[error("{0} was cancelled")]
+    Cancelled(Operation),
+}
+
+/// The name and message of the JS exception thrown when an op returns an error
+/// See `RuntimeOptions::error_mapper`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MappedError {
+    /// Name of the exception, such as `TimeoutError`
+    pub name: String,
+
+    /// The exception's message
+    pub message: String,
+}
+
+/// Converts an error returned by an op into the exception thrown in JS
+pub type ErrorMapper = Rc<dyn Fn(&Error) -> MappedError>;
+
+/// A syntax error found without running a module
+/// See `Module::validate_syntax`
+#[derive(Error, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[error("{filename}:{line}:{column}: {message}")]
+pub struct SyntaxError {
+    /// The filename of the module
+    pub filename: String,
+
+    /// 1-based line number of the error
+    pub line: usize,
+
+    /// 1-based column number of the error
+    pub column: usize,
+
+    /// A description of the error
+    pub message: String,
 }
 
 impl Error {
@@ -103,6 +199,75 @@ impl Error {
             _ => format!("{}", self),
         }
     }
+
+    /// Removes stack frames whose file matches the filter from a JsError
+    /// Other errors are returned unchanged
+    pub(crate) fn filter_stack(self, filter: &dyn Fn(&str) -> bool) -> Self {
+        match self {
+            Error::JsError(e) => Error::JsError(filter_js_error(e, filter)),
+            e => e,
+        }
+    }
+}
+
+/// The default stack filter - hides frames from extensions and rustyscript internals
+pub fn default_stack_filter(file: &str) -> bool {
+    file.starts_with("ext:") || file.starts_with("rustyscript:")
+}
+
+/// Lists the names tried when looking for an entrypoint, for `Error::MissingEntrypoint`
+fn attempted_names(attempted: &[String]) -> String {
+    if attempted.is_empty() {
+        String::new()
+    } else {
+        format!(" (tried {})", attempted.join(", "))
+    }
+}
+
+fn filter_js_error(
+    mut e: deno_core::error::JsError,
+    filter: &dyn Fn(&str) -> bool,
+) -> deno_core::error::JsError {
+    e.frames
+        .retain(|f| !f.file_name.as_deref().is_some_and(filter));
+    e.stack = e.stack.map(|stack| {
+        stack
+            .lines()
+            .filter(|line| !stack_line_file(line).is_some_and(filter))
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+    e.cause = e
+        .cause
+        .map(|cause| Box::new(filter_js_error(*cause, filter)));
+    e.aggregated = e.aggregated.map(|errors| {
+        errors
+            .into_iter()
+            .map(|e| filter_js_error(e, filter))
+            .collect()
+    });
+    e
+}
+
+/// Extracts the file from a line of a stack trace, such as
+/// `    at fn (file:///test.js:1:2)`
+fn stack_line_file(line: &str) -> Option<&str> {
+    let location = line.trim_start().strip_prefix("at ")?;
+    let mut location = match location.rfind('(') {
+        Some(i) if location.ends_with(')') => &location[i + 1..location.len() - 1],
+        _ => location,
+    };
+
+    // Strip line and column numbers
+    for _ in 0..2 {
+        if let Some(i) = location.rfind(':') {
+            if location[i + 1..].chars().all(|c| c.is_ascii_digit()) {
+                location = &location[..i];
+            }
+        }
+    }
+
+    Some(location)
 }
 
 #[macro_use]
@@ -120,6 +285,7 @@ mod error_macro {
     }
 }
 
+map_error!(std::cell::BorrowError, |e| Error::Runtime(e.to_string()));
 map_error!(std::cell::BorrowMutError, |e| Error::Runtime(e.to_string()));
 map_error!(std::io::Error, |e| Error::ModuleNotFound(e.to_string()));
 map_error!(deno_core::v8::DataError, |e| Error::Runtime(e.to_string()));
@@ -146,11 +312,23 @@ map_error!(deno_core::anyhow::Error, |e| {
 });
 
 map_error!(tokio::time::error::Elapsed, |e| {
-    Error::Timeout(e.to_string())
+    Error::Timeout {
+        operation: Operation::Other,
+        message: e.to_string(),
+        elapsed: None,
+    }
 });
 map_error!(tokio::task::JoinError, |e| {
-    Error::Timeout(e.to_string())
+    Error::Timeout {
+        operation: Operation::Other,
+        message: e.to_string(),
+        elapsed: None,
+    }
 });
 map_error!(deno_core::futures::channel::oneshot::Canceled, |e| {
-    Error::Timeout(e.to_string())
+    Error::Timeout {
+        operation: Operation::Other,
+        message: e.to_string(),
+        elapsed: None,
+    }
 });
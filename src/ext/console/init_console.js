@@ -4,7 +4,7 @@ import { applyToGlobal, nonEnumerable } from "ext:rustyscript/rustyscript.js";
 applyToGlobal({
   console: nonEnumerable(
     new console.Console((msg, level) =>
-      globalThis.Deno.core.print(msg, level > 1)
+      globalThis.Deno.core.ops.op_rustyscript_print(msg, level > 1)
     )
   ),
 });
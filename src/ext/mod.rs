@@ -1,6 +1,8 @@
 use deno_core::Extension;
 
+pub mod node_compat;
 pub mod rustyscript;
+pub mod worker;
 
 #[cfg(feature = "console")]
 pub mod console;
@@ -20,10 +22,46 @@ pub mod web_stub;
 #[cfg(feature = "webidl")]
 pub mod webidl;
 
+/// A deno_core extension, along with how rustyscript should load it
+pub struct RustyExtension {
+    /// The extension to add to the runtime
+    pub extension: Extension,
+
+    /// Transpile the extension's ESM and JS sources before loading them
+    /// Only needed for extensions shipping typescript - ops-only and plain JS
+    /// extensions can skip it to save work at startup
+    pub transpile: bool,
+}
+
+impl RustyExtension {
+    /// An extension whose sources are loaded as-is, without transpiling them
+    pub fn without_transpile(extension: Extension) -> Self {
+        Self {
+            extension,
+            transpile: false,
+        }
+    }
+}
+
+impl From<Extension> for RustyExtension {
+    fn from(extension: Extension) -> Self {
+        Self {
+            extension,
+            transpile: true,
+        }
+    }
+}
+
 ///
 /// Add up all required extensions
-pub fn all_extensions(user_extensions: Vec<Extension>) -> Vec<Extension> {
-    let mut extensions = rustyscript::extensions();
+pub fn all_extensions(
+    user_extensions: Vec<Extension>,
+    node_compat: node_compat::NodeCompat,
+    allow_shared_array_buffer: bool,
+    commonjs: bool,
+    random_seed: Option<u64>,
+) -> Vec<Extension> {
+    let mut extensions = rustyscript::extensions(allow_shared_array_buffer, commonjs, random_seed);
 
     #[cfg(feature = "console")]
     extensions.extend(console::extensions());
@@ -43,6 +81,8 @@ pub fn all_extensions(user_extensions: Vec<Extension>) -> Vec<Extension> {
     #[cfg(feature = "crypto")]
     extensions.extend(crypto::extensions());
 
+    extensions.extend(node_compat::extensions(node_compat));
+
     extensions.extend(user_extensions);
     extensions
 }
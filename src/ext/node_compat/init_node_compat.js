@@ -0,0 +1,146 @@
+import { applyToGlobal, nonEnumerable } from "ext:rustyscript/rustyscript.js";
+
+const config = Deno.core.ops.op_node_compat();
+
+const BASE64 =
+  "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const toBase64 = (bytes) => {
+  let out = "";
+  for (let i = 0; i < bytes.length; i += 3) {
+    const n = (bytes[i] << 16) | ((bytes[i + 1] ?? 0) << 8) | (bytes[i + 2] ?? 0);
+    out += BASE64[(n >> 18) & 63] + BASE64[(n >> 12) & 63];
+    out += i + 1 < bytes.length ? BASE64[(n >> 6) & 63] : "=";
+    out += i + 2 < bytes.length ? BASE64[n & 63] : "=";
+  }
+  return out;
+};
+
+const fromBase64 = (str) => {
+  const clean = str
+    .replace(/-/g, "+")
+    .replace(/_/g, "/")
+    .replace(/[^A-Za-z0-9+/]/g, "");
+  const bytes = [];
+  for (let i = 0; i < clean.length; i += 4) {
+    const chunk = clean.slice(i, i + 4);
+    let n = 0;
+    for (let j = 0; j < 4; j++) {
+      n = (n << 6) | (j < chunk.length ? BASE64.indexOf(chunk[j]) : 0);
+    }
+    bytes.push((n >> 16) & 255);
+    if (chunk.length > 2) bytes.push((n >> 8) & 255);
+    if (chunk.length > 3) bytes.push(n & 255);
+  }
+  return new Uint8Array(bytes);
+};
+
+const toHex = (bytes) =>
+  Array.from(bytes, (b) => b.toString(16).padStart(2, "0")).join("");
+
+const fromHex = (str) => {
+  const bytes = [];
+  for (let i = 0; i + 1 < str.length; i += 2) {
+    const byte = parseInt(str.slice(i, i + 2), 16);
+    if (Number.isNaN(byte)) break;
+    bytes.push(byte);
+  }
+  return new Uint8Array(bytes);
+};
+
+const encode = (str, encoding) => {
+  switch (encoding.toLowerCase()) {
+    case "utf8":
+    case "utf-8":
+      return Deno.core.encode(str);
+    case "hex":
+      return fromHex(str);
+    case "base64":
+    case "base64url":
+      return fromBase64(str);
+    case "latin1":
+    case "binary":
+    case "ascii":
+      return Uint8Array.from(str, (c) => c.charCodeAt(0) & 255);
+    default:
+      throw new TypeError(`Unknown encoding: ${encoding}`);
+  }
+};
+
+const decode = (bytes, encoding) => {
+  switch (encoding.toLowerCase()) {
+    case "utf8":
+    case "utf-8":
+      return Deno.core.decode(bytes);
+    case "hex":
+      return toHex(bytes);
+    case "base64":
+      return toBase64(bytes);
+    case "base64url":
+      return toBase64(bytes).replace(/\+/g, "-").replace(/\//g, "_").replace(/=/g, "");
+    case "latin1":
+    case "binary":
+    case "ascii":
+      return String.fromCharCode(...bytes);
+    default:
+      throw new TypeError(`Unknown encoding: ${encoding}`);
+  }
+};
+
+class Buffer extends Uint8Array {
+  static from(value, encoding = "utf8") {
+    if (typeof value === "string") {
+      return new Buffer(encode(value, encoding));
+    }
+    return new Buffer(value);
+  }
+
+  static alloc(size, fill = 0) {
+    return new Buffer(size).fill(fill);
+  }
+
+  static isBuffer(value) {
+    return value instanceof Buffer;
+  }
+
+  static byteLength(value, encoding = "utf8") {
+    return typeof value === "string"
+      ? encode(value, encoding).length
+      : value.byteLength;
+  }
+
+  static concat(list) {
+    const result = Buffer.alloc(list.reduce((n, b) => n + b.length, 0));
+    let offset = 0;
+    for (const b of list) {
+      result.set(b, offset);
+      offset += b.length;
+    }
+    return result;
+  }
+
+  toString(encoding = "utf8") {
+    return decode(new Uint8Array(this.buffer, this.byteOffset, this.length), encoding);
+  }
+
+  equals(other) {
+    return this.length === other.length && this.every((b, i) => b === other[i]);
+  }
+}
+
+if (config.global_alias) {
+  applyToGlobal({ global: nonEnumerable(globalThis) });
+}
+
+if (config.process_env) {
+  applyToGlobal({
+    process: nonEnumerable({
+      env: { ...config.env },
+      argv: [],
+    }),
+  });
+}
+
+if (config.buffer) {
+  applyToGlobal({ Buffer: nonEnumerable(Buffer) });
+}
@@ -0,0 +1,41 @@
+use deno_core::{extension, op2, Extension, OpState};
+use std::collections::HashMap;
+
+/// Minimal shims for Node.js globals, so that more scripts can run unmodified
+/// Each shim is off by default
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct NodeCompat {
+    /// Provide a `process` object, whose `env` contains the variables in `env`
+    pub process_env: bool,
+
+    /// Provide a minimal `Buffer` polyfill, supporting utf8, hex, base64 and latin1
+    pub buffer: bool,
+
+    /// Alias `global` to `globalThis`
+    pub global_alias: bool,
+
+    /// The variables visible to scripts through `process.env`
+    /// The host's own environment is never exposed
+    pub env: HashMap<String, String>,
+}
+
+#[op2]
+#[serde]
+/// Returns the node compatibility settings for the runtime
+fn op_node_compat(state: &mut OpState) -> NodeCompat {
+    state.borrow::<NodeCompat>().clone()
+}
+
+extension!(
+    init_node_compat,
+    deps = [rustyscript],
+    ops = [op_node_compat],
+    esm_entry_point = "ext:init_node_compat/init_node_compat.js",
+    esm = [ dir "src/ext/node_compat", "init_node_compat.js" ],
+    options = { config: NodeCompat },
+    state = |state, config| state.put(config.config)
+);
+
+pub fn extensions(config: NodeCompat) -> Vec<Extension> {
+    vec![init_node_compat::init_ops_and_esm(config)]
+}
@@ -0,0 +1,32 @@
+// Deep-freezes the global object and every intrinsic reachable from it,
+// so that untrusted scripts cannot monkey-patch shared objects such as Array.prototype
+// See `RuntimeOptions::freeze_globals`
+(() => {
+  const seen = new WeakSet();
+  const pending = [globalThis];
+
+  while (pending.length) {
+    const value = pending.pop();
+    if (
+      (typeof value !== "object" && typeof value !== "function") ||
+      value === null ||
+      seen.has(value)
+    ) {
+      continue;
+    }
+    seen.add(value);
+
+    // Getters are not called, since they may have side effects -
+    // but the getter and setter functions themselves are frozen
+    for (const key of Reflect.ownKeys(value)) {
+      const descriptor = Reflect.getOwnPropertyDescriptor(value, key);
+      if (!descriptor) continue;
+      if ("value" in descriptor) pending.push(descriptor.value);
+      if (descriptor.get) pending.push(descriptor.get);
+      if (descriptor.set) pending.push(descriptor.set);
+    }
+    pending.push(Object.getPrototypeOf(value));
+
+    Object.freeze(value);
+  }
+})();
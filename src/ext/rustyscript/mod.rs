@@ -1,5 +1,18 @@
-use crate::error::Error;
-use deno_core::{extension, op2, v8, Extension, OpState};
+use crate::{
+    error::{Error, ErrorMapper, MappedError, Operation},
+    module_loader::RustyLoader,
+    EntrypointMetadata,
+};
+use deno_core::{anyhow, extension, op2, serde_json, v8, Extension, ModuleSpecifier, OpState};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc,
+    time::Instant,
+};
 
 #[op2]
 /// Registers a JS function with the runtime as being the entrypoint for the module
@@ -7,21 +20,713 @@ use deno_core::{extension, op2, v8, Extension, OpState};
 /// # Arguments
 /// * `state` - The runtime's state, into which the function will be put
 /// * `callback` - The function to register
+/// * `metadata` - Optional information about the entrypoint
 fn op_register_entrypoint(
     state: &mut OpState,
     #[global] callback: v8::Global<v8::Function>,
+    #[serde] metadata: Option<EntrypointMetadata>,
 ) -> Result<(), Error> {
     state.put(callback);
+    match metadata {
+        Some(metadata) => state.put(metadata),
+        None => {
+            state.try_take::<EntrypointMetadata>();
+        }
+    }
     Ok(())
 }
 
+/// Functions registered by name with `rustyscript.register_named`
+#[derive(Default)]
+pub struct NamedEntrypoints(pub HashMap<String, v8::Global<v8::Function>>);
+
+#[op2]
+/// Registers a JS function with the runtime as a named entrypoint for the module
+///
+/// # Arguments
+/// * `state` - The runtime's state, into which the function will be put
+/// * `name` - The name to register the function under
+/// * `callback` - The function to register
+fn op_register_named(
+    state: &mut OpState,
+    #[string] name: String,
+    #[global] callback: v8::Global<v8::Function>,
+) -> Result<(), Error> {
+    if !state.has::<NamedEntrypoints>() {
+        state.put(NamedEntrypoints::default());
+    }
+    state
+        .borrow_mut::<NamedEntrypoints>()
+        .0
+        .insert(name, callback);
+    Ok(())
+}
+
+/// Functions registered with `rustyscript.onCleanup`, to run when the current call ends
+#[derive(Default)]
+pub struct CleanupHooks(pub Vec<v8::Global<v8::Function>>);
+
+#[op2]
+/// Registers a JS function to run once the current call ends, even if it times out
+///
+/// # Arguments
+/// * `state` - The runtime's state, into which the function will be put
+/// * `callback` - The function to register
+fn op_register_cleanup(
+    state: &mut OpState,
+    #[global] callback: v8::Global<v8::Function>,
+) -> Result<(), Error> {
+    if !state.has::<CleanupHooks>() {
+        state.put(CleanupHooks::default());
+    }
+    state.borrow_mut::<CleanupHooks>().0.push(callback);
+    Ok(())
+}
+
+/// String-keyed data set by the host, readable from JS with `rustyscript.getData`
+#[derive(Default)]
+pub struct GlobalData(pub HashMap<String, serde_json::Value>);
+
+#[op2]
+#[serde]
+/// Returns the value the host stored under a key, or null if there is none
+fn op_get_global_data(state: &mut OpState, #[string] key: String) -> serde_json::Value {
+    state
+        .try_borrow::<GlobalData>()
+        .and_then(|data| data.0.get(&key).cloned())
+        .unwrap_or_default()
+}
+
+/// Functions computing the values of global getters, by property name
+/// See `Runtime::define_global_getter`
+#[derive(Default)]
+pub struct GlobalGetters(pub HashMap<String, Rc<dyn Fn() -> serde_json::Value>>);
+
+#[op2]
+#[serde]
+/// Computes the current value of a global getter, or null if there is none
+fn op_global_getter(state: &mut OpState, #[string] name: String) -> serde_json::Value {
+    let getter = state
+        .try_borrow::<GlobalGetters>()
+        .and_then(|getters| getters.0.get(&name).cloned());
+    getter.map(|f| f()).unwrap_or_default()
+}
+
+/// Output printed by scripts while capture is enabled
+/// See `Runtime::start_output_capture`
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CapturedOutput {
+    /// Everything printed to stdout, such as by `console.log`
+    pub stdout: String,
+
+    /// Everything printed to stderr, such as by `console.error`
+    pub stderr: String,
+}
+
+#[op2(fast)]
+/// Prints a message, or stores it if output is being captured
+///
+/// # Arguments
+/// * `state` - The runtime's state, which may contain a capture buffer
+/// * `message` - The message to print
+/// * `is_err` - True if the message is for stderr
+fn op_rustyscript_print(state: &mut OpState, #[string] message: &str, is_err: bool) {
+    match state.try_borrow_mut::<CapturedOutput>() {
+        Some(output) if is_err => output.stderr.push_str(message),
+        Some(output) => output.stdout.push_str(message),
+        None if is_err => {
+            std::io::stderr().write_all(message.as_bytes()).ok();
+        }
+        None => {
+            std::io::stdout().write_all(message.as_bytes()).ok();
+        }
+    }
+}
+
+/// Where values sent with `rustyscript.emit` go
+/// See `Runtime::take_emit_receiver`
+pub struct EmitSender(pub mpsc::Sender<serde_json::Value>);
+
+#[op2]
+/// Sends a value to the host, or discards it if the host is not listening
+///
+/// # Arguments
+/// * `state` - The runtime's state, which may contain a sender
+/// * `value` - The value to send
+fn op_emit(state: &mut OpState, #[serde] value: serde_json::Value) {
+    let listening = state
+        .try_borrow::<EmitSender>()
+        .is_some_and(|sender| sender.0.send(value).is_ok());
+    if !listening {
+        state.try_take::<EmitSender>();
+    }
+}
+
+/// Environment variables visible to scripts through `rustyscript.env`
+/// See `RuntimeOptions::env_vars`
+pub struct EnvVars(pub HashMap<String, String>);
+
+#[op2]
+#[serde]
+/// Returns the value of an injected environment variable, if it is set
+fn op_env_get(state: &mut OpState, #[string] name: String) -> Option<String> {
+    state
+        .try_borrow::<EnvVars>()
+        .and_then(|vars| vars.0.get(&name).cloned())
+}
+
+/// Converts errors returned by ops into JS exceptions
+/// See `RuntimeOptions::error_mapper`
+pub struct OpErrorMapper(pub ErrorMapper);
+
+/// The kinds of `Error` an op's error is rebuilt as for the runtime's mapper
+/// Only the class and message of an op error reach JS, so the class carries the kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ErrorKind {
+    ValueNotFound,
+    ValueNotCallable,
+    V8Encoding,
+    JsonDecode,
+    ModuleNotFound,
+    Timeout(Operation),
+    Cancelled(Operation),
+    Runtime,
+}
+
+impl ErrorKind {
+    /// The kind of an error - errors with no kind of their own are `Runtime`
+    fn of(e: &Error) -> Self {
+        match e {
+            Error::ValueNotFound(_) => Self::ValueNotFound,
+            Error::ValueNotCallable(_) => Self::ValueNotCallable,
+            Error::V8Encoding(_) => Self::V8Encoding,
+            Error::JsonDecode(_) => Self::JsonDecode,
+            Error::ModuleNotFound(_) => Self::ModuleNotFound,
+            Error::Timeout { operation, .. } => Self::Timeout(*operation),
+            Error::Cancelled(operation) => Self::Cancelled(*operation),
+            _ => Self::Runtime,
+        }
+    }
+
+    /// Build an error of this kind around its text
+    fn build(self, text: String) -> Error {
+        match self {
+            Self::ValueNotFound => Error::ValueNotFound(text),
+            Self::ValueNotCallable => Error::ValueNotCallable(text),
+            Self::V8Encoding => Error::V8Encoding(text),
+            Self::JsonDecode => Error::JsonDecode(text),
+            Self::ModuleNotFound => Error::ModuleNotFound(text),
+            Self::Timeout(operation) => Error::Timeout {
+                operation,
+                message: text,
+                elapsed: None,
+            },
+            Self::Cancelled(operation) => Error::Cancelled(operation),
+            Self::Runtime => Error::Runtime(text),
+        }
+    }
+
+    /// Rebuild an error of this kind from its message
+    /// The text around the error's own fields is found by formatting the kind itself,
+    /// so it always matches the message. Details that are not part of the message,
+    /// such as how long a timed out operation ran for, are not recovered
+    fn rebuild(self, message: String) -> Error {
+        const MARKER: &str = "\u{0}";
+        let template = self.build(MARKER.to_string()).to_string();
+        let text = match template.split_once(MARKER) {
+            Some((prefix, suffix)) => message
+                .strip_prefix(prefix)
+                .and_then(|text| text.strip_suffix(suffix)),
+            None => (template == message).then_some(""),
+        };
+        match text {
+            Some(text) => self.build(text.to_string()),
+            None => Error::Runtime(message),
+        }
+    }
+}
+
+/// Classes of the JS exceptions built from errors returned by ops, and the kind each stands for
+/// Kinds that carry an operation have a class for each one
+const MAPPED_ERROR_CLASSES: &[(&str, ErrorKind)] = &[
+    ("RustyscriptValueNotFound", ErrorKind::ValueNotFound),
+    ("RustyscriptValueNotCallable", ErrorKind::ValueNotCallable),
+    ("RustyscriptV8Encoding", ErrorKind::V8Encoding),
+    ("RustyscriptJsonDecode", ErrorKind::JsonDecode),
+    ("RustyscriptModuleNotFound", ErrorKind::ModuleNotFound),
+    (
+        "RustyscriptTimeoutLoadModule",
+        ErrorKind::Timeout(Operation::LoadModule),
+    ),
+    (
+        "RustyscriptTimeoutCallFunction",
+        ErrorKind::Timeout(Operation::CallFunction),
+    ),
+    (
+        "RustyscriptTimeoutGetValue",
+        ErrorKind::Timeout(Operation::GetValue),
+    ),
+    (
+        "RustyscriptTimeoutEval",
+        ErrorKind::Timeout(Operation::Eval),
+    ),
+    (
+        "RustyscriptTimeoutOther",
+        ErrorKind::Timeout(Operation::Other),
+    ),
+    (
+        "RustyscriptCancelledLoadModule",
+        ErrorKind::Cancelled(Operation::LoadModule),
+    ),
+    (
+        "RustyscriptCancelledCallFunction",
+        ErrorKind::Cancelled(Operation::CallFunction),
+    ),
+    (
+        "RustyscriptCancelledGetValue",
+        ErrorKind::Cancelled(Operation::GetValue),
+    ),
+    (
+        "RustyscriptCancelledEval",
+        ErrorKind::Cancelled(Operation::Eval),
+    ),
+    (
+        "RustyscriptCancelledOther",
+        ErrorKind::Cancelled(Operation::Other),
+    ),
+    ("RustyscriptRuntime", ErrorKind::Runtime),
+];
+
+/// Picks the JS class of the exception thrown when an op returns an error
+/// Each kind of `Error` has its own class, so that it can be rebuilt for the runtime's mapper
+pub fn get_error_class(e: &anyhow::Error) -> &'static str {
+    let Some(e) = e.downcast_ref::<Error>() else {
+        return "Error";
+    };
+    let kind = ErrorKind::of(e);
+    MAPPED_ERROR_CLASSES
+        .iter()
+        .find(|(_, k)| *k == kind)
+        .map_or("RustyscriptRuntime", |(class, _)| class)
+}
+
+/// Rebuild an op's error from the class and message of its exception
+fn rebuild_error(class: &str, message: String) -> Error {
+    match MAPPED_ERROR_CLASSES.iter().find(|(c, _)| *c == class) {
+        Some((_, kind)) => kind.rebuild(message),
+        None => Error::Runtime(message),
+    }
+}
+
+#[op2]
+#[serde]
+/// Returns the JS classes used for errors returned by ops
+fn op_mapped_error_classes() -> Vec<String> {
+    MAPPED_ERROR_CLASSES
+        .iter()
+        .map(|(class, _)| class.to_string())
+        .collect()
+}
+
+#[op2]
+#[serde]
+/// Returns the name and message of the exception to throw for an op error
+///
+/// # Arguments
+/// * `state` - The runtime's state, which may contain a mapper
+/// * `class` - The class picked for the error by `get_error_class`
+/// * `message` - The error's message, used if there is no mapper
+fn op_map_error(
+    state: &mut OpState,
+    #[string] class: String,
+    #[string] message: String,
+) -> MappedError {
+    match state.try_borrow::<OpErrorMapper>() {
+        Some(mapper) => (mapper.0)(&rebuild_error(&class, message)),
+        None => MappedError {
+            name: "Error".to_string(),
+            message,
+        },
+    }
+}
+
+/// Whether `require` is available to scripts
+/// See `RuntimeOptions::commonjs`
+pub struct CommonJs(pub bool);
+
+/// The loader `require` reads modules with
+pub struct CommonJsLoader(pub Rc<RustyLoader>);
+
+/// The runtime's loader, whose current directory file paths are resolved against
+/// See `Runtime::set_current_dir`
+pub struct RuntimeLoader(pub Rc<RustyLoader>);
+
+/// A module read for `require`
+#[derive(serde::Serialize)]
+struct RequiredModule {
+    url: String,
+    filename: String,
+    dirname: String,
+    source: String,
+}
+
+/// The values of `__filename` and `__dirname` for a CommonJS module
+fn commonjs_paths(url: &ModuleSpecifier) -> (String, String) {
+    match url.to_file_path() {
+        Ok(path) => (
+            path.display().to_string(),
+            path.parent()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+        ),
+        Err(_) => (url.to_string(), String::new()),
+    }
+}
+
+/// Wrap a CommonJS module in an ES module, whose default export is `module.exports`
+pub fn commonjs_module(url: &ModuleSpecifier, source: &str) -> Result<String, Error> {
+    let (filename, dirname) = commonjs_paths(url);
+    Ok(format!(
+        "export default globalThis[Symbol.for(\"rustyscript.commonjs\")]({}, {}, {}, function (exports, require, module, __filename, __dirname) {{\n{source}\n}});",
+        serde_json::to_string(url.as_str())?,
+        serde_json::to_string(&filename)?,
+        serde_json::to_string(&dirname)?,
+    ))
+}
+
+#[op2(fast)]
+/// Returns true if `require` should be installed
+fn op_commonjs_enabled(state: &mut OpState) -> bool {
+    state.try_borrow::<CommonJs>().is_some_and(|c| c.0)
+}
+
+#[op2]
+#[serde]
+/// Reads a module for `require`
+///
+/// # Arguments
+/// * `state` - The runtime's state, containing the loader
+/// * `specifier` - The path passed to `require`
+/// * `referrer` - The url of the requiring module, or an empty string
+fn op_require_source(
+    state: &mut OpState,
+    #[string] specifier: String,
+    #[string] referrer: String,
+) -> Result<RequiredModule, Error> {
+    let loader = state.try_borrow::<CommonJsLoader>().ok_or_else(|| {
+        Error::Runtime(
+            "CommonJS support is not enabled. See `RuntimeOptions::commonjs`".to_string(),
+        )
+    })?;
+
+    let (url, source) = loader.0.require_source(&specifier, &referrer)?;
+    let (filename, dirname) = commonjs_paths(&url);
+    Ok(RequiredModule {
+        url: url.to_string(),
+        filename,
+        dirname,
+        source,
+    })
+}
+
+/// Files and directories scripts may read with `rustyscript.readTextFile`
+/// See `RuntimeOptions::read_allowlist`
+#[derive(Clone, Default)]
+pub struct ReadAllowlist(pub Vec<PathBuf>);
+
+impl ReadAllowlist {
+    /// Returns true if the path is, or is inside, an allowed path
+    /// Relative allowed paths are resolved against `current_dir`
+    fn allows(&self, path: &Path, current_dir: &Path) -> bool {
+        self.0.iter().any(|allowed| {
+            let allowed = current_dir.join(allowed);
+            let allowed = std::fs::canonicalize(&allowed).unwrap_or(allowed);
+            path.starts_with(allowed)
+        })
+    }
+}
+
+#[op2(async)]
+#[string]
+/// Reads a text file, if the path is in the runtime's allowlist
+///
+/// # Arguments
+/// * `state` - The runtime's state, containing the allowlist
+/// * `path` - The file to read, relative to the runtime's current directory
+async fn op_read_text_file(
+    state: Rc<RefCell<OpState>>,
+    #[string] path: String,
+) -> Result<String, Error> {
+    let (allowlist, current_dir) = {
+        let state = state.borrow();
+        let allowlist = state
+            .try_borrow::<ReadAllowlist>()
+            .cloned()
+            .unwrap_or_default();
+        let current_dir = match state.try_borrow::<RuntimeLoader>() {
+            Some(loader) => loader.0.current_dir(),
+            None => std::env::current_dir().map_err(|e| Error::Runtime(e.to_string())),
+        };
+        (allowlist, current_dir?)
+    };
+    let denied = || Error::Runtime(format!("Permission denied: cannot read {path}"));
+
+    // Links and `..` are resolved first, so they cannot escape the allowlist
+    let requested = current_dir.join(&path);
+    let resolved = match tokio::fs::canonicalize(&requested).await {
+        Ok(resolved) => resolved,
+        Err(_) if !allowlist.allows(&requested, &current_dir) => return Err(denied()),
+        Err(e) => return Err(Error::Runtime(format!("{path}: {e}"))),
+    };
+    if !allowlist.allows(&resolved, &current_dir) {
+        return Err(denied());
+    }
+
+    tokio::fs::read_to_string(&resolved)
+        .await
+        .map_err(|e| Error::Runtime(format!("{path}: {e}")))
+}
+
+/// The locale used by `Intl` when a script does not specify one
+pub struct DefaultLocale(pub String);
+
+#[op2]
+#[serde]
+/// Returns the configured default locale, if any
+fn op_default_locale(state: &mut OpState) -> Option<String> {
+    state.try_borrow::<DefaultLocale>().map(|l| l.0.clone())
+}
+
+/// Whether scripts may use `SharedArrayBuffer`
+/// See `RuntimeOptions::allow_shared_array_buffer`
+pub struct AllowSharedArrayBuffer(pub bool);
+
+#[op2(fast)]
+/// Returns true if `SharedArrayBuffer` should be left available to scripts
+fn op_shared_array_buffer_allowed(state: &mut OpState) -> bool {
+    state
+        .try_borrow::<AllowSharedArrayBuffer>()
+        .is_some_and(|allow| allow.0)
+}
+
+/// The point at which the currently running task will time out
+/// `None` if the task has no time limit
+pub struct Deadline(pub Option<Instant>);
+
+#[op2(fast)]
+/// Returns the number of milliseconds left before the current task times out
+/// or `Infinity` if there is no time limit
+fn op_deadline_remaining(state: &mut OpState) -> f64 {
+    match state.try_borrow::<Deadline>().and_then(|d| d.0) {
+        Some(deadline) => {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs_f64()
+                * 1000.0
+        }
+        None => f64::INFINITY,
+    }
+}
+
+/// State of the generator behind a seeded `Math.random`
+/// See `RuntimeOptions::random_seed`
+pub struct SeededRandom(pub u64);
+
+impl SeededRandom {
+    /// Returns the next value in the sequence, in the range [0, 1)
+    /// This is splitmix64, which is fast and well distributed, but not cryptographically secure
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        // The top 53 bits fill the mantissa of a double exactly
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[op2(fast)]
+/// Returns true if `Math.random` should be replaced with a seeded generator
+fn op_random_seeded(state: &mut OpState) -> bool {
+    state.has::<SeededRandom>()
+}
+
+#[op2(fast)]
+/// Returns the next value from the seeded generator
+fn op_random(state: &mut OpState) -> f64 {
+    state
+        .try_borrow_mut::<SeededRandom>()
+        .map_or(0.0, SeededRandom::next_f64)
+}
+
+/// The moment the runtime was created, which `rustyscript.now()` counts from
+pub struct StartTime(pub Instant);
+
+#[op2(fast)]
+/// Returns the number of milliseconds since the runtime was created
+/// Unlike `Date.now()`, this clock is monotonic, and has sub-millisecond precision
+fn op_now(state: &mut OpState) -> f64 {
+    state
+        .try_borrow::<StartTime>()
+        .map_or(0.0, |start| start.0.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Application-level counters and gauges set by scripts
+#[derive(Default)]
+pub struct Metrics(pub HashMap<String, i64>);
+
+#[op2]
+/// Adds to a named counter, creating it if needed
+///
+/// # Arguments
+/// * `state` - The runtime's state, containing the metrics
+/// * `name` - The name of the counter
+/// * `by` - The amount to add to the counter, which must be an integer
+fn op_metrics_incr(state: &mut OpState, #[string] name: String, by: f64) -> Result<(), Error> {
+    let by = metric_value(&name, by)?;
+    if !state.has::<Metrics>() {
+        state.put(Metrics::default());
+    }
+    *state.borrow_mut::<Metrics>().0.entry(name).or_default() += by;
+    Ok(())
+}
+
+#[op2]
+/// Sets a named gauge to a value
+///
+/// # Arguments
+/// * `state` - The runtime's state, containing the metrics
+/// * `name` - The name of the gauge
+/// * `value` - The new value of the gauge, which must be an integer
+fn op_metrics_set(state: &mut OpState, #[string] name: String, value: f64) -> Result<(), Error> {
+    let value = metric_value(&name, value)?;
+    if !state.has::<Metrics>() {
+        state.put(Metrics::default());
+    }
+    state.borrow_mut::<Metrics>().0.insert(name, value);
+    Ok(())
+}
+
+/// Metrics are integers - any other number from JS is rejected,
+/// rather than being rounded or failing to deserialize
+fn metric_value(name: &str, value: f64) -> Result<i64, Error> {
+    const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+    if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER {
+        Ok(value as i64)
+    } else {
+        Err(Error::Runtime(format!(
+            "Metric '{name}' must be changed by a safe integer, got {value}"
+        )))
+    }
+}
+
+/// Information about the running version of rustyscript, readable from JS
+#[derive(serde::Serialize)]
+struct CrateInfo {
+    version: &'static str,
+    features: HashMap<&'static str, bool>,
+}
+
+#[op2]
+#[serde]
+/// Returns the crate version, and the state of each compile-time feature flag
+fn op_crate_info() -> CrateInfo {
+    CrateInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        features: HashMap::from([
+            ("console", cfg!(feature = "console")),
+            ("crypto", cfg!(feature = "crypto")),
+            ("url", cfg!(feature = "url")),
+            ("web", cfg!(feature = "web")),
+            ("webidl", cfg!(feature = "webidl")),
+            ("web_stub", cfg!(feature = "web_stub")),
+            ("fs_import", cfg!(feature = "fs_import")),
+            ("url_import", cfg!(feature = "url_import")),
+            ("timestamp", cfg!(feature = "timestamp")),
+        ]),
+    }
+}
+
 extension!(
     rustyscript,
-    ops = [op_register_entrypoint],
+    ops = [
+        op_register_entrypoint,
+        op_register_named,
+        op_register_cleanup,
+        op_get_global_data,
+        op_global_getter,
+        op_rustyscript_print,
+        op_emit,
+        op_env_get,
+        op_mapped_error_classes,
+        op_map_error,
+        op_commonjs_enabled,
+        op_require_source,
+        op_read_text_file,
+        op_crate_info,
+        op_default_locale,
+        op_shared_array_buffer_allowed,
+        op_deadline_remaining,
+        op_now,
+        op_random_seeded,
+        op_random,
+        op_metrics_incr,
+        op_metrics_set
+    ],
     esm_entry_point = "ext:rustyscript/rustyscript.js",
     esm = [ dir "src/ext/rustyscript", "rustyscript.js" ],
+    options = { allow_shared_array_buffer: bool, commonjs: bool, random_seed: Option<u64> },
+    state = |state, options| {
+        state.put(AllowSharedArrayBuffer(options.allow_shared_array_buffer));
+        state.put(CommonJs(options.commonjs));
+        if let Some(seed) = options.random_seed {
+            state.put(SeededRandom(seed));
+        }
+        state.put(StartTime(Instant::now()));
+    }
 );
 
-pub fn extensions() -> Vec<Extension> {
-    vec![rustyscript::init_ops_and_esm()]
+pub fn extensions(
+    allow_shared_array_buffer: bool,
+    commonjs: bool,
+    random_seed: Option<u64>,
+) -> Vec<Extension> {
+    vec![rustyscript::init_ops_and_esm(
+        allow_shared_array_buffer,
+        commonjs,
+        random_seed,
+    )]
+}
+
+#[cfg(test)]
+mod test_rustyscript_ext {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rebuild_error() {
+        let errors = [
+            Error::ValueNotFound("a is not a function".to_string()),
+            Error::ValueNotCallable("b".to_string()),
+            Error::V8Encoding("c".to_string()),
+            Error::JsonDecode("invalid type: value could not be deserialized: d".to_string()),
+            Error::ModuleNotFound("e.js".to_string()),
+            Error::Timeout {
+                operation: Operation::GetValue,
+                message: "f".to_string(),
+                elapsed: Some(Duration::from_millis(5)),
+            },
+            Error::Cancelled(Operation::Eval),
+            Error::Runtime("g".to_string()),
+        ];
+
+        for error in errors {
+            let class = get_error_class(&anyhow::Error::from(error.clone()));
+            let rebuilt = rebuild_error(class, error.to_string());
+            assert_eq!(ErrorKind::of(&error), ErrorKind::of(&rebuilt));
+            assert_eq!(error.to_string(), rebuilt.to_string());
+        }
+    }
 }
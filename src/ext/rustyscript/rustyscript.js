@@ -26,10 +26,184 @@ const getterOnly = (getter) => {
 const applyToGlobal = (properties) =>
   Object.defineProperties(globalThis, properties);
 
+const { version, features } = Deno.core.ops.op_crate_info();
+
 globalThis.rustyscript = {
-  register_entrypoint: (f) => Deno.core.ops.op_register_entrypoint(f),
+  register_entrypoint: (f, metadata) =>
+    Deno.core.ops.op_register_entrypoint(f, metadata),
+  register_named: (name, f) => Deno.core.ops.op_register_named(name, f),
+  onCleanup: (f) => Deno.core.ops.op_register_cleanup(f),
+  getData: (key) => Deno.core.ops.op_get_global_data(key),
+  emit: (value) => Deno.core.ops.op_emit(value),
+  readTextFile: (path) => Deno.core.ops.op_read_text_file(path),
+  env: Object.freeze({
+    get: (name) => Deno.core.ops.op_env_get(name) ?? undefined,
+  }),
+  deadlineRemainingMs: () => Deno.core.ops.op_deadline_remaining(),
+  now: () => Deno.core.ops.op_now(),
+  incr: (name, by = 1) => Deno.core.ops.op_metrics_incr(name, by),
+  gauge: (name, value) => Deno.core.ops.op_metrics_set(name, value),
+  version,
+  features: Object.freeze(features),
 };
 
 Object.freeze(globalThis.rustyscript);
 
+// Errors returned by ops are named by the runtime's error mapper, if it has one
+// Each kind of error has its own class, so the error can be rebuilt from its class and message
+for (const errorClass of Deno.core.ops.op_mapped_error_classes()) {
+  Deno.core.registerErrorClass(errorClass, function (message) {
+    const mapped = Deno.core.ops.op_map_error(errorClass, message);
+    const error = new Error(mapped.message);
+    error.name = mapped.name;
+    return error;
+  });
+}
+
+// Deterministic `Math.random`, if the runtime was given a seed
+if (Deno.core.ops.op_random_seeded()) {
+  Math.random = () => Deno.core.ops.op_random();
+}
+
+// CommonJS interop - `require` reads local modules through the module loader
+if (Deno.core.ops.op_commonjs_enabled()) {
+  const commonJsModules = new Map();
+
+  const evaluateCommonJs = (url, filename, dirname, factory) => {
+    if (commonJsModules.has(url)) {
+      return commonJsModules.get(url).exports;
+    }
+
+    const module = { exports: {}, id: filename, filename, loaded: false };
+    commonJsModules.set(url, module);
+    factory.call(
+      module.exports,
+      module.exports,
+      createRequire(url),
+      module,
+      filename,
+      dirname,
+    );
+    module.loaded = true;
+    return module.exports;
+  };
+
+  const createRequire = (referrer) => (specifier) => {
+    const { url, filename, dirname, source } = Deno.core.ops.op_require_source(
+      specifier,
+      referrer,
+    );
+    if (commonJsModules.has(url)) {
+      return commonJsModules.get(url).exports;
+    } else if (url.endsWith(".json")) {
+      commonJsModules.set(url, { exports: JSON.parse(source) });
+      return commonJsModules.get(url).exports;
+    }
+
+    const factory = new Function(
+      "exports",
+      "require",
+      "module",
+      "__filename",
+      "__dirname",
+      `${source}\n//# sourceURL=${url}`,
+    );
+    return evaluateCommonJs(url, filename, dirname, factory);
+  };
+
+  // ES modules share the global `require`, which resolves relative to the module calling it
+  // Frames are the error itself, `callerUrl`, `require`, and then the caller
+  const callerUrl = () => {
+    const frame = new Error().stack?.split("\n")[3] ?? "";
+    return frame.match(/\(?([a-z][\w+.-]*:[^\s()]*?):\d+:\d+\)?$/i)?.[1] ?? "";
+  };
+
+  Object.defineProperty(globalThis, "require", {
+    value: function require(specifier) {
+      return createRequire(callerUrl())(specifier);
+    },
+    writable: true,
+    enumerable: false,
+    configurable: true,
+  });
+  Object.defineProperty(globalThis, Symbol.for("rustyscript.commonjs"), {
+    value: evaluateCommonJs,
+  });
+}
+
+// Scripts cannot share memory between threads here, so unless the host allows
+// it, SharedArrayBuffer fails loudly when constructed instead of behaving unexpectedly
+// It is still a function, so feature detection and `instanceof` checks keep working
+if (!Deno.core.ops.op_shared_array_buffer_allowed()) {
+  const SharedArrayBuffer = function SharedArrayBuffer() {
+    throw new TypeError(
+      "SharedArrayBuffer is not available in this runtime. Enable it with `RuntimeOptions::allow_shared_array_buffer`",
+    );
+  };
+  Object.defineProperty(globalThis, "SharedArrayBuffer", {
+    value: SharedArrayBuffer,
+    writable: true,
+    enumerable: false,
+    configurable: true,
+  });
+}
+
+// Use the runtime's configured locale when a script does not provide one
+const withDefaultLocale = (locales) =>
+  locales === undefined
+    ? Deno.core.ops.op_default_locale() ?? undefined
+    : locales;
+
+if (globalThis.Intl) {
+  for (const name of [
+    "Collator",
+    "DateTimeFormat",
+    "ListFormat",
+    "NumberFormat",
+    "PluralRules",
+    "RelativeTimeFormat",
+    "Segmenter",
+  ]) {
+    const Original = Intl[name];
+    if (!Original) continue;
+
+    // Some of these may be called without `new`, which a class would not allow
+    const Localized = function (locales, options) {
+      const args = [withDefaultLocale(locales), options];
+      return new.target
+        ? Reflect.construct(Original, args, new.target)
+        : Original(...args);
+    };
+    Object.setPrototypeOf(Localized, Original);
+    Localized.prototype = Original.prototype;
+    Object.defineProperty(Localized, "name", { value: name });
+    Object.defineProperty(Intl, name, {
+      value: Localized,
+      writable: true,
+      enumerable: false,
+      configurable: true,
+    });
+  }
+
+  const localizeMethod = (proto, name, localeIndex) => {
+    const original = proto[name];
+    Object.defineProperty(proto, name, {
+      value: function (...args) {
+        args[localeIndex] = withDefaultLocale(args[localeIndex]);
+        return original.apply(this, args);
+      },
+      writable: true,
+      enumerable: false,
+      configurable: true,
+    });
+  };
+
+  localizeMethod(Number.prototype, "toLocaleString", 0);
+  localizeMethod(BigInt.prototype, "toLocaleString", 0);
+  localizeMethod(Date.prototype, "toLocaleString", 0);
+  localizeMethod(Date.prototype, "toLocaleDateString", 0);
+  localizeMethod(Date.prototype, "toLocaleTimeString", 0);
+  localizeMethod(String.prototype, "localeCompare", 1);
+}
+
 export { nonEnumerable, readOnly, writeable, getterOnly, applyToGlobal };
@@ -1,7 +1,38 @@
+use crate::Error;
 use deno_core::{extension, Extension};
 
-#[derive(Clone)]
-pub struct Permissions;
+#[derive(Clone, Default)]
+pub struct Permissions {
+    /// Hosts `fetch` may reach, or `None` to allow any host
+    /// See `RuntimeOptions::fetch_allowlist`
+    pub fetch_allowlist: Option<Vec<String>>,
+}
+
+impl Permissions {
+    /// Returns true if scripts may connect to the given host
+    /// Entries match the host, or its `host:port` if they include a port, ignoring case
+    fn allows_host(&self, host: &str, port: Option<u16>) -> bool {
+        let Some(allowlist) = &self.fetch_allowlist else {
+            return true;
+        };
+
+        let host_and_port = port.map(|port| format!("{host}:{port}"));
+        allowlist.iter().any(|allowed| {
+            allowed.eq_ignore_ascii_case(host)
+                || host_and_port
+                    .as_ref()
+                    .is_some_and(|h| allowed.eq_ignore_ascii_case(h))
+        })
+    }
+
+    /// Returns true if `fetch` may reach the given url
+    fn allows_fetch(&self, url: &deno_core::url::Url) -> bool {
+        match url.host_str() {
+            Some(host) => self.allows_host(host, url.port_or_known_default()),
+            None => self.fetch_allowlist.is_none(),
+        }
+    }
+}
 
 impl deno_web::TimersPermission for Permissions {
     fn allow_hrtime(&mut self) -> bool {
@@ -12,10 +43,18 @@ impl deno_web::TimersPermission for Permissions {
 impl deno_fetch::FetchPermissions for Permissions {
     fn check_net_url(
         &mut self,
-        _url: &deno_core::url::Url,
-        _api_name: &str,
+        url: &deno_core::url::Url,
+        api_name: &str,
     ) -> Result<(), deno_core::error::AnyError> {
-        Ok(())
+        if self.allows_fetch(url) {
+            Ok(())
+        } else {
+            Err(Error::Runtime(format!(
+                "Permission denied: {api_name} cannot reach {}",
+                url.host_str().unwrap_or(url.as_str())
+            ))
+            .into())
+        }
     }
 
     fn check_read(
@@ -30,10 +69,18 @@ impl deno_fetch::FetchPermissions for Permissions {
 impl deno_net::NetPermissions for Permissions {
     fn check_net<T: AsRef<str>>(
         &mut self,
-        _host: &(T, Option<u16>),
-        _api_name: &str,
+        host: &(T, Option<u16>),
+        api_name: &str,
     ) -> Result<(), deno_core::error::AnyError> {
-        Ok(())
+        if self.allows_host(host.0.as_ref(), host.1) {
+            Ok(())
+        } else {
+            Err(Error::Runtime(format!(
+                "Permission denied: {api_name} cannot reach {}",
+                host.0.as_ref()
+            ))
+            .into())
+        }
     }
 
     fn check_read(
@@ -58,7 +105,7 @@ extension!(
     deps = [rustyscript],
     esm_entry_point = "ext:init_web/init_web.js",
     esm = [ dir "src/ext/web", "init_web.js" ],
-    state = |state| state.put(Permissions{})
+    state = |state| state.put(Permissions::default())
 );
 
 extension!(
@@ -66,7 +113,7 @@ extension!(
     deps = [rustyscript],
     esm_entry_point = "ext:init_fetch/init_fetch.js",
     esm = [ dir "src/ext/web", "init_fetch.js" ],
-    state = |state| state.put(Permissions{})
+    state = |state| state.put(Permissions::default())
 );
 
 extension!(
@@ -86,3 +133,36 @@ pub fn extensions() -> Vec<Extension> {
         init_net::init_ops_and_esm(),
     ]
 }
+
+#[cfg(test)]
+mod test_web {
+    use super::*;
+
+    #[test]
+    fn test_allows_host() {
+        let permissions = Permissions {
+            fetch_allowlist: Some(vec![
+                "Example.com".to_string(),
+                "localhost:8080".to_string(),
+            ]),
+        };
+        assert!(permissions.allows_host("example.COM", None));
+        assert!(permissions.allows_host("LocalHost", Some(8080)));
+        assert!(!permissions.allows_host("localhost", Some(80)));
+        assert!(!permissions.allows_host("example.org", Some(443)));
+
+        let url = deno_core::url::Url::parse("https://example.com/").unwrap();
+        assert!(permissions.allows_fetch(&url));
+    }
+
+    #[test]
+    fn test_check_net() {
+        let mut permissions = Permissions {
+            fetch_allowlist: Some(vec!["127.0.0.1".to_string()]),
+        };
+        deno_net::NetPermissions::check_net(&mut permissions, &("127.0.0.1", Some(80)), "test")
+            .expect("Allowed host was denied");
+        deno_net::NetPermissions::check_net(&mut permissions, &("example.com", Some(80)), "test")
+            .expect_err("Disallowed host was allowed");
+    }
+}
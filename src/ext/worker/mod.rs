@@ -0,0 +1,33 @@
+use crate::error::Error;
+use deno_core::{extension, op2, serde_json, OpState};
+use std::sync::mpsc::Sender;
+
+/// The channel used by a worker to post messages back to its host
+pub struct WorkerSender(pub Sender<serde_json::Value>);
+
+#[op2]
+/// Posts a message from a worker back to the host
+///
+/// # Arguments
+/// * `state` - The runtime's state, containing the channel to the host
+/// * `message` - The message to send
+fn op_worker_post_message(
+    state: &mut OpState,
+    #[serde] message: serde_json::Value,
+) -> Result<(), Error> {
+    let sender = state
+        .try_borrow::<WorkerSender>()
+        .ok_or_else(|| Error::Runtime("postMessage is only available in workers".to_string()))?;
+    sender
+        .0
+        .send(message)
+        .map_err(|_| Error::Runtime("The worker's host has stopped listening".to_string()))
+}
+
+extension!(
+    worker,
+    deps = [rustyscript],
+    ops = [op_worker_post_message],
+    esm_entry_point = "ext:worker/worker.js",
+    esm = [ dir "src/ext/worker", "worker.js" ],
+);
@@ -0,0 +1,6 @@
+import { applyToGlobal, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
+applyToGlobal({
+    postMessage: nonEnumerable(
+        (message) => Deno.core.ops.op_worker_post_message(message)
+    ),
+});
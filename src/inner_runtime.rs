@@ -1,81 +1,686 @@
 use crate::{
-    ext,
+    argument_serializer::ArgumentSerializer,
+    cancellation::CancellationToken,
+    error::{default_stack_filter, ErrorMapper, Operation, SyntaxError},
+    ext::{
+        self,
+        node_compat::NodeCompat,
+        rustyscript::{
+            CapturedOutput, CleanupHooks, Deadline, GlobalData, GlobalGetters, Metrics,
+            NamedEntrypoints,
+        },
+    },
+    inspector::{InspectorOptions, InspectorServer},
     js_function::JsFunction,
     module_cache::ModuleCacheProvider,
-    module_loader::RustyLoader,
+    module_loader::{
+        DiagnosticCallback, ModuleLoadCallback, ModuleLoadEvent, RustyLoader, VirtualModuleSource,
+    },
+    platform,
+    profiler::CpuProfile,
     traits::{ToDefinedValue, ToModuleSpecifier, ToV8String},
-    transpiler, Error, Module, ModuleHandle,
+    transpiler::{self, EsTarget},
+    watchdog::Watchdog,
+    ArchiveModuleSource, EmbeddedModuleProvider, EntrypointMetadata, Error, JsValue, Module,
+    ModuleHandle, RustyExtension,
 };
-use deno_core::{serde_json, v8, Extension, JsRuntime, PollEventLoopOptions, RuntimeOptions};
-use std::{rc::Rc, time::Duration};
+use deno_core::{
+    anyhow, error::JsError, serde_json, v8, Extension, JsRuntime, LocalInspectorSession, ModuleId,
+    ModuleSpecifier, OpState, PollEventLoopOptions, RuntimeOptions,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc,
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+/// Time cleanup hooks may run for after a call, before they are interrupted
+const CLEANUP_BUDGET: Duration = Duration::from_millis(100);
 
 /// Type required to pass arguments to JsFunctions
 pub type FunctionArguments = [serde_json::Value];
 
+/// A function argument, which unlike a JSON value can also hold binary data
+/// See `Runtime::call_function_with_args`
+#[derive(Clone)]
+pub enum FunctionArgument {
+    /// Any JSON value
+    Json(serde_json::Value),
+
+    /// Binary data, delivered to JS as a `Uint8Array` - see `Runtime::arg_bytes`
+    /// The data is moved into the buffer when the argument is created, and never copied,
+    /// so every call the argument is passed to sees the same memory
+    Bytes(v8::SharedRef<v8::BackingStore>),
+}
+
+impl std::fmt::Debug for FunctionArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(value) => f.debug_tuple("Json").field(value).finish(),
+            Self::Bytes(store) => f.debug_tuple("Bytes").field(&store.byte_length()).finish(),
+        }
+    }
+}
+
+impl PartialEq for FunctionArgument {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Json(a), Self::Json(b)) => a == b,
+            (Self::Bytes(a), Self::Bytes(b)) => a.iter().map(Cell::get).eq(b.iter().map(Cell::get)),
+            _ => false,
+        }
+    }
+}
+
+impl From<serde_json::Value> for FunctionArgument {
+    fn from(value: serde_json::Value) -> Self {
+        Self::Json(value)
+    }
+}
+
+/// A saved copy of the enumerable own properties of `globalThis`
+/// See `Runtime::snapshot_globals`
+pub struct GlobalSnapshot {
+    names: HashSet<String>,
+    values: Vec<(v8::Global<v8::Value>, v8::Global<v8::Value>)>,
+}
+
+/// Returns the names of the enumerable own properties of `globalThis`
+fn global_property_names<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    global: v8::Local<'s, v8::Object>,
+) -> Vec<(String, v8::Local<'s, v8::Value>)> {
+    let args = v8::GetPropertyNamesArgs {
+        key_conversion: v8::KeyConversionMode::ConvertToString,
+        ..Default::default()
+    };
+    let Some(keys) = global.get_own_property_names(scope, args) else {
+        return vec![];
+    };
+
+    let mut names = vec![];
+    for i in 0..keys.length() {
+        if let Some(key) = keys.get_index(scope, i) {
+            names.push((key.to_rust_string_lossy(scope), key));
+        }
+    }
+    names
+}
+
+/// Converts a function argument into a JS value
+pub trait ToV8Argument {
+    /// Create the JS value passed to the function
+    fn to_v8_argument<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        argument_serializer: Option<&Rc<dyn ArgumentSerializer>>,
+    ) -> Result<v8::Local<'s, v8::Value>, Error>;
+}
+
+impl ToV8Argument for serde_json::Value {
+    fn to_v8_argument<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        argument_serializer: Option<&Rc<dyn ArgumentSerializer>>,
+    ) -> Result<v8::Local<'s, v8::Value>, Error> {
+        match argument_serializer.and_then(|s| s.serialize(scope, self)) {
+            Some(value) => Ok(value),
+            None => Ok(deno_core::serde_v8::to_v8(scope, self)?),
+        }
+    }
+}
+
+impl ToV8Argument for v8::Global<v8::Value> {
+    fn to_v8_argument<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        _argument_serializer: Option<&Rc<dyn ArgumentSerializer>>,
+    ) -> Result<v8::Local<'s, v8::Value>, Error> {
+        Ok(v8::Local::new(scope, self))
+    }
+}
+
+impl ToV8Argument for FunctionArgument {
+    fn to_v8_argument<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        argument_serializer: Option<&Rc<dyn ArgumentSerializer>>,
+    ) -> Result<v8::Local<'s, v8::Value>, Error> {
+        match self {
+            Self::Json(value) => value.to_v8_argument(scope, argument_serializer),
+            Self::Bytes(store) => {
+                // The buffer wraps the existing data, instead of copying it or going through JSON
+                let length = store.byte_length();
+                let buffer = v8::ArrayBuffer::with_backing_store(scope, store);
+                let array = v8::Uint8Array::new(scope, buffer, 0, length)
+                    .ok_or_else(|| Error::Runtime("could not create a Uint8Array".to_string()))?;
+                Ok(array.into())
+            }
+        }
+    }
+}
+
 /// Represents the set of options accepted by the runtime constructor
 pub struct InnerRuntimeOptions {
     /// A set of deno_core extensions to add to the runtime
     pub extensions: Vec<deno_core::Extension>,
 
+    /// Functions building additional extensions, called while the runtime is created
+    /// Useful for extensions that are expensive to build, or need state that
+    /// is only available once the runtime is being set up
+    pub extension_factories: Vec<Box<dyn FnOnce() -> Extension>>,
+
+    /// Extensions that can opt out of being transpiled, such as ops-only extensions
+    /// Unlike these, `extensions` and `extension_factories` are always transpiled
+    pub rusty_extensions: Vec<RustyExtension>,
+
     /// Function to use as entrypoint if the module does not provide one
     pub default_entrypoint: Option<String>,
 
     /// Amount of time to run for before killing the thread
     pub timeout: Duration,
 
+    /// Measure the timeout with the shared watchdog thread, instead of with tokio's timer
+    /// This is more accurate, especially under load. Scripts that never yield are
+    /// interrupted by the watchdog either way
+    pub precise_timeout: bool,
+
+    /// Allows running tasks to be cancelled from outside the runtime
+    /// Cancelled tasks fail with `Error::Cancelled`
+    pub cancel_token: Option<CancellationToken>,
+
     pub module_cache: Rc<dyn ModuleCacheProvider>,
+
+    /// Locale to use for `Intl` and `toLocaleString` calls that do not specify one
+    /// If not set, the default ICU locale is used
+    pub default_locale: Option<String>,
+
+    /// Used to hide stack frames from errors thrown by JS. Frames whose file
+    /// the function returns true for are removed from the stack
+    /// By default, frames from extensions and rustyscript internals are hidden
+    pub stack_filter: Option<Rc<dyn Fn(&str) -> bool>>,
+
+    /// A set of in-memory modules, by filename, that imports will be resolved against
+    /// before the filesystem or network. Does not require the `fs_import` feature
+    pub virtual_modules: HashMap<String, String>,
+
+    /// Shims for Node.js globals such as `process.env`, `Buffer` and `global`
+    /// Each shim is off by default
+    pub node_compat: NodeCompat,
+
+    /// Maximum number of side modules evaluated at once by `load_modules`
+    /// Side modules are still loaded in the order given, in batches of this size,
+    /// so a module must be listed after any side modules it imports
+    /// Values of 0 or 1 evaluate side modules one at a time
+    pub side_module_concurrency: usize,
+
+    /// Used to convert function arguments into richer JS values than JSON allows,
+    /// such as a real `Date`. See `TaggedArgumentSerializer`
+    pub argument_serializer: Option<Rc<dyn ArgumentSerializer>>,
+
+    /// Modules embedded in the binary, that imports will be resolved against
+    /// before the filesystem or network. See `EmbeddedModuleProvider`
+    pub embedded_modules: EmbeddedModuleProvider,
+
+    /// An archive of modules, that imports will be resolved against
+    /// before the filesystem or network. See `ArchiveModuleSource`
+    pub archive: Option<ArchiveModuleSource>,
+
+    /// Enables the V8 inspector, for debugging scripts with Chrome DevTools
+    pub inspector: Option<InspectorOptions>,
+
+    /// Enables `Runtime::start_cpu_profile`
+    /// Profiling uses the V8 inspector, which adds some overhead even when no profile is running
+    pub cpu_profiling: bool,
+
+    /// Leaves `SharedArrayBuffer` available to scripts
+    /// Scripts cannot share memory with other threads here, so by default
+    /// constructing a `SharedArrayBuffer` throws an error instead
+    pub allow_shared_array_buffer: bool,
+
+    /// Enables CommonJS interop - a global `require` for local modules, which are resolved
+    /// relative to the calling module and read through the module loader with the same
+    /// permissions as imports.
+    /// Modules with a `.cjs` extension are loaded with `module.exports` as their default export
+    pub commonjs: bool,
+
+    /// Replaces `Math.random` with a generator seeded with this value, so that every
+    /// runtime given the same seed produces the same sequence, like deno's `--seed` flag
+    /// The generator is not cryptographically secure, and `crypto` is not affected
+    pub random_seed: Option<u64>,
+
+    /// Store the main module's exports once its evaluation completes,
+    /// including any top-level `await` - see `ModuleHandle::evaluation_result`
+    pub capture_evaluation_result: bool,
+
+    /// Deep-freeze `globalThis`, and every object reachable from it such as `Array.prototype`,
+    /// once the runtime's extensions are loaded, so that untrusted scripts cannot modify them
+    /// Scripts can still declare their own variables, but cannot add new globals, and features
+    /// that add globals after startup, such as `Runtime::define_global_getter`, will fail
+    pub freeze_globals: bool,
+
+    /// Converts errors returned by ops into the name and message of the exception thrown in JS
+    /// For example, `Error::Timeout` could be thrown as a `TimeoutError`
+    /// Only errors of this crate's `Error` type are mapped - by default they are thrown as `Error`
+    /// The mapper sees an error of the same kind, with the same message, but details that are
+    /// not part of the message, such as a timeout's `elapsed`, are not available
+    /// Kinds without a variant of their own, such as `Error::JsError`, arrive as `Error::Runtime`
+    pub error_mapper: Option<ErrorMapper>,
+
+    /// Environment variables scripts can read with `rustyscript.env.get(name)`
+    /// Only these variables are visible - the host's own environment is never exposed
+    pub env_vars: HashMap<String, String>,
+
+    /// Files, and directories, whose contents scripts may read with
+    /// `rustyscript.readTextFile(path)`. Reads of any other path are rejected
+    /// Relative paths, here and in scripts, are resolved against the runtime's current
+    /// directory - see `Runtime::set_current_dir`
+    /// This is separate from module imports, which are not affected
+    pub read_allowlist: Vec<PathBuf>,
+
+    /// Directory that module filenames and root imports are resolved against
+    /// If not set, the process's current working directory is used
+    pub base_dir: Option<PathBuf>,
+
+    /// Flags passed to V8, such as `--expose-gc` or `--max-old-space-size=512`
+    /// Flags are process-global, and must be set by the first runtime created in the process -
+    /// setting them afterwards, or setting a different set of flags, is an error
+    pub v8_flags: Vec<String>,
+
+    /// Maximum size of the JS call stack, in bytes. Recursion past it throws a catchable
+    /// `RangeError`, which is returned as an error, instead of overflowing the thread's stack.
+    /// Keep it below the stack size of the thread the runtime runs on
+    ///
+    /// The limit applies only to this runtime's isolate, and is measured again from the
+    /// point on the thread's stack where each call into the runtime is made
+    pub max_stack_size: Option<usize>,
+
+    /// Hosts scripts may reach with `fetch` or network ops, such as `api.example.com` or
+    /// `localhost:8080`, compared without regard to case
+    /// Requests and connections to any other host are rejected with a permission error
+    /// If not set, scripts can reach any host
+    #[cfg(feature = "web")]
+    pub fetch_allowlist: Option<Vec<String>>,
+
+    /// Called for each module as it is loaded, with its specifier, size, how long it
+    /// took to read and transpile, and whether it was served by the module cache
+    pub on_module_load: Option<ModuleLoadCallback>,
+
+    /// Warn about syntax in each module, after transpiling, that is newer than this version
+    /// Transpiling does not downlevel syntax, so such code is still loaded as-is
+    /// Warnings are sent to `on_diagnostic`, and nothing is checked without it
+    pub transpile_target: Option<EsTarget>,
+
+    /// Called with each warning found in a module as it is loaded, such as
+    /// syntax newer than `transpile_target`
+    pub on_diagnostic: Option<DiagnosticCallback>,
+
+    /// Fields added to the `import.meta` object of every module, such as a build id
+    /// Fields provided by the runtime, like `import.meta.url`, can be overridden
+    pub import_meta: HashMap<String, serde_json::Value>,
+
+    /// Client used to fetch modules imported from URLs, such as one configured
+    /// with a proxy or client certificates. A shared default client is used if not set
+    #[cfg(feature = "url_import")]
+    pub http_client: Option<reqwest::Client>,
 }
 
 impl Default for InnerRuntimeOptions {
     fn default() -> Self {
         Self {
             extensions: Default::default(),
+            extension_factories: Default::default(),
+            rusty_extensions: Default::default(),
             default_entrypoint: Default::default(),
             timeout: Duration::MAX,
+            precise_timeout: false,
+            cancel_token: None,
             module_cache: Rc::new(()),
+            default_locale: None,
+            stack_filter: Some(Rc::new(default_stack_filter)),
+            virtual_modules: Default::default(),
+            node_compat: Default::default(),
+            side_module_concurrency: 1,
+            argument_serializer: None,
+            embedded_modules: Default::default(),
+            archive: None,
+            inspector: None,
+            cpu_profiling: false,
+            allow_shared_array_buffer: false,
+            commonjs: false,
+            random_seed: None,
+            capture_evaluation_result: false,
+            freeze_globals: false,
+            error_mapper: None,
+            env_vars: Default::default(),
+            read_allowlist: Default::default(),
+            base_dir: None,
+            v8_flags: Vec::new(),
+            max_stack_size: None,
+
+            #[cfg(feature = "web")]
+            fetch_allowlist: None,
+
+            on_module_load: None,
+            transpile_target: None,
+            on_diagnostic: None,
+            import_meta: HashMap::new(),
+
+            #[cfg(feature = "url_import")]
+            http_client: None,
+        }
+    }
+}
+
+impl InnerRuntimeOptions {
+    /// Check that the options make sense together
+    ///
+    /// # Returns
+    /// A `Result` containing nothing, or an error if the options conflict:
+    /// - A precise timeout without a timeout
+    /// - The inspector with a timeout, which would stop scripts paused in the debugger
+    /// - A maximum stack size alongside V8's `--stack-size` flag
+    pub fn validate(&self) -> Result<(), Error> {
+        let has_timeout = self.timeout != Duration::MAX;
+        if self.precise_timeout && !has_timeout {
+            return Err(Error::Runtime(
+                "A precise timeout requires a timeout to be set".to_string(),
+            ));
+        }
+
+        if self.inspector.is_some() && has_timeout {
+            return Err(Error::Runtime(
+                "The inspector cannot be used with a timeout, which would stop scripts paused in the debugger".to_string(),
+            ));
+        }
+
+        let has_stack_flag = self
+            .v8_flags
+            .iter()
+            .any(|flag| flag.starts_with("--stack-size") || flag.starts_with("--stack_size"));
+        if self.max_stack_size.is_some() && has_stack_flag {
+            return Err(Error::Runtime(
+                "A maximum stack size cannot be combined with V8's --stack-size flag".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Apply the configured stack filter to an error
+    pub(crate) fn filter_error(&self, e: Error) -> Error {
+        match &self.stack_filter {
+            Some(filter) => e.filter_stack(filter.as_ref()),
+            None => e,
         }
     }
 }
 
+/// How a task run by `InnerRuntime::run_async_task` is timed out or cancelled
+struct TaskContext {
+    timeout: Duration,
+    precise_timeout: bool,
+    cancel_token: Option<CancellationToken>,
+    op_state: Rc<RefCell<OpState>>,
+    isolate: v8::IsolateHandle,
+}
+
 /// Deno JsRuntime wrapper providing helper functions needed
 /// by the public-facing Runtime API
 pub struct InnerRuntime {
     pub deno_runtime: JsRuntime,
     pub options: InnerRuntimeOptions,
+    module_loader: Rc<RustyLoader>,
+    inspector_server: Option<InspectorServer>,
+    profiler_session: Option<LocalInspectorSession>,
+    module_versions: HashMap<ModuleSpecifier, usize>,
+    op_names: Vec<String>,
 }
 
 impl InnerRuntime {
-    pub fn new(options: InnerRuntimeOptions) -> Self {
-        Self {
-            deno_runtime: JsRuntime::new(RuntimeOptions {
-                extensions: InnerRuntime::all_extensions(options.extensions),
-                module_loader: Some(Rc::new(RustyLoader::new(options.module_cache))),
-                ..Default::default()
-            }),
+    pub fn new(options: InnerRuntimeOptions) -> Result<Self, Error> {
+        options.validate()?;
+
+        // Creating a runtime sets up a default platform if none exists yet
+        platform::ensure_initialized();
+
+        let mut modules = VirtualModuleSource::new(options.virtual_modules)
+            .with_embedded(&options.embedded_modules);
+        if let Some(archive) = &options.archive {
+            modules = modules.with_archive(archive);
+        }
+        let module_loader = RustyLoader::new(options.module_cache, modules);
+
+        let module_loader = match options.base_dir {
+            Some(base_dir) => module_loader.with_base_dir(base_dir),
+            None => module_loader,
+        };
+
+        let module_loader = module_loader
+            .with_import_meta(&options.import_meta)
+            .with_commonjs(options.commonjs);
+
+        let module_loader = match (options.transpile_target, options.on_diagnostic) {
+            (Some(target), Some(on_diagnostic)) => {
+                module_loader.with_target_check(target, on_diagnostic)
+            }
+            _ => module_loader,
+        };
+
+        let module_loader = match options.on_module_load {
+            Some(on_load) => module_loader.with_load_callback(on_load),
+            None => module_loader,
+        };
+
+        #[cfg(feature = "url_import")]
+        let module_loader = match options.http_client {
+            Some(client) => module_loader.with_http_client(client),
+            None => module_loader,
+        };
+        let module_loader = Rc::new(module_loader);
+
+        let extensions = InnerRuntime::all_extensions(
+            options.extensions,
+            options.extension_factories,
+            options.rusty_extensions,
+            options.node_compat,
+            options.allow_shared_array_buffer,
+            options.commonjs,
+            options.random_seed,
+        )?;
+
+        let mut deno_runtime = JsRuntime::new(RuntimeOptions {
+            extensions,
+            module_loader: Some(module_loader.clone()),
+            inspector: options.inspector.is_some() || options.cpu_profiling,
+            is_main: options.inspector.is_some(),
+            get_error_class_fn: Some(&ext::rustyscript::get_error_class),
+            ..Default::default()
+        });
+
+        // Every registered op is visible to JS, including deno_core's built-in ones
+        let op_names = {
+            let names = deno_runtime
+                .execute_script("ext:rustyscript/op_names.js", "Object.keys(Deno.core.ops)")?;
+            let mut scope = deno_runtime.handle_scope();
+            let names = v8::Local::new(&mut scope, names);
+            deno_core::serde_v8::from_v8::<Vec<String>>(&mut scope, names)?
+        };
+
+        if options.freeze_globals {
+            deno_runtime.execute_script(
+                "ext:rustyscript/freeze_globals.js",
+                include_str!("ext/rustyscript/freeze_globals.js"),
+            )?;
+        }
+
+        deno_runtime
+            .op_state()
+            .borrow_mut()
+            .put(ext::rustyscript::EnvVars(options.env_vars));
+        deno_runtime
+            .op_state()
+            .borrow_mut()
+            .put(ext::rustyscript::ReadAllowlist(options.read_allowlist));
+        deno_runtime
+            .op_state()
+            .borrow_mut()
+            .put(ext::rustyscript::RuntimeLoader(module_loader.clone()));
+
+        #[cfg(feature = "web")]
+        if options.fetch_allowlist.is_some() {
+            deno_runtime
+                .op_state()
+                .borrow_mut()
+                .put(ext::web::Permissions {
+                    fetch_allowlist: options.fetch_allowlist,
+                });
+        }
+
+        if options.commonjs {
+            deno_runtime
+                .op_state()
+                .borrow_mut()
+                .put(ext::rustyscript::CommonJsLoader(module_loader.clone()));
+        }
+
+        if let Some(mapper) = options.error_mapper {
+            deno_runtime
+                .op_state()
+                .borrow_mut()
+                .put(ext::rustyscript::OpErrorMapper(mapper));
+        }
+
+        if let Some(locale) = options.default_locale {
+            deno_runtime
+                .op_state()
+                .borrow_mut()
+                .put(ext::rustyscript::DefaultLocale(locale));
+        }
+
+        Ok(Self {
+            deno_runtime,
             options: InnerRuntimeOptions {
                 timeout: options.timeout,
+                precise_timeout: options.precise_timeout,
+                cancel_token: options.cancel_token,
                 default_entrypoint: options.default_entrypoint,
+                stack_filter: options.stack_filter,
+                side_module_concurrency: options.side_module_concurrency,
+                argument_serializer: options.argument_serializer,
+                inspector: options.inspector,
+                cpu_profiling: options.cpu_profiling,
+                capture_evaluation_result: options.capture_evaluation_result,
+                commonjs: options.commonjs,
                 ..Default::default()
             },
+            module_loader,
+            inspector_server: None,
+            profiler_session: None,
+            module_versions: HashMap::new(),
+            op_names,
+        })
+    }
+
+    /// The error for a module without an entrypoint, listing the default entrypoints tried
+    pub fn missing_entrypoint(&self, module: &Module) -> Error {
+        Error::MissingEntrypoint {
+            module: module.clone(),
+            attempted: self.options.default_entrypoint.iter().cloned().collect(),
         }
     }
 
+    /// The names of the ops registered by the runtime's extensions
+    pub fn op_names(&self) -> &[String] {
+        &self.op_names
+    }
+
+    /// Start serving the inspector protocol, if the inspector is enabled
+    pub fn start_inspector(&mut self) -> Result<(), Error> {
+        if let Some(inspector) = self.options.inspector {
+            let session_sender = self.deno_runtime.inspector().borrow().get_session_sender();
+            self.inspector_server =
+                Some(InspectorServer::start(inspector.address, session_sender)?);
+        }
+        Ok(())
+    }
+
+    /// Returns the address the inspector is listening on, if it is running
+    pub fn inspector_address(&self) -> Option<SocketAddr> {
+        self.inspector_server.as_ref().map(InspectorServer::address)
+    }
+
     ///
     /// Add up all required extensions
-    fn all_extensions(user_extensions: Vec<Extension>) -> Vec<Extension> {
-        let mut extensions = ext::all_extensions(user_extensions);
-
-        // Transpilation step
-        for extension in &mut extensions {
-            for source in extension.esm_files.to_mut() {
-                transpiler::transpile_extension(source).expect("could not load extension");
-            }
-            for source in extension.js_files.to_mut() {
-                transpiler::transpile_extension(source).expect("could not load extension");
+    fn all_extensions(
+        user_extensions: Vec<Extension>,
+        extension_factories: Vec<Box<dyn FnOnce() -> Extension>>,
+        rusty_extensions: Vec<RustyExtension>,
+        node_compat: NodeCompat,
+        allow_shared_array_buffer: bool,
+        commonjs: bool,
+        random_seed: Option<u64>,
+    ) -> Result<Vec<Extension>, Error> {
+        let rusty_extensions = user_extensions
+            .into_iter()
+            .chain(extension_factories.into_iter().map(|factory| factory()))
+            .map(RustyExtension::from)
+            .chain(rusty_extensions);
+
+        let mut user_extensions = Vec::new();
+        for RustyExtension {
+            mut extension,
+            transpile,
+        } in rusty_extensions
+        {
+            // Transpilation step
+            if transpile {
+                Self::transpile_extension(&mut extension).map_err(|e| Error::Extension {
+                    name: extension.name.to_string(),
+                    source: Box::new(e),
+                })?;
             }
+            user_extensions.push(extension);
         }
 
-        extensions
+        // Our own extensions are plain JS, and are never transpiled
+        Ok(ext::all_extensions(
+            user_extensions,
+            node_compat,
+            allow_shared_array_buffer,
+            commonjs,
+            random_seed,
+        ))
+    }
+
+    /// Transpile each of an extension's ESM and JS sources
+    fn transpile_extension(extension: &mut Extension) -> Result<(), Error> {
+        for source in extension.esm_files.to_mut() {
+            transpiler::transpile_extension(source)?;
+        }
+        for source in extension.js_files.to_mut() {
+            transpiler::transpile_extension(source)?;
+        }
+        Ok(())
+    }
+
+    /// Measure the stack limit from the current position on the thread's stack
+    /// Called before entering V8, so that the limit does not depend on how deep
+    /// the stack was when the runtime was created
+    fn update_stack_limit(&mut self) {
+        if let Some(size) = self.options.max_stack_size {
+            // V8 takes the lowest address the stack may grow down to
+            let here = 0u8;
+            let here = std::hint::black_box(std::ptr::addr_of!(here)) as usize;
+            self.deno_runtime
+                .v8_isolate()
+                .set_stack_limit(here.saturating_sub(size));
+        }
     }
 
     /// Access the underlying deno runtime instance directly
@@ -111,6 +716,247 @@ impl InnerRuntime {
         Ok(())
     }
 
+    /// Store a value that JS can read with `rustyscript.getData(key)`
+    pub fn set_global_data(&mut self, key: &str, value: serde_json::Value) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        if !state.has::<GlobalData>() {
+            state.put(GlobalData::default());
+        }
+        state
+            .borrow_mut::<GlobalData>()
+            .0
+            .insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Define a read-only property on the global object, whose value
+    /// is computed by calling `f` each time it is read
+    pub fn define_global_getter(
+        &mut self,
+        name: &str,
+        f: Rc<dyn Fn() -> serde_json::Value>,
+    ) -> Result<(), Error> {
+        {
+            let state = self.deno_runtime().op_state();
+            let mut state = state.try_borrow_mut()?;
+            if !state.has::<GlobalGetters>() {
+                state.put(GlobalGetters::default());
+            }
+            state
+                .borrow_mut::<GlobalGetters>()
+                .0
+                .insert(name.to_string(), f);
+        }
+
+        let name = serde_json::to_string(name)?;
+        self.eval::<serde_json::Value>(&format!(
+            "Object.defineProperty(globalThis, {name}, {{
+                get: () => Deno.core.ops.op_global_getter({name}),
+                enumerable: true,
+                configurable: true,
+            }}); undefined"
+        ))?;
+        Ok(())
+    }
+
+    /// Start capturing printed output, discarding anything captured so far
+    pub fn start_output_capture(&mut self) -> Result<(), Error> {
+        self.put(CapturedOutput::default())
+    }
+
+    /// Stop capturing printed output, and return what was captured
+    pub fn take_output(&mut self) -> CapturedOutput {
+        self.take::<CapturedOutput>().unwrap_or_default()
+    }
+
+    /// Change the directory module filenames and root imports are resolved against
+    pub fn set_current_dir(&mut self, path: &Path) -> Result<(), Error> {
+        let path = std::env::current_dir()?.join(path);
+        if !path.is_dir() {
+            return Err(Error::Runtime(format!(
+                "{} is not a directory",
+                path.display()
+            )));
+        }
+        self.module_loader.set_base_dir(path);
+        Ok(())
+    }
+
+    /// Start listening for values sent with `rustyscript.emit`
+    /// Any previous receiver stops receiving values
+    pub fn take_emit_receiver(&mut self) -> Result<mpsc::Receiver<serde_json::Value>, Error> {
+        let (sender, receiver) = mpsc::channel();
+        self.put(ext::rustyscript::EmitSender(sender))?;
+        Ok(receiver)
+    }
+
+    /// The specifier a module is loaded under
+    /// Reloaded modules get a new specifier each time, since V8 cannot replace a module
+    fn module_specifier(&self, module: &Module) -> Result<ModuleSpecifier, Error> {
+        let mut specifier = self.module_loader.resolve_path(module.filename())?;
+        if let Some(version) = self.module_versions.get(&specifier) {
+            specifier.set_query(Some(&format!("reload={version}")));
+        }
+        Ok(specifier)
+    }
+
+    /// Load a new version of a module that was already loaded
+    pub async fn reload_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        let specifier = self.module_loader.resolve_path(module.filename())?;
+        *self.module_versions.entry(specifier).or_default() += 1;
+        self.load_modules(None, vec![module]).await
+    }
+
+    /// Every module the given module imports, directly or indirectly
+    pub fn module_dependencies(&self, module_context: &ModuleHandle) -> Vec<ModuleSpecifier> {
+        match self.module_specifier(module_context.module()) {
+            Ok(specifier) => self.module_loader.dependencies(&specifier),
+            Err(_) => vec![],
+        }
+    }
+
+    /// Translate a 1-based position in a loaded module's transpiled code
+    /// back to a 1-based position in its original source
+    pub fn original_position(&self, module: &Module, line: u32, column: u32) -> Option<(u32, u32)> {
+        let specifier = self.module_specifier(module).ok()?;
+        self.module_loader
+            .original_position(&specifier, line, column)
+    }
+
+    /// Start recording a CPU profile, using a local inspector session
+    pub async fn start_cpu_profile(&mut self) -> Result<(), Error> {
+        if !self.options.cpu_profiling {
+            return Err(Error::Runtime(
+                "CPU profiling is not enabled. See `RuntimeOptions::cpu_profiling`".to_string(),
+            ));
+        } else if self.profiler_session.is_some() {
+            return Err(Error::Runtime(
+                "A CPU profile is already running".to_string(),
+            ));
+        }
+
+        let mut session = self
+            .deno_runtime
+            .inspector()
+            .borrow()
+            .create_local_session();
+        let future = async {
+            session.post_message::<()>("Profiler.enable", None).await?;
+            session.post_message::<()>("Profiler.start", None).await
+        };
+
+        // The session's messages are only processed while the event loop is polled
+        self.deno_runtime
+            .with_event_loop_future(Box::pin(future), Default::default())
+            .await?;
+
+        self.profiler_session = Some(session);
+        Ok(())
+    }
+
+    /// Stop recording the running CPU profile, and return it
+    pub async fn stop_cpu_profile(&mut self) -> Result<CpuProfile, Error> {
+        let mut session = self
+            .profiler_session
+            .take()
+            .ok_or_else(|| Error::Runtime("No CPU profile is running".to_string()))?;
+
+        let future = async move {
+            let result = session.post_message::<()>("Profiler.stop", None).await?;
+            session.post_message::<()>("Profiler.disable", None).await?;
+            Ok::<_, anyhow::Error>(result)
+        };
+        let mut result = self
+            .deno_runtime
+            .with_event_loop_future(Box::pin(future), Default::default())
+            .await?;
+
+        Ok(serde_json::from_value(result["profile"].take())?)
+    }
+
+    /// Returns the current value of every counter and gauge set by scripts
+    pub fn metrics_snapshot(&mut self) -> HashMap<String, i64> {
+        let state = self.deno_runtime().op_state();
+        let state = state.borrow();
+        state
+            .try_borrow::<Metrics>()
+            .map(|metrics| metrics.0.clone())
+            .unwrap_or_default()
+    }
+
+    /// Save the current value of each enumerable own property of `globalThis`
+    pub fn snapshot_globals(&mut self) -> GlobalSnapshot {
+        let context = self.deno_runtime.main_context();
+        let mut scope = self.deno_runtime.handle_scope();
+        let global = context.open(&mut scope).global(&mut scope);
+
+        let mut snapshot = GlobalSnapshot {
+            names: HashSet::new(),
+            values: vec![],
+        };
+        for (name, key) in global_property_names(&mut scope, global) {
+            if let Some(value) = global.get(&mut scope, key) {
+                snapshot.names.insert(name);
+                snapshot.values.push((
+                    v8::Global::new(&mut scope, key),
+                    v8::Global::new(&mut scope, value),
+                ));
+            }
+        }
+        snapshot
+    }
+
+    /// Return `globalThis` to the state saved by `snapshot_globals`
+    /// Properties added since are deleted, and changed properties are set back
+    pub fn restore_globals(&mut self, snapshot: &GlobalSnapshot) -> Result<(), Error> {
+        let context = self.deno_runtime.main_context();
+        let mut scope = self.deno_runtime.handle_scope();
+        let global = context.open(&mut scope).global(&mut scope);
+
+        for (name, key) in global_property_names(&mut scope, global) {
+            if !snapshot.names.contains(&name) && global.delete(&mut scope, key) != Some(true) {
+                return Err(Error::Runtime(format!(
+                    "could not delete global property {name}"
+                )));
+            }
+        }
+
+        for (key, value) in &snapshot.values {
+            let key = v8::Local::new(&mut scope, key);
+            let value = v8::Local::new(&mut scope, value);
+            let unchanged = global
+                .get(&mut scope, key)
+                .is_some_and(|current| current.strict_equals(value));
+            if !unchanged {
+                global.set(&mut scope, key, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Perform a single iteration of the event loop
+    ///
+    /// # Returns
+    /// A `Result` containing true if the event loop still has pending work,
+    /// or an error (`Error`) if a task failed
+    pub fn step(&mut self) -> Result<bool, Error> {
+        self.update_stack_limit();
+        let waker = deno_core::futures::task::noop_waker();
+        let mut cx = std::task::Context::from_waker(&waker);
+        match self
+            .deno_runtime
+            .poll_event_loop(&mut cx, PollEventLoopOptions::default())
+        {
+            Poll::Ready(result) => {
+                result?;
+                Ok(false)
+            }
+            Poll::Pending => Ok(true),
+        }
+    }
+
     /// Get a value from a runtime instance
     ///
     /// # Arguments
@@ -132,7 +978,53 @@ impl InnerRuntime {
         let value = self.get_value_ref_async(module_context, name).await?;
         let mut scope = self.deno_runtime.handle_scope();
         let value = v8::Local::<v8::Value>::new(&mut scope, value);
-        Ok(deno_core::serde_v8::from_v8(&mut scope, value)?)
+        Ok(crate::js_collections::from_v8(&mut scope, value)?)
+    }
+
+    /// Get a value as a `JsValue`, read directly since serde cannot tell undefined from null
+    /// Unlike other types, exports that are declared but `null` or `undefined` are found
+    pub async fn get_js_value(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<JsValue, Error> {
+        let value = match self.get_value_ref_async(module_context, name).await {
+            Ok(value) => value,
+            Err(Error::ValueNotFound(_)) => {
+                return self.get_declared_export(module_context, name);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let value = v8::Local::<v8::Value>::new(&mut scope, value);
+        JsValue::from_v8(&mut scope, value)
+    }
+
+    /// Get an export of a module, even if its value is `null` or `undefined`
+    fn get_declared_export(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<JsValue, Error> {
+        let module_namespace = self
+            .deno_runtime
+            .get_module_namespace(module_context.id())?;
+        let mut scope = self.deno_runtime.handle_scope();
+        let module_namespace = module_namespace.open(&mut scope);
+
+        let key = name.to_v8_string(&mut scope)?;
+        if !module_namespace
+            .has_own_property(&mut scope, key.into())
+            .unwrap_or_default()
+        {
+            return Err(Error::ValueNotFound(name.to_string()));
+        }
+
+        let value = module_namespace
+            .get(&mut scope, key.into())
+            .unwrap_or_else(|| v8::undefined(&mut scope).into());
+        JsValue::from_v8(&mut scope, value)
     }
 
     /// Evaluate a piece of non-ECMAScript-module JavaScript code
@@ -149,11 +1041,82 @@ impl InnerRuntime {
     where
         T: serde::de::DeserializeOwned,
     {
-        let result = self.deno_runtime().execute_script("", expr.to_string())?;
+        self.eval_source(expr.to_string())
+    }
+
+    /// Read a file, and evaluate it as non-ECMAScript-module JavaScript code
+    /// Relative paths are resolved the same way as module filenames
+    pub fn eval_file<T>(&mut self, path: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let specifier = self.module_loader.resolve_path(path)?;
+        let file_path = specifier
+            .to_file_path()
+            .map_err(|_| Error::ModuleNotFound(format!("{path} is not a file path")))?;
+        let code = std::fs::read_to_string(file_path)
+            .map_err(|e| Error::ModuleNotFound(format!("{path}: {e}")))?;
+
+        // Names the script in stack traces, since scripts are otherwise anonymous
+        self.eval_source(format!("{code}\n//# sourceURL={specifier}"))
+    }
+
+    /// Compile a piece of non-ECMAScript-module JavaScript code without running it
+    /// Returns the position of the first syntax error, if there is one
+    pub fn check_script_syntax(&mut self, expr: &str) -> Result<(), SyntaxError> {
+        let mut scope = self.deno_runtime.handle_scope();
+        let mut scope = v8::TryCatch::new(&mut scope);
+        let Some(source) = v8::String::new(&mut scope, expr) else {
+            return Err(SyntaxError {
+                filename: "eval".to_string(),
+                line: 1,
+                column: 1,
+                message: "script is too long".to_string(),
+            });
+        };
+
+        if v8::Script::compile(&mut scope, source, None).is_some() {
+            return Ok(());
+        }
+
+        let (Some(exception), Some(message)) = (scope.exception(), scope.message()) else {
+            return Ok(());
+        };
+        let line = message.get_line_number(&mut scope).unwrap_or(1);
+        let column = message.get_start_column() + 1;
+        let error = JsError::from_v8_exception(&mut scope, exception);
+        Err(SyntaxError {
+            filename: "eval".to_string(),
+            line,
+            column,
+            message: error.message.unwrap_or(error.exception_message),
+        })
+    }
+
+    fn eval_source<T>(&mut self, code: String) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.update_stack_limit();
+
+        // Scripts cannot yield here, so only the watchdog can enforce the timeout
+        let isolate = self.deno_runtime.v8_isolate().thread_safe_handle();
+        let started = Instant::now();
+        let watchdog = Watchdog::start(self.options.timeout, isolate.clone());
+        let result = self.deno_runtime().execute_script("", code);
+        if watchdog.stop() {
+            isolate.cancel_terminate_execution();
+            return Err(Error::Timeout {
+                operation: Operation::Eval,
+                message: "Task timed out".to_string(),
+                elapsed: Some(started.elapsed()),
+            });
+        }
+        let result = result.map_err(|e| self.options.filter_error(e.into()))?;
 
         let mut scope = self.deno_runtime.handle_scope();
         let result = v8::Local::new(&mut scope, result);
-        Ok(deno_core::serde_v8::from_v8(&mut scope, result)?)
+        Ok(crate::js_collections::from_v8(&mut scope, result)?)
     }
 
     /// Calls a stored javascript function and deserializes its return value.
@@ -190,20 +1153,58 @@ impl InnerRuntime {
     /// A `Result` containing the deserialized result of the function call (`T`)
     /// or an error (`Error`) if the function cannot be found, if there are issues with
     /// calling the function, or if the result cannot be deserialized.
-    pub async fn call_function<T>(
+    pub async fn call_function<T, A>(
         &mut self,
         module_context: &ModuleHandle,
         name: &str,
-        args: &FunctionArguments,
+        args: &[A],
     ) -> Result<T, Error>
     where
         T: deno_core::serde::de::DeserializeOwned,
+        A: ToV8Argument,
     {
         let function = self.get_function_by_name(module_context, name)?;
         self.call_function_by_ref_async(module_context, function, args)
             .await
     }
 
+    /// Parse a JSON array straight into V8, returning its elements as function arguments
+    ///
+    /// # Arguments
+    /// * `raw_json` - A JSON array
+    ///
+    /// # Returns
+    /// A `Result` containing the array's elements, or an error (`Error`)
+    /// if the text is not valid JSON, or not an array
+    pub fn parse_json_arguments(
+        &mut self,
+        raw_json: &str,
+    ) -> Result<Vec<v8::Global<v8::Value>>, Error> {
+        let mut scope = self.deno_runtime.handle_scope();
+        let mut scope = v8::TryCatch::new(&mut scope);
+
+        let text = raw_json.to_v8_string(&mut scope)?;
+        let Some(value) = v8::json::parse(&mut scope, text) else {
+            let message = scope
+                .exception()
+                .map(|e| e.to_rust_string_lossy(&mut scope))
+                .unwrap_or_else(|| "invalid JSON".to_string());
+            return Err(Error::JsonDecode(message));
+        };
+
+        let array = v8::Local::<v8::Array>::try_from(value).map_err(|_| {
+            Error::JsonDecode("function arguments must be a JSON array".to_string())
+        })?;
+        let mut args = Vec::with_capacity(array.length() as usize);
+        for i in 0..array.length() {
+            let element = array
+                .get_index(&mut scope, i)
+                .unwrap_or_else(|| v8::undefined(&mut scope).into());
+            args.push(v8::Global::new(&mut scope, element));
+        }
+        Ok(args)
+    }
+
     /// Attempt to get a value out of the global context (globalThis.name)
     ///
     /// # Arguments
@@ -254,6 +1255,32 @@ impl InnerRuntime {
         }
     }
 
+    /// Get the default export of a module, if it has one
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle to a loaded module
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized default export, `None` if the
+    /// module has no default export, or an error (`Error`) if it cannot be deserialized
+    pub fn get_default_export<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+    ) -> Result<Option<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let value = match self.get_module_export_value(module_context, "default") {
+            Ok(value) => value,
+            Err(Error::ValueNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let value = v8::Local::<v8::Value>::new(&mut scope, value);
+        Ok(Some(crate::js_collections::from_v8(&mut scope, value)?))
+    }
+
     /// Attempt to get a value out of a runtime
     ///
     /// # Arguments
@@ -274,12 +1301,59 @@ impl InnerRuntime {
         .ok_or::<Error>(Error::ValueNotFound(name.to_string()))
     }
 
+    /// Get several values from a runtime instance at once
+    /// All of the values are resolved while driving the event loop a single time
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `names` - The names of the values to find
+    ///
+    /// # Returns
+    /// A `Result` containing the values, in the same order as `names`, or an
+    /// error (`Error`) naming the first value that cannot be found
+    pub async fn get_values(
+        &mut self,
+        module_context: &ModuleHandle,
+        names: &[&str],
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        let context = self.task_context();
+        let values = Self::run_async_task(
+            async move {
+                let mut futures = Vec::with_capacity(names.len());
+                for name in names {
+                    let value = self.get_value_ref_sync(module_context, name)?;
+                    futures.push(self.deno_runtime.resolve(value));
+                }
+
+                let future = deno_core::futures::future::try_join_all(futures);
+                let values = self
+                    .deno_runtime
+                    .with_event_loop_future(future, Default::default())
+                    .await?;
+                Ok::<Vec<v8::Global<v8::Value>>, Error>(values)
+            },
+            Operation::GetValue,
+            context,
+        )
+        .await
+        .map_err(|e| self.options.filter_error(e))?;
+
+        let mut scope = self.deno_runtime.handle_scope();
+        values
+            .into_iter()
+            .map(|value| {
+                let value = v8::Local::new(&mut scope, value);
+                Ok(crate::js_collections::from_v8(&mut scope, value)?)
+            })
+            .collect()
+    }
+
     pub async fn get_value_ref_async(
         &mut self,
         module_context: &ModuleHandle,
         name: &str,
     ) -> Result<v8::Global<v8::Value>, Error> {
-        let timeout = self.options.timeout;
+        let context = self.task_context();
         Self::run_async_task(
             async move {
                 let result = self.get_value_ref_sync(module_context, name)?;
@@ -296,9 +1370,11 @@ impl InnerRuntime {
                 let value = v8::Global::new(&mut scope, result);
                 Ok::<v8::Global<v8::Value>, Error>(value)
             },
-            timeout,
+            Operation::GetValue,
+            context,
         )
         .await
+        .map_err(|e| self.options.filter_error(e))
     }
 
     /// This method takes a javascript function and invokes it within the Deno runtime.
@@ -313,15 +1389,20 @@ impl InnerRuntime {
     /// A `Result` containing the deserialized result of the function call (`T`)
     /// or an error (`Error`) if the function call fails or the return value cannot
     /// be deserialized.
-    pub fn call_function_by_ref_sync(
+    pub fn call_function_by_ref_sync<A>(
         &mut self,
         module_context: &ModuleHandle,
         function: v8::Global<v8::Function>,
-        args: &FunctionArguments,
-    ) -> Result<v8::Global<v8::Value>, Error> {
+        args: &[A],
+    ) -> Result<v8::Global<v8::Value>, Error>
+    where
+        A: ToV8Argument,
+    {
+        self.update_stack_limit();
         let module_namespace = self
             .deno_runtime
             .get_module_namespace(module_context.id())?;
+        let argument_serializer = self.options.argument_serializer.clone();
         let mut scope = self.deno_runtime.handle_scope();
         let mut scope = v8::TryCatch::new(&mut scope);
 
@@ -329,9 +1410,9 @@ impl InnerRuntime {
         let function_instance = function.open(&mut scope);
 
         // Prep arguments
-        let f_args: Result<Vec<v8::Local<v8::Value>>, deno_core::serde_v8::Error> = args
+        let f_args: Result<Vec<v8::Local<v8::Value>>, Error> = args
             .iter()
-            .map(|f| deno_core::serde_v8::to_v8(&mut scope, f))
+            .map(|f| f.to_v8_argument(&mut scope, argument_serializer.as_ref()))
             .collect();
         let final_args = f_args?;
 
@@ -341,21 +1422,11 @@ impl InnerRuntime {
                 let value = v8::Global::new(&mut scope, value);
                 Ok(value)
             }
-            None if scope.has_caught() => {
-                let e = scope.message().unwrap();
-
-                let filename = e.get_script_resource_name(&mut scope);
-                let filename = if let Some(v) = filename {
-                    v.to_rust_string_lossy(&mut scope)
-                } else {
-                    module_context.module().filename().to_string()
-                };
-
-                let linenumber = e.get_line_number(&mut scope).unwrap_or_default();
-                let msg = e.get(&mut scope).to_rust_string_lossy(&mut scope);
-
-                let s = format!("{filename}:{linenumber}: {msg}");
-                Err(Error::Runtime(s))
+            // Keep the structure of the thrown value - name, message and cause chain
+            None if scope.has_caught() && scope.exception().is_some() => {
+                let exception = scope.exception().unwrap();
+                let e = deno_core::error::JsError::from_v8_exception(&mut scope, exception);
+                Err(self.options.filter_error(Error::JsError(e)))
             }
             None => Err(Error::Runtime(
                 "Unknown error during function execution".to_string(),
@@ -392,17 +1463,44 @@ impl InnerRuntime {
         Ok(v8::Global::<v8::Function>::new(&mut scope, f))
     }
 
-    pub async fn call_function_by_ref_async<T>(
+    /// Get the number of parameters a function expects, from its `length` property
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle to a loaded module
+    /// * `name` - Name of the function
+    ///
+    /// # Returns
+    /// A `Result` containing the function's arity, or an error (`Error`) if
+    /// the value cannot be found, or is not a function
+    pub fn function_arity(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<usize, Error> {
+        let function = self.get_function_by_name(module_context, name)?;
+        let mut scope = self.deno_runtime.handle_scope();
+        let function = v8::Local::new(&mut scope, function);
+
+        let key = "length".to_v8_string(&mut scope)?;
+        let length = function
+            .get(&mut scope, key.into())
+            .and_then(|length| length.uint32_value(&mut scope))
+            .unwrap_or_default();
+        Ok(length as usize)
+    }
+
+    pub async fn call_function_by_ref_async<T, A>(
         &mut self,
         module_context: &ModuleHandle,
         function: v8::Global<v8::Function>,
-        args: &FunctionArguments,
+        args: &[A],
     ) -> Result<T, Error>
     where
         T: deno_core::serde::de::DeserializeOwned,
+        A: ToV8Argument,
     {
-        let timeout = self.options.timeout;
-        Self::run_async_task(
+        let context = self.task_context();
+        let result = Self::run_async_task(
             async move {
                 let result = self.call_function_by_ref_sync(module_context, function, args)?;
                 let future = self.deno_runtime.resolve(result);
@@ -413,30 +1511,168 @@ impl InnerRuntime {
 
                 //let result = self.deno_runtime.resolve(result).await?;
 
-                let mut scope = self.deno_runtime.handle_scope();
-                let result = v8::Local::new(&mut scope, result);
+                let mut scope = self.deno_runtime.handle_scope();
+                let result = v8::Local::new(&mut scope, result);
+
+                // Decode value
+                let value: T = crate::js_collections::from_v8(&mut scope, result)?;
+                Ok::<T, Error>(value)
+            },
+            Operation::CallFunction,
+            context,
+        )
+        .await;
+
+        self.run_cleanup_hooks();
+        result.map_err(|e| self.options.filter_error(e))
+    }
+
+    /// Run the functions registered with `rustyscript.onCleanup`, then forget them
+    /// This is best-effort: errors are ignored, returned promises are not awaited,
+    /// and hooks still running after `CLEANUP_BUDGET` are interrupted
+    fn run_cleanup_hooks(&mut self) {
+        let hooks = match self.take::<CleanupHooks>() {
+            Some(hooks) if !hooks.0.is_empty() => hooks.0,
+            _ => return,
+        };
+
+        let isolate = self.deno_runtime.v8_isolate().thread_safe_handle();
+        let watchdog = Watchdog::start(CLEANUP_BUDGET, isolate.clone());
+        {
+            let mut scope = self.deno_runtime.handle_scope();
+            let mut scope = v8::TryCatch::new(&mut scope);
+            for hook in hooks {
+                let hook = v8::Local::new(&mut scope, hook);
+                let undefined: v8::Local<v8::Value> = v8::undefined(&mut scope).into();
+                hook.call(&mut scope, undefined, &[]);
+                if scope.has_terminated() {
+                    break;
+                }
+            }
+        }
+
+        if watchdog.stop() {
+            isolate.cancel_terminate_execution();
+        }
+    }
+
+    /// Calls a function, and settles the promise it returns (if any) using only
+    /// the microtask queue. The event loop is not polled, so pending timers and
+    /// async ops do not run
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the call fails, the promise rejects, or the promise
+    /// cannot be settled without running the event loop
+    pub fn call_function_by_ref_immediate<T, A>(
+        &mut self,
+        module_context: &ModuleHandle,
+        function: v8::Global<v8::Function>,
+        args: &[A],
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+        A: ToV8Argument,
+    {
+        let result = self.call_function_by_ref_sync(module_context, function, args)?;
+        let mut scope = self.deno_runtime.handle_scope();
+        scope.perform_microtask_checkpoint();
+
+        let result = v8::Local::new(&mut scope, result);
+        let result = match v8::Local::<v8::Promise>::try_from(result) {
+            Ok(promise) => match promise.state() {
+                v8::PromiseState::Fulfilled => promise.result(&mut scope),
+                v8::PromiseState::Rejected => {
+                    promise.mark_as_handled();
+                    let exception = promise.result(&mut scope);
+                    let e = deno_core::error::JsError::from_v8_exception(&mut scope, exception);
+                    return Err(self.options.filter_error(e.into()));
+                }
+                v8::PromiseState::Pending => {
+                    return Err(Error::Runtime(
+                        "Promise could not be settled without running the event loop".to_string(),
+                    ))
+                }
+            },
+            Err(_) => result,
+        };
+
+        Ok(crate::js_collections::from_v8(&mut scope, result)?)
+    }
 
-                // Decode value
-                let value: T = deno_core::serde_v8::from_v8(&mut scope, result)?;
-                Ok::<T, Error>(value)
-            },
-            timeout,
-        )
-        .await
+    /// What a task needs from the runtime to be timed out or cancelled
+    /// It is taken before the task borrows the runtime, so the stack limit is updated here too
+    fn task_context(&mut self) -> TaskContext {
+        self.update_stack_limit();
+        TaskContext {
+            timeout: self.options.timeout,
+            precise_timeout: self.options.precise_timeout,
+            cancel_token: self.options.cancel_token.clone(),
+            op_state: self.deno_runtime.op_state(),
+            isolate: self.deno_runtime.v8_isolate().thread_safe_handle(),
+        }
     }
 
-    pub async fn run_async_task<T, F>(f: F, timeout: Duration) -> Result<T, Error>
+    /// Run a task, failing if it times out or is cancelled
+    async fn run_async_task<T, F>(
+        f: F,
+        operation: Operation,
+        context: TaskContext,
+    ) -> Result<T, Error>
     where
-        F: tokio::macros::support::Future + std::future::Future<Output = Result<T, Error>>,
+        F: std::future::Future<Output = Result<T, Error>>,
     {
-        tokio::select! {
-            _ = tokio::time::sleep(timeout) => {
-                Err(Error::Timeout("Task timed out".to_string()))
+        let TaskContext {
+            timeout,
+            precise_timeout,
+            cancel_token,
+            op_state,
+            isolate,
+        } = context;
+
+        // Make the deadline visible to JS, restoring the outer one once done
+        let previous = op_state.borrow_mut().try_take::<Deadline>();
+        op_state
+            .borrow_mut()
+            .put(Deadline(Instant::now().checked_add(timeout)));
+
+        let started = Instant::now();
+        let timed_out = || Error::Timeout {
+            operation,
+            message: "Task timed out".to_string(),
+            elapsed: Some(started.elapsed()),
+        };
+
+        // The watchdog interrupts scripts that never yield to the event loop
+        let mut watchdog = Watchdog::start(timeout, isolate.clone());
+        let mut result = tokio::select! {
+            _ = Self::timeout_expired(timeout, precise_timeout, &mut watchdog) => {
+                Err(timed_out())
+            }
+            _ = Self::cancelled(cancel_token.as_ref()) => {
+                Err(Error::Cancelled(operation))
             }
             res = f => {
                 res
             }
+        };
+
+        if watchdog.stop() {
+            // Execution was terminated, so the isolate must be made usable again
+            isolate.cancel_terminate_execution();
+            if !matches!(result, Err(Error::Timeout { .. })) {
+                result = Err(timed_out());
+            }
+        }
+
+        match previous {
+            Some(deadline) => op_state.borrow_mut().put(deadline),
+            None => {
+                op_state.borrow_mut().try_take::<Deadline>();
+            }
         }
+
+        result
     }
 
     /// Load one or more modules
@@ -448,8 +1684,10 @@ impl InnerRuntime {
         main_module: Option<&Module>,
         side_modules: Vec<&Module>,
     ) -> Result<ModuleHandle, Error> {
-        let timeout = self.options.timeout;
+        let context = self.task_context();
         let default_entrypoint = self.options.default_entrypoint.clone();
+        let capture_evaluation_result =
+            self.options.capture_evaluation_result && main_module.is_some();
 
         if main_module.is_none() && side_modules.is_empty() {
             return Err(Error::Runtime(
@@ -457,41 +1695,115 @@ impl InnerRuntime {
             ));
         }
 
+        // Give a debugger the chance to attach before anything runs
+        if self.inspector_server.is_some()
+            && self
+                .options
+                .inspector
+                .is_some_and(|inspector| inspector.wait_for_connection)
+        {
+            self.deno_runtime
+                .inspector()
+                .borrow_mut()
+                .wait_for_session();
+        }
+
+        let side_modules = side_modules
+            .into_iter()
+            .map(|module| Ok((module, self.module_specifier(module)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let main_module = main_module
+            .map(|module| Ok::<_, Error>((module, self.module_specifier(module)?)))
+            .transpose()?;
+
+        let side_module_concurrency = self.options.side_module_concurrency.max(1);
+        let commonjs = self.options.commonjs;
+        let module_loader = self.module_loader.clone();
+        let op_state = self.deno_runtime.op_state();
         let deno_runtime = &mut self.deno_runtime();
         let module_handle_stub = Self::run_async_task(
             async move {
                 let mut module_handle_stub = Default::default();
 
-                // Get additional modules first
-                for side_module in side_modules {
-                    let module_specifier = side_module.filename().to_module_specifier()?;
-                    let code = transpiler::transpile(&module_specifier, side_module.contents())?;
-
-                    let s_modid = deno_runtime
-                        .load_side_es_module_from_code(
-                            &module_specifier,
-                            deno_core::FastString::from(code),
-                        )
-                        .await?;
-                    let result = deno_runtime.mod_evaluate(s_modid);
+                // Get additional modules first, evaluating each batch together
+                for batch in side_modules.chunks(side_module_concurrency) {
+                    let mut loaded = Vec::with_capacity(batch.len());
+                    for (side_module, module_specifier) in batch {
+                        let s_modid = match Self::loader_source(side_module) {
+                            // Remote modules are fetched by the loader, like an import
+                            _ if side_module.is_remote() => {
+                                deno_runtime.load_side_es_module(module_specifier).await?
+                            }
+
+                            // Binary and JSON modules are served as-is by the loader
+                            Some(bytes) => {
+                                module_loader.bytes_add(module_specifier, bytes);
+                                deno_runtime.load_side_es_module(module_specifier).await?
+                            }
+
+                            None => {
+                                let code = Self::module_code(
+                                    &module_loader,
+                                    side_module,
+                                    module_specifier,
+                                    commonjs,
+                                )?;
+                                deno_runtime
+                                    .load_side_es_module_from_code(
+                                        module_specifier,
+                                        deno_core::FastString::from(code),
+                                    )
+                                    .await?
+                            }
+                        };
+                        loaded.push((*side_module, s_modid));
+                    }
+
+                    let results: Vec<_> = loaded
+                        .iter()
+                        .map(|(_, s_modid)| deno_runtime.mod_evaluate(*s_modid))
+                        .collect();
                     deno_runtime
                         .run_event_loop(PollEventLoopOptions::default())
                         .await?;
-                    result.await?;
-                    module_handle_stub = ModuleHandle::new(side_module, s_modid, None);
+                    for result in results {
+                        result.await?;
+                    }
+
+                    if let Some((side_module, s_modid)) = loaded.last() {
+                        module_handle_stub = ModuleHandle::new(side_module, *s_modid, None);
+                    }
                 }
 
                 // Load main module
-                if let Some(module) = main_module {
-                    let module_specifier = module.filename().to_module_specifier()?;
-                    let code = transpiler::transpile(&module_specifier, module.contents())?;
-
-                    let module_id = deno_runtime
-                        .load_main_es_module_from_code(
-                            &module_specifier,
-                            deno_core::FastString::from(code),
-                        )
-                        .await?;
+                if let Some((module, module_specifier)) = main_module {
+                    let module_id = match Self::loader_source(module) {
+                        // Remote modules are fetched by the loader, like an import
+                        _ if module.is_remote() => {
+                            deno_runtime.load_main_es_module(&module_specifier).await?
+                        }
+
+                        // Binary and JSON modules are served as-is by the loader
+                        Some(bytes) => {
+                            module_loader.bytes_add(&module_specifier, bytes);
+                            deno_runtime.load_main_es_module(&module_specifier).await?
+                        }
+
+                        None => {
+                            let code = Self::module_code(
+                                &module_loader,
+                                module,
+                                &module_specifier,
+                                commonjs,
+                            )?;
+                            deno_runtime
+                                .load_main_es_module_from_code(
+                                    &module_specifier,
+                                    deno_core::FastString::from(code),
+                                )
+                                .await?
+                        }
+                    };
 
                     // Finish execution
                     let result = deno_runtime.mod_evaluate(module_id);
@@ -507,26 +1819,136 @@ impl InnerRuntime {
 
                 Ok::<ModuleHandle, Error>(module_handle_stub)
             },
-            timeout,
+            Operation::LoadModule,
+            context,
         )
-        .await?;
+        .await
+        .map_err(|e| self.options.filter_error(e))?;
 
         // Try to get an entrypoint
         let state = self.deno_runtime().op_state();
         let mut deep_state = state.try_borrow_mut()?;
-        let f_entrypoint = match deep_state.try_take::<v8::Global<v8::Function>>() {
-            Some(entrypoint) => Some(entrypoint),
-            None => default_entrypoint.and_then(|default_entrypoint| {
-                self.get_function_by_name(&module_handle_stub, &default_entrypoint)
-                    .ok()
-            }),
+        let metadata = deep_state.try_take::<EntrypointMetadata>();
+        let named_entrypoints = deep_state
+            .try_take::<NamedEntrypoints>()
+            .unwrap_or_default();
+        let (f_entrypoint, metadata) = match deep_state.try_take::<v8::Global<v8::Function>>() {
+            Some(entrypoint) => (Some(entrypoint), metadata),
+            None => (
+                default_entrypoint.and_then(|default_entrypoint| {
+                    self.get_function_by_name(&module_handle_stub, &default_entrypoint)
+                        .ok()
+                }),
+                None,
+            ),
+        };
+
+        drop(deep_state);
+
+        let evaluation_result = if capture_evaluation_result {
+            Some(self.module_exports_json(module_handle_stub.id())?)
+        } else {
+            None
         };
 
         Ok(ModuleHandle::new(
             module_handle_stub.module(),
             module_handle_stub.id(),
             f_entrypoint,
-        ))
+        )
+        .with_entrypoint_metadata(metadata)
+        .with_named_entrypoints(named_entrypoints.0)
+        .with_evaluation_result(evaluation_result))
+    }
+
+    /// Resolves once the token is cancelled, or never if there is no token
+    async fn cancelled(cancel_token: Option<&CancellationToken>) {
+        match cancel_token {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Resolves once the timeout has passed
+    async fn timeout_expired(timeout: Duration, precise: bool, watchdog: &mut Watchdog) {
+        if precise {
+            watchdog.expired().await;
+        } else {
+            tokio::time::sleep(timeout).await;
+        }
+    }
+
+    /// The code to load for a module - transpiled, or wrapped if it is a CommonJS module,
+    /// and given any `import.meta` fields.
+    /// The loader is told about the code, since it is loaded without going through it
+    fn module_code(
+        module_loader: &RustyLoader,
+        module: &Module,
+        module_specifier: &ModuleSpecifier,
+        commonjs: bool,
+    ) -> Result<String, Error> {
+        let start = Instant::now();
+        let code = if commonjs && module.is_commonjs() {
+            ext::rustyscript::commonjs_module(module_specifier, module.contents())?
+        } else {
+            transpiler::transpile_cached(module_specifier, module.contents())?
+        };
+        module_loader.check_target(module_specifier, &code);
+        let code = RustyLoader::add_import_meta(module_loader.import_meta(), code);
+
+        module_loader.code_add(module_specifier, &code);
+        module_loader.module_loaded(ModuleLoadEvent {
+            specifier: module_specifier.clone(),
+            source_length: code.len(),
+            duration: start.elapsed(),
+            cache_hit: false,
+        });
+        Ok(code)
+    }
+
+    /// The contents of a module that must be served by the loader, instead of loaded as code
+    /// The loader decides the module's type, so that JSON modules are not parsed as JS
+    fn loader_source(module: &Module) -> Option<Vec<u8>> {
+        match module.bytes() {
+            Some(bytes) => Some(bytes.to_vec()),
+            None if module.is_json() => Some(module.contents().as_bytes().to_vec()),
+            None => None,
+        }
+    }
+
+    /// Serialize the exports of a module to JSON, leaving out any that JSON cannot represent
+    fn module_exports_json(&mut self, module_id: ModuleId) -> Result<serde_json::Value, Error> {
+        let namespace = self.deno_runtime.get_module_namespace(module_id)?;
+        let mut scope = self.deno_runtime.handle_scope();
+        let namespace = v8::Local::new(&mut scope, namespace);
+        let mut scope = v8::TryCatch::new(&mut scope);
+
+        let names = namespace
+            .get_own_property_names(&mut scope, Default::default())
+            .ok_or_else(|| Error::Runtime("could not list module exports".to_string()))?;
+
+        // Each export is serialized on its own, so one that throws, like a BigInt, is just skipped
+        let mut exports = serde_json::Map::new();
+        for i in 0..names.length() {
+            let Some(name) = names.get_index(&mut scope, i) else {
+                continue;
+            };
+            let Some(value) = namespace.get(&mut scope, name) else {
+                scope.reset();
+                continue;
+            };
+            let Some(json) = v8::json::stringify(&mut scope, value) else {
+                scope.reset();
+                continue;
+            };
+
+            // Values JSON leaves out, such as functions, do not produce valid JSON text
+            let json = json.to_rust_string_lossy(&mut scope);
+            if let Ok(value) = serde_json::from_str(&json) {
+                exports.insert(name.to_rust_string_lossy(&mut scope), value);
+            }
+        }
+        Ok(serde_json::Value::Object(exports))
     }
 }
 
@@ -548,7 +1970,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -589,7 +2011,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -627,7 +2049,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -668,7 +2090,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -678,7 +2100,20 @@ mod test_inner_runtime {
             .call_function::<usize>(&module, "fn", json_args!(1))
             .await
             .unwrap_err();
-        assert!(e.to_string().ends_with("test.js:2: Uncaught Error: msg"));
+        match e {
+            Error::JsError(e) => {
+                assert_eq!(Some("Error"), e.name.as_deref());
+                assert_eq!(Some("msg"), e.message.as_deref());
+
+                let frame = e.frames.first().expect("No stack frames");
+                assert!(frame
+                    .file_name
+                    .as_deref()
+                    .is_some_and(|f| f.ends_with("test.js")));
+                assert_eq!(Some(2), frame.line_number);
+            }
+            e => panic!("Unexpected error: {e}"),
+        }
     }
 
     #[tokio::test]
@@ -692,7 +2127,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -716,7 +2151,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -750,7 +2185,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -763,6 +2198,197 @@ mod test_inner_runtime {
         assert_eq!(value, 2);
     }
 
+    #[cfg(feature = "url_import")]
+    #[tokio::test]
+    async fn test_http_client() {
+        use std::io::{BufRead, BufReader, Write};
+
+        // Serve a single module, recording the user agent it was requested with
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Could not bind");
+        let port = listener.local_addr().expect("No address").port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("No connection");
+            let mut user_agent = None;
+            for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                let line = line.expect("Could not read request");
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("user-agent") {
+                        user_agent = Some(value.trim().to_string());
+                    }
+                }
+            }
+
+            let body = "export const value = 2;";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/javascript\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .expect("Could not respond");
+            user_agent
+        });
+
+        let client = reqwest::Client::builder()
+            .user_agent("rustyscript-test")
+            .build()
+            .expect("Could not build client");
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            http_client: Some(client),
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+
+        let module = Module::new(
+            "test.js",
+            &format!("export {{ value }} from 'http://127.0.0.1:{port}/module.js';"),
+        );
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .get_value(&module, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(2, value);
+
+        let user_agent = server.join().expect("Server failed");
+        assert_eq!(Some("rustyscript-test".to_string()), user_agent);
+    }
+
+    #[cfg(feature = "url_import")]
+    #[tokio::test]
+    async fn test_module_from_url() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Could not bind");
+        let port = listener.local_addr().expect("No address").port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("No connection");
+            let mut path = None;
+            for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                let line = line.expect("Could not read request");
+                if line.is_empty() {
+                    break;
+                }
+                if path.is_none() {
+                    path = line.split(' ').nth(1).map(str::to_string);
+                }
+            }
+
+            let body = "export const value = 2;";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/javascript\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .expect("Could not respond");
+            path
+        });
+
+        let module = Module::from_url(&format!("http://127.0.0.1:{port}/script.js"));
+        assert!(module.is_remote());
+        assert!(module.contents().is_empty());
+
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .get_value(&module, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(2, value);
+
+        let path = server.join().expect("Server failed");
+        assert_eq!(Some("/script.js".to_string()), path);
+    }
+
+    #[tokio::test]
+    async fn test_transpile_cache() {
+        let module = Module::new("test_transpile_cache.ts", "export const value: number = 2;");
+        let specifier = module
+            .filename()
+            .to_module_specifier()
+            .expect("Invalid filename");
+
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
+        let handle = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .get_value(&handle, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(2, value);
+
+        // The first load populated the shared cache
+        let cached = transpiler::transpile_cache()
+            .lock()
+            .unwrap()
+            .get(&specifier, module.contents())
+            .map(str::to_string)
+            .expect("Transpiled code was not cached");
+        assert!(!cached.contains("number"));
+
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
+        let handle = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .get_value(&handle, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(2, value);
+    }
+
+    #[tokio::test]
+    async fn test_evaluation_result() {
+        let module = Module::new(
+            "test.js",
+            "
+            const init = async () => ({ ready: true });
+            export const config = await init();
+            export const answer = await Promise.resolve(42);
+            export const big = 10n;
+            export function test() {}
+        ",
+        );
+
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            capture_evaluation_result: true,
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+        let handle = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+        let result: Option<serde_json::Value> = handle
+            .evaluation_result()
+            .expect("Could not deserialize result");
+        assert_eq!(
+            Some(serde_json::json!({ "answer": 42, "config": { "ready": true } })),
+            result
+        );
+
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
+        let handle = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+        let result: Option<serde_json::Value> = handle
+            .evaluation_result()
+            .expect("Could not deserialize result");
+        assert_eq!(None, result);
+    }
+
     #[cfg(feature = "web")]
     #[tokio::test]
     async fn test_promise() {
@@ -779,7 +2405,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -806,7 +2432,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -819,6 +2445,296 @@ mod test_inner_runtime {
         assert_eq!(value, 2);
     }
 
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_timeout_operation() {
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const test = () => new Promise((r) => setTimeout(r, 2000));
+            export const fast = () => 2;
+        ",
+        );
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+
+        match runtime
+            .call_function::<Undefined>(&module, "test", json_args!())
+            .await
+        {
+            Err(Error::Timeout { operation, .. }) => assert_eq!(Operation::CallFunction, operation),
+            _ => panic!("Did not time out"),
+        }
+
+        let module = Module::new(
+            "test2.js",
+            "
+            await new Promise((r) => setTimeout(r, 2000));
+        ",
+        );
+        match runtime.load_modules(None, vec![&module]).await {
+            Err(Error::Timeout { operation, .. }) => assert_eq!(Operation::LoadModule, operation),
+            _ => panic!("Did not time out"),
+        }
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_cancel_token() {
+        let token = CancellationToken::new();
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            cancel_token: Some(token.clone()),
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const test = () => new Promise((r) => setTimeout(r, 2000));
+        ",
+        );
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+
+        let handle = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            handle.cancel();
+        });
+
+        match runtime
+            .call_function::<Undefined>(&module, "test", json_args!())
+            .await
+        {
+            Err(Error::Cancelled(operation)) => assert_eq!(Operation::CallFunction, operation),
+            _ => panic!("Was not cancelled"),
+        }
+
+        // Work can continue once the token is reset
+        token.reset();
+        let value: usize = runtime
+            .call_function(&module, "fast", json_args!())
+            .await
+            .expect("Runtime was not usable after cancellation");
+        assert_eq!(2, value);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_sync_loop() {
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const spin = () => { while (true) {} };
+        ",
+        );
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+
+        match runtime
+            .call_function::<Undefined>(&module, "spin", json_args!())
+            .await
+        {
+            Err(Error::Timeout { operation, .. }) => assert_eq!(Operation::CallFunction, operation),
+            _ => panic!("Did not time out"),
+        }
+
+        // The runtime is still usable afterwards
+        let value: usize = runtime.eval("2").expect("Could not eval after timeout");
+        assert_eq!(2, value);
+
+        let module = Module::new("test2.js", "while (true) {}");
+        match runtime.load_modules(None, vec![&module]).await {
+            Err(Error::Timeout { operation, .. }) => assert_eq!(Operation::LoadModule, operation),
+            _ => panic!("Did not time out"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_hooks() {
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const spin = () => {
+                rustyscript.onCleanup(() => rustyscript.incr('cleanups'));
+                while (true) {}
+            };
+            export const done = () => {
+                rustyscript.onCleanup(() => rustyscript.incr('cleanups'));
+                return 2;
+            };
+        ",
+        );
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+
+        runtime
+            .call_function::<Undefined>(&module, "spin", json_args!())
+            .await
+            .expect_err("Did not time out");
+        assert_eq!(Some(&1), runtime.metrics_snapshot().get("cleanups"));
+
+        // Hooks only run once, for the call that registered them
+        let value: usize = runtime
+            .call_function(&module, "done", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(2, value);
+        assert_eq!(Some(&2), runtime.metrics_snapshot().get("cleanups"));
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_precise_timeout() {
+        let timeout = Duration::from_millis(50);
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            timeout,
+            precise_timeout: true,
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const test = () => new Promise((r) => setTimeout(r, 2000));
+        ",
+        );
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+
+        // Keep the CPU busy while waiting
+        let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let load: Vec<_> = (0..4)
+            .map(|_| {
+                let stop = stop.clone();
+                std::thread::spawn(move || {
+                    while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                        std::hint::spin_loop();
+                    }
+                })
+            })
+            .collect();
+
+        let result = runtime
+            .call_function::<Undefined>(&module, "test", json_args!())
+            .await;
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        load.into_iter().for_each(|t| t.join().unwrap());
+
+        match result {
+            Err(Error::Timeout {
+                elapsed: Some(elapsed),
+                ..
+            }) => {
+                // Other tests run in parallel, so this only checks that the
+                // deadline did not wait for the 2 second timer
+                assert!(elapsed >= timeout);
+                assert!(elapsed < timeout * 10, "Fired late: {elapsed:?}");
+            }
+            _ => panic!("Did not time out"),
+        }
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_side_module_concurrency() {
+        let modules: Vec<Module> = (0..4)
+            .map(|i| {
+                Module::new(
+                    &format!("side{i}.js"),
+                    "await new Promise((r) => setTimeout(r, 250));",
+                )
+            })
+            .collect();
+        let modules: Vec<&Module> = modules.iter().collect();
+
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
+        let start = Instant::now();
+        runtime
+            .load_modules(None, modules.clone())
+            .await
+            .expect("Could not load modules");
+        let sequential = start.elapsed();
+
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            side_module_concurrency: 4,
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+        let start = Instant::now();
+        runtime
+            .load_modules(None, modules)
+            .await
+            .expect("Could not load modules");
+        let concurrent = start.elapsed();
+
+        assert!(sequential >= Duration::from_millis(1000));
+        assert!(concurrent < Duration::from_millis(750));
+    }
+
+    #[tokio::test]
+    async fn test_thrown_error_cause() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const test = () => {
+                throw new TypeError('x', { cause: new Error('y') });
+            };
+        ",
+        );
+
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .await
+            .expect("Could not load module");
+
+        match runtime
+            .call_function::<Undefined>(&module, "test", json_args!())
+            .await
+        {
+            Err(Error::JsError(e)) => {
+                assert_eq!(Some("TypeError"), e.name.as_deref());
+                assert_eq!(Some("x"), e.message.as_deref());
+
+                let cause = e.cause.expect("Cause was not parsed");
+                assert_eq!(Some("Error"), cause.name.as_deref());
+                assert_eq!(Some("y"), cause.message.as_deref());
+                assert!(cause.cause.is_none());
+            }
+            _ => panic!("Did not return a structured error"),
+        }
+    }
+
     #[tokio::test]
     async fn test_serialize_deep_fn() {
         let module = Module::new(
@@ -831,7 +2747,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -864,7 +2780,7 @@ mod test_inner_runtime {
         ",
         );
 
-        let mut runtime = InnerRuntime::new(Default::default());
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
         let module = runtime
             .load_modules(Some(&module), vec![])
             .await
@@ -0,0 +1,346 @@
+//! A minimal inspector server, bridging Chrome DevTools websocket
+//! connections to the V8 inspector sessions deno_core provides
+use crate::Error;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use deno_core::{
+    futures::{channel::mpsc, StreamExt},
+    serde_json, InspectorMsg, InspectorSessionProxy,
+};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// GUID used to compute the websocket handshake response, from RFC 6455
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest frame payload accepted from a debugger client
+/// DevTools messages are far smaller; anything larger is treated as a protocol error
+const MAX_FRAME_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Options for the V8 inspector, used to debug scripts with Chrome DevTools
+/// See `RuntimeOptions::inspector`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InspectorOptions {
+    /// Address to listen for debugger connections on
+    /// Use port 0 to pick a free port - see `Runtime::inspector_address`
+    pub address: SocketAddr,
+
+    /// If true, loading modules will wait until a debugger connects
+    pub wait_for_connection: bool,
+}
+
+/// Serves the inspector protocol over websockets until dropped
+pub struct InspectorServer {
+    address: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl InspectorServer {
+    /// Start listening for debugger connections
+    /// Each connection becomes a new session, sent through `session_sender`
+    pub fn start(
+        address: SocketAddr,
+        session_sender: mpsc::UnboundedSender<InspectorSessionProxy>,
+    ) -> Result<Self, Error> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let stop = shutdown.clone();
+        std::thread::Builder::new()
+            .name("rustyscript inspector".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    if let Ok(stream) = stream {
+                        let session_sender = session_sender.clone();
+                        std::thread::spawn(move || {
+                            handle_connection(stream, address, session_sender).ok();
+                        });
+                    }
+                }
+            })?;
+
+        Ok(Self { address, shutdown })
+    }
+
+    /// Returns the address the server is listening on
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for InspectorServer {
+    fn drop(&mut self) {
+        // Wake the listener so it can see the shutdown flag
+        self.shutdown.store(true, Ordering::SeqCst);
+        TcpStream::connect(self.address).ok();
+    }
+}
+
+/// Serve a single HTTP request - either a discovery endpoint, or a websocket upgrade
+fn handle_connection(
+    stream: TcpStream,
+    address: SocketAddr,
+    session_sender: mpsc::UnboundedSender<InspectorSessionProxy>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let is_upgrade = headers
+        .get("upgrade")
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    if is_upgrade {
+        let key = headers
+            .get("sec-websocket-key")
+            .cloned()
+            .unwrap_or_default();
+        let accept = websocket_accept(&key);
+        write!(
+            writer,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+        )?;
+        return run_session(reader, writer, session_sender);
+    }
+
+    let websocket_url = format!("{address}/ws");
+    let body = match path.as_str() {
+        "/json/version" => serde_json::json!({
+            "Browser": format!("rustyscript/{}", env!("CARGO_PKG_VERSION")),
+            "Protocol-Version": "1.3",
+        }),
+        "/json" | "/json/list" => serde_json::json!([{
+            "description": "rustyscript",
+            "devtoolsFrontendUrl": format!(
+                "devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={websocket_url}"
+            ),
+            "id": "rustyscript",
+            "title": format!("rustyscript [pid: {}]", std::process::id()),
+            "type": "node",
+            "url": "rustyscript",
+            "webSocketDebuggerUrl": format!("ws://{websocket_url}"),
+        }]),
+        _ => {
+            return write!(
+                writer,
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+        }
+    };
+
+    let body = body.to_string();
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=UTF-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Relay messages between a websocket client and a new inspector session
+fn run_session(
+    mut reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    session_sender: mpsc::UnboundedSender<InspectorSessionProxy>,
+) -> std::io::Result<()> {
+    let (inbound_tx, inbound_rx) = mpsc::unbounded::<String>();
+    let (outbound_tx, mut outbound_rx) = mpsc::unbounded::<InspectorMsg>();
+    if session_sender
+        .unbounded_send(InspectorSessionProxy {
+            tx: outbound_tx,
+            rx: inbound_rx,
+        })
+        .is_err()
+    {
+        // The runtime is gone
+        return Ok(());
+    }
+
+    let writer = Arc::new(Mutex::new(writer));
+    let outbound_writer = writer.clone();
+    std::thread::spawn(move || {
+        let Ok(tokio_runtime) = tokio::runtime::Builder::new_current_thread().build() else {
+            return;
+        };
+        tokio_runtime.block_on(async {
+            while let Some(message) = outbound_rx.next().await {
+                let Ok(mut writer) = outbound_writer.lock() else {
+                    break;
+                };
+                if write_frame(&mut *writer, 0x1, message.content.as_bytes()).is_err() {
+                    break;
+                }
+            }
+        });
+    });
+
+    let mut message = Vec::new();
+    loop {
+        let (fin, opcode, payload) = read_frame(&mut reader)?;
+        match opcode {
+            // Text, binary, or a continuation of either
+            0x0..=0x2 => {
+                message.extend(payload);
+                if fin {
+                    let text = String::from_utf8_lossy(&message).into_owned();
+                    message.clear();
+                    if inbound_tx.unbounded_send(text).is_err() {
+                        break;
+                    }
+                }
+            }
+
+            // Ping
+            0x9 => {
+                if let Ok(mut writer) = writer.lock() {
+                    write_frame(&mut *writer, 0xA, &payload)?;
+                }
+            }
+
+            // Close
+            0x8 => {
+                if let Ok(mut writer) = writer.lock() {
+                    write_frame(&mut *writer, 0x8, &[]).ok();
+                }
+                break;
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a single websocket frame, returning the fin bit, opcode and unmasked payload
+fn read_frame(reader: &mut impl Read) -> std::io::Result<(bool, u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head)?;
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0F;
+    let masked = head[1] & 0x80 != 0;
+
+    let length = match head[1] & 0x7F {
+        126 => {
+            let mut length = [0u8; 2];
+            reader.read_exact(&mut length)?;
+            u64::from(u16::from_be_bytes(length))
+        }
+        127 => {
+            let mut length = [0u8; 8];
+            reader.read_exact(&mut length)?;
+            u64::from_be_bytes(length)
+        }
+        length => u64::from(length),
+    };
+    if length > MAX_FRAME_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("websocket frame of {length} bytes exceeds the {MAX_FRAME_SIZE} byte limit"),
+        ));
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+
+    let mut payload = vec![0u8; length as usize];
+    reader.read_exact(&mut payload)?;
+    if masked {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+
+    Ok((fin, opcode, payload))
+}
+
+/// Write a single, unmasked websocket frame
+fn write_frame(writer: &mut impl Write, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        length if length < 126 => frame.push(length as u8),
+        length if length <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend((length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend((length as u64).to_be_bytes());
+        }
+    }
+    frame.extend(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Compute the `Sec-WebSocket-Accept` response for a handshake key
+fn websocket_accept(key: &str) -> String {
+    let digest = Sha1::digest(format!("{key}{WEBSOCKET_GUID}").as_bytes());
+    BASE64.encode(digest)
+}
+
+#[cfg(test)]
+mod test_inspector {
+    use super::*;
+
+    #[test]
+    fn test_handshake_accept() {
+        // Example from RFC 6455
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let accept = websocket_accept(key);
+        assert_eq!("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=", accept);
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, 0x1, b"hello").expect("Could not write frame");
+
+        let (fin, opcode, payload) =
+            read_frame(&mut frame.as_slice()).expect("Could not read frame");
+        assert!(fin);
+        assert_eq!(0x1, opcode);
+        assert_eq!(b"hello".to_vec(), payload);
+    }
+
+    #[test]
+    fn test_oversized_frame() {
+        // A header claiming a payload far larger than the limit, with no payload behind it
+        let mut frame = vec![0x81, 127];
+        frame.extend(u64::MAX.to_be_bytes());
+
+        let error = read_frame(&mut frame.as_slice()).expect_err("Oversized frame was accepted");
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    }
+}
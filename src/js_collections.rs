@@ -0,0 +1,435 @@
+use crate::TaggedArgumentSerializer;
+use deno_core::{serde_json, serde_v8, v8};
+use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// How deeply arrays and objects are searched for a `Map` or a `Set`
+const MAX_DEPTH: usize = 512;
+
+/// Deserialize a value from the runtime, reading any `Map` or `Set` inside it
+/// in the tagged form used by `JsMap` and `JsSet`
+pub(crate) fn from_v8<'s, T>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+) -> Result<T, serde_v8::Error>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let value = tag_collections(scope, value, &mut Vec::new()).unwrap_or(value);
+    serde_v8::from_v8(scope, value)
+}
+
+/// Replace every `Map` with `{ "$map": [[key, value]] }` and every `Set` with `{ "$set": [value] }`
+/// Returns `None` if nothing was replaced; arrays and objects are only copied if something inside them was
+fn tag_collections<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+    ancestors: &mut Vec<v8::Local<'s, v8::Value>>,
+) -> Option<v8::Local<'s, v8::Value>> {
+    if !value.is_object()
+        || value.is_function()
+        || value.is_array_buffer()
+        || value.is_array_buffer_view()
+        || ancestors.len() >= MAX_DEPTH
+        || ancestors
+            .iter()
+            .any(|ancestor| ancestor.strict_equals(value))
+    {
+        return None;
+    }
+
+    ancestors.push(value);
+    let tagged = if let Ok(map) = v8::Local::<v8::Map>::try_from(value) {
+        // The entries are flattened into [key, value, key, value, ...]
+        let entries = tag_elements(scope, map.as_array(scope), ancestors).0;
+        let pairs: Vec<_> = entries
+            .chunks(2)
+            .map(|pair| v8::Array::new_with_elements(scope, pair).into())
+            .collect();
+        let pairs = v8::Array::new_with_elements(scope, &pairs);
+        Some(tagged_object(scope, "$map", pairs.into()))
+    } else if let Ok(set) = v8::Local::<v8::Set>::try_from(value) {
+        let values = tag_elements(scope, set.as_array(scope), ancestors).0;
+        let values = v8::Array::new_with_elements(scope, &values);
+        Some(tagged_object(scope, "$set", values.into()))
+    } else if let Ok(array) = v8::Local::<v8::Array>::try_from(value) {
+        let (elements, changed) = tag_elements(scope, array, ancestors);
+        changed.then(|| v8::Array::new_with_elements(scope, &elements).into())
+    } else {
+        v8::Local::<v8::Object>::try_from(value)
+            .ok()
+            .and_then(|object| tag_properties(scope, object, ancestors))
+    };
+    ancestors.pop();
+    tagged
+}
+
+/// Read the elements of an array, tagging any collections among them
+/// Also returns true if any element was replaced
+fn tag_elements<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    array: v8::Local<'s, v8::Array>,
+    ancestors: &mut Vec<v8::Local<'s, v8::Value>>,
+) -> (Vec<v8::Local<'s, v8::Value>>, bool) {
+    let mut changed = false;
+    let mut elements = Vec::with_capacity(array.length() as usize);
+    for i in 0..array.length() {
+        let element = array
+            .get_index(scope, i)
+            .unwrap_or_else(|| v8::undefined(scope).into());
+        match tag_collections(scope, element, ancestors) {
+            Some(tagged) => {
+                changed = true;
+                elements.push(tagged);
+            }
+            None => elements.push(element),
+        }
+    }
+    (elements, changed)
+}
+
+/// Copy an object's own enumerable properties, if any of them contain a collection
+fn tag_properties<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    object: v8::Local<'s, v8::Object>,
+    ancestors: &mut Vec<v8::Local<'s, v8::Value>>,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let names = object.get_own_property_names(scope, Default::default())?;
+
+    let mut changed = false;
+    let mut properties = Vec::with_capacity(names.length() as usize);
+    for i in 0..names.length() {
+        let Some(name) = names.get_index(scope, i) else {
+            continue;
+        };
+        let property = object
+            .get(scope, name)
+            .unwrap_or_else(|| v8::undefined(scope).into());
+        match tag_collections(scope, property, ancestors) {
+            Some(tagged) => {
+                changed = true;
+                properties.push((name, tagged));
+            }
+            None => properties.push((name, property)),
+        }
+    }
+
+    if !changed {
+        return None;
+    }
+    let copy = v8::Object::new(scope);
+    for (name, property) in properties {
+        copy.set(scope, name, property);
+    }
+    Some(copy.into())
+}
+
+/// Build `{ [tag]: value }`
+fn tagged_object<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    tag: &str,
+    value: v8::Local<'s, v8::Value>,
+) -> v8::Local<'s, v8::Value> {
+    let object = v8::Object::new(scope);
+    if let Some(tag) = v8::String::new(scope, tag) {
+        object.set(scope, tag.into(), value);
+    }
+    object.into()
+}
+
+/// The tagged form of a `Map`
+#[derive(Deserialize)]
+struct TaggedMap<K, V> {
+    #[serde(rename = "$map")]
+    entries: Vec<(K, V)>,
+}
+
+/// The tagged form of a `Set`
+#[derive(Deserialize)]
+struct TaggedSet<T> {
+    #[serde(rename = "$set")]
+    values: Vec<T>,
+}
+
+/// The entries of a JS `Map`, which would otherwise deserialize as an empty object
+///
+/// Values read from the runtime have every `Map` converted to the tagged form
+/// `{ "$map": [[key, value]] }` before they are deserialized, and `JsMap` reads and
+/// writes that form. As a function argument it is delivered to JS as a `Map` by
+/// `TaggedArgumentSerializer`
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ JsMap, Module, Runtime };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// # tokio_test::block_on(async {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("test.js", "export const scores = new Map([['a', 1]]);");
+/// let module = runtime.load_module(&module).await?;
+/// let scores: JsMap<String, f64> = runtime.get_value(&module, "scores").await?;
+/// assert_eq!(Some(&1.0), scores.get("a"));
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JsMap<K: Eq + Hash, V>(pub HashMap<K, V>);
+
+impl<K: Eq + Hash, V> JsMap<K, V> {
+    /// Returns the underlying map
+    pub fn into_inner(self) -> HashMap<K, V> {
+        self.0
+    }
+}
+
+impl<K: Eq + Hash, V> std::ops::Deref for JsMap<K, V> {
+    type Target = HashMap<K, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<K: Eq + Hash, V> From<HashMap<K, V>> for JsMap<K, V> {
+    fn from(map: HashMap<K, V>) -> Self {
+        Self(map)
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for JsMap<K, V>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let map = TaggedMap::deserialize(deserializer)?;
+        Ok(Self(map.entries.into_iter().collect()))
+    }
+}
+
+impl<K: Eq + Hash + Serialize, V: Serialize> Serialize for JsMap<K, V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let entries: Vec<_> = self.0.iter().collect();
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("$map", &entries)?;
+        map.end()
+    }
+}
+
+impl<K: Eq + Hash + Serialize, V: Serialize> From<JsMap<K, V>> for serde_json::Value {
+    fn from(map: JsMap<K, V>) -> Self {
+        TaggedArgumentSerializer::map(map.0.into_iter().map(|(k, v)| {
+            (
+                serde_json::to_value(k).unwrap_or_default(),
+                serde_json::to_value(v).unwrap_or_default(),
+            )
+        }))
+    }
+}
+
+/// The values of a JS `Set`, in insertion order, which would otherwise deserialize as an empty object
+///
+/// Values read from the runtime have every `Set` converted to the tagged form
+/// `{ "$set": [value] }` before they are deserialized, and `JsSet` reads and
+/// writes that form. As a function argument it is delivered to JS as a `Set` by
+/// `TaggedArgumentSerializer`
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ JsSet, Module, Runtime };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// # tokio_test::block_on(async {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("test.js", "export const tags = new Set(['a', 'b', 'a']);");
+/// let module = runtime.load_module(&module).await?;
+/// let tags: JsSet<String> = runtime.get_value(&module, "tags").await?;
+/// assert_eq!(vec!["a", "b"], tags.into_inner());
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JsSet<T>(pub Vec<T>);
+
+impl<T> JsSet<T> {
+    /// Returns the values of the set, in insertion order
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+
+    /// Returns the values of the set as a `HashSet`
+    pub fn into_hash_set(self) -> HashSet<T>
+    where
+        T: Eq + Hash,
+    {
+        self.0.into_iter().collect()
+    }
+}
+
+impl<T> std::ops::Deref for JsSet<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<Vec<T>> for JsSet<T> {
+    fn from(values: Vec<T>) -> Self {
+        Self(values)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for JsSet<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self(TaggedSet::deserialize(deserializer)?.values))
+    }
+}
+
+impl<T: Serialize> Serialize for JsSet<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("$set", &self.0)?;
+        map.end()
+    }
+}
+
+impl<T: Serialize> From<JsSet<T>> for serde_json::Value {
+    fn from(set: JsSet<T>) -> Self {
+        TaggedArgumentSerializer::set(
+            set.0
+                .into_iter()
+                .map(|v| serde_json::to_value(v).unwrap_or_default()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_js_collections {
+    use super::*;
+    use crate::{json_args, Module, Runtime, RuntimeOptions};
+
+    #[tokio::test]
+    async fn test_receive_collections() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const scores = () => new Map([['a', 1], ['b', 2.5]]);
+            export const ids = () => new Set([3, 1, 3, 2]);
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let scores: JsMap<String, f64> = runtime
+            .call_function(&module, "scores", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(
+            HashMap::from([("a".to_string(), 1.0), ("b".to_string(), 2.5)]),
+            scores.into_inner()
+        );
+
+        let ids: JsSet<usize> = runtime
+            .call_function(&module, "ids", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(vec![3, 1, 2], ids.0);
+        assert_eq!(HashSet::from([1, 2, 3]), ids.into_hash_set());
+
+        runtime
+            .call_function::<JsSet<usize>>(&module, "scores", json_args!())
+            .await
+            .expect_err("Did not detect a Map read as a Set");
+    }
+
+    #[tokio::test]
+    async fn test_nested_collections() {
+        #[derive(Deserialize)]
+        struct Report {
+            name: String,
+            scores: JsMap<String, JsSet<u32>>,
+        }
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const report = () => ({
+                name: 'r',
+                scores: new Map([['a', new Set([1, 2])]]),
+            });
+            export const plain = () => ({ ids: new Set([1]) });
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let report: Report = runtime
+            .call_function(&module, "report", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!("r", report.name);
+        assert_eq!(vec![1, 2], report.scores["a"].0);
+
+        let plain: serde_json::Value = runtime
+            .call_function(&module, "plain", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(serde_json::json!({ "ids": { "$set": [1] } }), plain);
+    }
+
+    #[tokio::test]
+    async fn test_pass_collections() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const check = (scores, ids) =>
+                scores instanceof Map && scores.get('a') === 1 &&
+                ids instanceof Set && ids.has(2) && ids.size === 2;
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            argument_serializer: Some(std::rc::Rc::new(TaggedArgumentSerializer)),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let scores = JsMap(HashMap::from([("a".to_string(), 1)]));
+        let ids = JsSet(vec![1, 2]);
+        let value: bool = runtime
+            .call_function(&module, "check", json_args!(scores, ids))
+            .await
+            .expect("Could not call function");
+        assert!(value);
+    }
+}
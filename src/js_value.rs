@@ -0,0 +1,382 @@
+use crate::Error;
+use deno_core::{serde_json, serde_v8, v8};
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::collections::BTreeMap;
+
+/// How deeply arrays and objects can be nested before reading them fails
+const MAX_DEPTH: usize = 512;
+
+/// A JS value, which unlike `serde_json::Value` keeps `undefined` and `null` apart
+///
+/// Values read directly from the runtime with `Runtime::get_js_value`
+/// distinguish the two. Serde formats have no `undefined`, so when a `JsValue`
+/// is deserialized by any other means, both become `JsValue::Null`
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ JsValue, Module, Runtime };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// # tokio_test::block_on(async {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("test.js", "export const missing = undefined;");
+/// let module = runtime.load_module(&module).await?;
+/// let value = runtime.get_js_value(&module, "missing").await?;
+/// assert_eq!(JsValue::Undefined, value);
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsValue {
+    /// `undefined`
+    Undefined,
+
+    /// `null`
+    Null,
+
+    /// `true` or `false`
+    Bool(bool),
+
+    /// Any number
+    Number(f64),
+
+    /// A string
+    String(String),
+
+    /// An array
+    Array(Vec<JsValue>),
+
+    /// An object's own enumerable properties
+    Object(BTreeMap<String, JsValue>),
+}
+
+impl JsValue {
+    /// Returns true if the value is `undefined`
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, Self::Undefined)
+    }
+
+    /// Returns true if the value is `null`
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Read a value from the runtime
+    /// Fails if the value contains a reference to itself, or is nested too deeply
+    pub(crate) fn from_v8<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        value: v8::Local<'s, v8::Value>,
+    ) -> Result<Self, Error> {
+        Self::read_v8(scope, value, &mut Vec::new())
+    }
+
+    /// Read a value, given the arrays and objects it is nested in
+    fn read_v8<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        value: v8::Local<'s, v8::Value>,
+        ancestors: &mut Vec<v8::Local<'s, v8::Object>>,
+    ) -> Result<Self, Error> {
+        if value.is_undefined() {
+            Ok(Self::Undefined)
+        } else if value.is_null() {
+            Ok(Self::Null)
+        } else if value.is_boolean() {
+            Ok(Self::Bool(value.boolean_value(scope)))
+        } else if value.is_number() {
+            Ok(Self::Number(value.number_value(scope).unwrap_or(f64::NAN)))
+        } else if value.is_string() {
+            Ok(Self::String(value.to_rust_string_lossy(scope)))
+        } else if value.is_object() && !value.is_function() {
+            let object = v8::Local::<v8::Object>::try_from(value)
+                .map_err(|e| Error::Runtime(e.to_string()))?;
+            if ancestors
+                .iter()
+                .any(|ancestor| ancestor.strict_equals(value))
+            {
+                return Err(Error::Runtime(
+                    "value contains a reference to itself".to_string(),
+                ));
+            }
+            if ancestors.len() >= MAX_DEPTH {
+                return Err(Error::Runtime("value is nested too deeply".to_string()));
+            }
+
+            ancestors.push(object);
+            let result = Self::read_object(scope, object, ancestors);
+            ancestors.pop();
+            result
+        } else {
+            // Anything else is decoded the same way serde would decode it
+            let value: serde_json::Value = serde_v8::from_v8(scope, value)?;
+            Ok(value.into())
+        }
+    }
+}
+
+impl JsValue {
+    /// Read the elements of an array, or the own properties of any other object
+    fn read_object<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        object: v8::Local<'s, v8::Object>,
+        ancestors: &mut Vec<v8::Local<'s, v8::Object>>,
+    ) -> Result<Self, Error> {
+        if let Ok(array) = v8::Local::<v8::Array>::try_from(object) {
+            let mut elements = Vec::with_capacity(array.length() as usize);
+            for i in 0..array.length() {
+                let element = array
+                    .get_index(scope, i)
+                    .unwrap_or_else(|| v8::undefined(scope).into());
+                elements.push(Self::read_v8(scope, element, ancestors)?);
+            }
+            return Ok(Self::Array(elements));
+        }
+
+        let names = object
+            .get_own_property_names(scope, Default::default())
+            .ok_or_else(|| Error::Runtime("could not list object properties".to_string()))?;
+
+        let mut properties = BTreeMap::new();
+        for i in 0..names.length() {
+            let Some(name) = names.get_index(scope, i) else {
+                continue;
+            };
+            let property = object
+                .get(scope, name)
+                .unwrap_or_else(|| v8::undefined(scope).into());
+            properties.insert(
+                name.to_rust_string_lossy(scope),
+                Self::read_v8(scope, property, ancestors)?,
+            );
+        }
+        Ok(Self::Object(properties))
+    }
+}
+
+impl From<serde_json::Value> for JsValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Self::Null,
+            serde_json::Value::Bool(b) => Self::Bool(b),
+            serde_json::Value::Number(n) => Self::Number(n.as_f64().unwrap_or(f64::NAN)),
+            serde_json::Value::String(s) => Self::String(s),
+            serde_json::Value::Array(a) => Self::Array(a.into_iter().map(Self::from).collect()),
+            serde_json::Value::Object(o) => {
+                Self::Object(o.into_iter().map(|(k, v)| (k, Self::from(v))).collect())
+            }
+        }
+    }
+}
+
+impl Serialize for JsValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Undefined | Self::Null => serializer.serialize_unit(),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Number(n) => serializer.serialize_f64(*n),
+            Self::String(s) => serializer.serialize_str(s),
+            Self::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Object(properties) => {
+                let mut map = serializer.serialize_map(Some(properties.len()))?;
+                for (name, value) in properties {
+                    map.serialize_entry(name, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(JsValueVisitor)
+    }
+}
+
+struct JsValueVisitor;
+
+impl<'de> Visitor<'de> for JsValueVisitor {
+    type Value = JsValue;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "any JS value")
+    }
+
+    fn visit_unit<E>(self) -> Result<JsValue, E> {
+        Ok(JsValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsValue, E> {
+        Ok(JsValue::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<JsValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        JsValue::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<JsValue, E> {
+        Ok(JsValue::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<JsValue, E> {
+        Ok(JsValue::Number(v as f64))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<JsValue, E> {
+        Ok(JsValue::Number(v as f64))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<JsValue, E> {
+        Ok(JsValue::Number(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<JsValue, E> {
+        Ok(JsValue::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<JsValue, E> {
+        Ok(JsValue::String(v))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<JsValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsValue::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<JsValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut properties = BTreeMap::new();
+        while let Some((name, value)) = map.next_entry()? {
+            properties.insert(name, value);
+        }
+        Ok(JsValue::Object(properties))
+    }
+}
+
+#[cfg(test)]
+mod test_js_value {
+    use super::*;
+    use crate::{Module, Runtime};
+
+    #[tokio::test]
+    async fn test_undefined_and_null() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const a = undefined;
+            export const b = null;
+            export const c = { list: [1, 'two', undefined] };
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let a = runtime
+            .get_js_value(&module, "a")
+            .await
+            .expect("Could not get value");
+        assert!(a.is_undefined());
+
+        let b = runtime
+            .get_js_value(&module, "b")
+            .await
+            .expect("Could not get value");
+        assert!(b.is_null());
+
+        let c = runtime
+            .get_js_value(&module, "c")
+            .await
+            .expect("Could not get value");
+        assert_eq!(
+            JsValue::Object(BTreeMap::from([(
+                "list".to_string(),
+                JsValue::Array(vec![
+                    JsValue::Number(1.0),
+                    JsValue::String("two".to_string()),
+                    JsValue::Undefined,
+                ])
+            )])),
+            c
+        );
+
+        // Serde formats have no undefined
+        let json: JsValue = serde_json::from_str("[null, true]").expect("Could not parse");
+        assert_eq!(
+            JsValue::Array(vec![JsValue::Null, JsValue::Bool(true)]),
+            json
+        );
+    }
+
+    #[tokio::test]
+    async fn test_self_reference() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const node = { name: 'a' };
+            node.parent = node;
+
+            const leaf = { value: 1 };
+            export const shared = [leaf, { leaf }];
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        runtime
+            .get_js_value(&module, "node")
+            .await
+            .expect_err("Did not detect a self-referencing value");
+
+        // The same object can appear more than once, as long as it does not contain itself
+        let leaf = JsValue::Object(BTreeMap::from([(
+            "value".to_string(),
+            JsValue::Number(1.0),
+        )]));
+        let shared = runtime
+            .get_js_value(&module, "shared")
+            .await
+            .expect("Could not get value");
+        assert_eq!(
+            JsValue::Array(vec![
+                leaf.clone(),
+                JsValue::Object(BTreeMap::from([("leaf".to_string(), leaf)])),
+            ]),
+            shared
+        );
+    }
+}
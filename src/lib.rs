@@ -135,6 +135,7 @@
 //! |             |                                                                                                   |                  |                                                                                 |
 //! |fs_import    | Enables importing arbitrary code from the filesystem through JS                                   |**NO**            |None                                                                             |
 //! |url_import   | Enables importing arbitrary code from network locations through JS                                |**NO**            |reqwest                                                                          |
+//! |timestamp    | Provides the `Timestamp` type, for reading JS `Date` objects                                      |yes               |None                                                                             |
 //! ----
 //!
 //! Please also check out [@Bromeon/js_sandbox](https://github.com/Bromeon/js-sandbox), another great crate in this niche
@@ -148,33 +149,85 @@ mod transl8;
 
 mod v8_serializer;
 
+mod archive_modules;
+mod argument_serializer;
+mod bytes;
+mod cancellation;
+mod embedded_modules;
 mod error;
 mod ext;
 mod inner_runtime;
+mod inspector;
+mod js_collections;
 mod js_function;
+mod js_value;
 mod module;
 mod module_cache;
 mod module_handle;
 mod module_loader;
 mod module_wrapper;
+mod platform;
+mod profiler;
+mod repl;
 mod runtime;
+mod runtime_builder;
+mod runtime_pool;
+mod source_map;
+mod template;
+mod thread_runtime;
+#[cfg(feature = "timestamp")]
+mod timestamp;
 mod traits;
 mod transpiler;
+mod type_check;
+mod type_descriptor;
 mod utilities;
+mod watchdog;
+mod worker;
 
 // Expose a few dependencies that could be useful
 pub use deno_core;
 pub use deno_core::serde_json;
 
 // Expose some important stuff from us
-pub use error::Error;
-pub use inner_runtime::FunctionArguments;
+pub use archive_modules::ArchiveModuleSource;
+pub use argument_serializer::{ArgumentSerializer, TaggedArgumentSerializer};
+pub use bytes::Bytes;
+pub use cancellation::CancellationToken;
+pub use embedded_modules::EmbeddedModuleProvider;
+pub use error::{Error, ErrorMapper, MappedError, Operation, SyntaxError};
+pub use ext::{node_compat::NodeCompat, rustyscript::CapturedOutput, RustyExtension};
+pub use inner_runtime::{FunctionArgument, FunctionArguments, GlobalSnapshot};
+pub use inspector::InspectorOptions;
+pub use js_collections::{JsMap, JsSet};
 pub use js_function::JsFunction;
+pub use js_value::JsValue;
 pub use module::{Module, StaticModule};
-pub use module_handle::ModuleHandle;
+pub use module_cache::{
+    CompiledModuleCacheProvider, MemoryModuleCacheProvider, ModuleCacheProvider,
+    TtlModuleCacheProvider,
+};
+pub use module_handle::{EntrypointMetadata, ModuleHandle};
+pub use module_loader::{DiagnosticCallback, ModuleLoadCallback, ModuleLoadEvent};
 pub use module_wrapper::ModuleWrapper;
-pub use runtime::{Runtime, RuntimeOptions, Undefined};
-pub use utilities::{evaluate, import, resolve_path, validate};
+pub use platform::PlatformOptions;
+pub use profiler::{CallFrame, CpuProfile, CpuProfileNode};
+pub use repl::{Repl, ReplOutput};
+pub use runtime::{EvalOutcome, Fingerprint, HeapStats, Runtime, RuntimeOptions, Undefined};
+pub use runtime_builder::RuntimeOptionsBuilder;
+pub use runtime_pool::{RuntimePool, RuntimePoolOptions};
+pub use template::TemplateHandle;
+pub use thread_runtime::{ThreadModuleHandle, ThreadRuntime};
+#[cfg(feature = "timestamp")]
+pub use timestamp::Timestamp;
+pub use transpiler::{EsTarget, TranspileOptions};
+pub use type_check::Diagnostic;
+pub use type_descriptor::TypeDescriptor;
+pub use utilities::{
+    evaluate, evaluate_with_timeout, import, resolve_path, resolve_path_from, transpile_module,
+    type_check, validate,
+};
+pub use worker::WorkerHandle;
 
 #[cfg(test)]
 mod test {
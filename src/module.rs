@@ -1,3 +1,4 @@
+use crate::{error::SyntaxError, traits::ToModuleSpecifier, transpiler};
 use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::fmt::Display;
@@ -51,6 +52,10 @@ macro_rules! module {
 pub struct Module {
     filename: String,
     contents: String,
+    bytes: Option<Vec<u8>>,
+
+    #[serde(default)]
+    remote: bool,
 }
 
 impl Display for Module {
@@ -81,9 +86,70 @@ impl Module {
         Self {
             filename: filename.to_string(),
             contents: contents.to_string(),
+            bytes: None,
+            remote: false,
+        }
+    }
+
+    /// Creates a new `Module` instance with the given filename and binary contents.
+    /// The contents are passed to the runtime as-is, and are not transpiled.
+    ///
+    /// # Arguments
+    /// * `filename` - A string representing the filename of the module.
+    /// * `bytes` - The raw contents of the module.
+    ///
+    /// # Returns
+    /// A new `Module` instance.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new_bytes("data.json", b"{\"a\": 1}".to_vec());
+    /// ```
+    pub fn new_bytes(filename: &str, bytes: Vec<u8>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            contents: String::new(),
+            bytes: Some(bytes),
+            remote: false,
         }
     }
 
+    /// Creates a new `Module` instance for a remote script
+    /// The contents are not fetched until the module is loaded into a runtime,
+    /// in the same way as a module imported from that URL
+    ///
+    /// # Arguments
+    /// * `url` - The `http` or `https` URL of the module.
+    ///
+    /// # Returns
+    /// A new `Module` instance, with empty contents.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rustyscript::{ Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// # tokio_test::block_on(async {
+    /// let module = Module::from_url("https://example.com/script.js");
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.load_module(&module).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    #[cfg(feature = "url_import")]
+    pub fn from_url(url: &str) -> Self {
+        Self {
+            filename: url.to_string(),
+            contents: String::new(),
+            bytes: None,
+            remote: true,
+        }
+    }
     /// Loads a `Module` instance from a file with the given filename.
     ///
     /// # Arguments
@@ -182,6 +248,136 @@ impl Module {
     pub fn contents(&self) -> &str {
         &self.contents
     }
+
+    /// Returns the binary contents of the module, if it was created with `Module::new_bytes`
+    ///
+    /// # Returns
+    /// A reference to the raw module contents, if any.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new_bytes("data.json", b"[1, 2]".to_vec());
+    /// assert_eq!(Some(&b"[1, 2]"[..]), module.bytes());
+    /// ```
+    pub fn bytes(&self) -> Option<&[u8]> {
+        self.bytes.as_deref()
+    }
+
+    /// Returns true if the module was created with `Module::from_url`,
+    /// and its contents will be fetched when it is loaded
+    pub fn is_remote(&self) -> bool {
+        self.remote
+    }
+
+    /// Returns true if the module is a JSON module, based on its extension
+    /// JSON modules are not transpiled, and their value is the module's default export
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new("config.json", "{ \"port\": 8080 }");
+    /// assert!(module.is_json());
+    /// ```
+    pub fn is_json(&self) -> bool {
+        Path::new(&self.filename)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+    }
+
+    /// Returns true if the module is a CommonJS module, based on its `.cjs` extension
+    /// See `RuntimeOptions::commonjs`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new("lib.cjs", "module.exports = 2;");
+    /// assert!(module.is_commonjs());
+    /// ```
+    pub fn is_commonjs(&self) -> bool {
+        Path::new(&self.filename)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("cjs"))
+    }
+
+    /// Checks the module for syntax errors, without creating a runtime or running any code
+    /// This is much faster than `rustyscript::validate`, and has no side effects
+    /// Modules created from bytes or URLs are not checked
+    ///
+    /// # Returns
+    /// A `Result` that is an error (`SyntaxError`) with the position of the first
+    /// syntax error, if there is one
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new("module.js", "const a = [1, 2;");
+    /// let error = module.validate_syntax().unwrap_err();
+    /// assert_eq!((1, 16), (error.line, error.column));
+    /// ```
+    pub fn validate_syntax(&self) -> Result<(), SyntaxError> {
+        if self.bytes.is_some() || self.remote {
+            return Ok(());
+        }
+
+        if self.is_json() {
+            return deno_core::serde_json::from_str::<deno_core::serde_json::Value>(&self.contents)
+                .map(|_| ())
+                .map_err(|e| SyntaxError {
+                    filename: self.filename.clone(),
+                    line: e.line(),
+                    column: e.column(),
+                    message: e.to_string(),
+                });
+        }
+
+        let specifier = self
+            .filename
+            .to_module_specifier()
+            .map_err(|e| SyntaxError {
+                filename: self.filename.clone(),
+                line: 1,
+                column: 1,
+                message: e.to_string(),
+            })?;
+        transpiler::validate_syntax(&specifier, &self.contents)
+    }
+
+    /// Determines if the module uses top-level await, without loading it
+    /// Modules that do must be loaded asynchronously
+    /// Modules that cannot be parsed, or that were created from bytes, return false
+    ///
+    /// # Returns
+    /// True if `await` or `for await` is used outside of any function
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let module = Module::new("module.js", "const value = await Promise.resolve(2);");
+    /// assert!(module.uses_top_level_await());
+    /// ```
+    pub fn uses_top_level_await(&self) -> bool {
+        if self.bytes.is_some() || self.is_json() {
+            return false;
+        }
+
+        match self.filename.to_module_specifier() {
+            Ok(specifier) => {
+                transpiler::uses_top_level_await(&specifier, &self.contents).unwrap_or(false)
+            }
+            Err(_) => false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -195,6 +391,67 @@ mod test_module {
         assert_eq!(module.contents(), "console.log('Hello, World!');");
     }
 
+    #[test]
+    fn test_new_bytes() {
+        let module = Module::new_bytes("module.json", vec![0xff, 0xfe]);
+        assert_eq!(module.filename(), "module.json");
+        assert_eq!(module.bytes(), Some(&[0xff, 0xfe][..]));
+    }
+
+    #[test]
+    fn test_validate_syntax() {
+        let module = Module::new(
+            "module.ts",
+            "
+            export function f(items: number[]): number {
+                return items.length;
+            }
+        ",
+        );
+        module.validate_syntax().expect("Valid module was rejected");
+
+        let module = Module::new(
+            "module.js",
+            "export function f() {
+    return [1, 2;
+}",
+        );
+        let error = module
+            .validate_syntax()
+            .expect_err("Did not detect bracket mismatch");
+        assert_eq!((2, 17), (error.line, error.column));
+
+        let module = Module::new("data.json", "{ \"a\": 1 }");
+        assert!(module.is_json());
+        module.validate_syntax().expect("Valid JSON was rejected");
+
+        let module = Module::new("data.json", "{ \"a\": 1, }");
+        module
+            .validate_syntax()
+            .expect_err("Did not detect trailing comma");
+    }
+
+    #[test]
+    fn test_uses_top_level_await() {
+        let module = Module::new(
+            "module.js",
+            "
+            const value = await Promise.resolve(2);
+            for await (const x of [1, 2]) {}
+        ",
+        );
+        assert!(module.uses_top_level_await());
+
+        let module = Module::new(
+            "module.ts",
+            "
+            export async function f(): Promise<number> { return await Promise.resolve(2); }
+            export const g = async () => { for await (const x of [1, 2]) {} };
+        ",
+        );
+        assert!(!module.uses_top_level_await());
+    }
+
     #[test]
     fn test_load_module() {
         let module =
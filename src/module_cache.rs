@@ -1,7 +1,12 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use deno_core::{
     parking_lot::Mutex, ModuleCodeBytes, ModuleSource, ModuleSourceCode, ModuleSpecifier,
+    SourceCodeCacheInfo,
 };
 
 /// Module cache provider trait
@@ -15,6 +20,22 @@ pub trait ModuleCacheProvider {
     /// Get a module source from the cache
     async fn get(&self, specifier: &ModuleSpecifier) -> Option<ModuleSource>;
 
+    /// Return false to keep a module out of the cache, such as one served from localhost
+    /// Called before the loader stores a module it has fetched
+    fn should_cache(&self, _specifier: &ModuleSpecifier) -> bool {
+        true
+    }
+
+    /// Return true to have the loader request V8's compiled code cache for
+    /// the modules it loads. They will be passed to `set_code_cache`
+    fn stores_code_cache(&self) -> bool {
+        false
+    }
+
+    /// Store V8's compiled code cache for a module
+    /// `hash` identifies the source the cache was generated for
+    fn set_code_cache(&self, _specifier: &ModuleSpecifier, _hash: u64, _code_cache: &[u8]) {}
+
     /// Clone a module source
     fn clone_source(&self, specifier: &ModuleSpecifier, source: &ModuleSource) -> ModuleSource {
         ModuleSource::new(
@@ -41,19 +62,256 @@ impl ModuleCacheProvider for () {
 }
 
 /// Default in-memory module cache provider
+/// Unbounded by default - use `MemoryModuleCacheProvider::with_capacity` to limit its size
 #[derive(Default)]
-pub struct MemoryModuleCacheProvider(Mutex<HashMap<ModuleSpecifier, ModuleSource>>);
+pub struct MemoryModuleCacheProvider {
+    cache: Mutex<LruCache>,
+    capacity: Option<usize>,
+}
+
+/// Module sources, along with when each was last used
+#[derive(Default)]
+struct LruCache {
+    entries: HashMap<ModuleSpecifier, (u64, ModuleSource)>,
+    clock: u64,
+}
+
+impl LruCache {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl MemoryModuleCacheProvider {
+    /// Create a cache holding at most `max_entries` modules
+    /// Once full, storing a new module evicts the least recently used one
+    pub fn with_capacity(max_entries: usize) -> Self {
+        Self {
+            cache: Default::default(),
+            capacity: Some(max_entries),
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl ModuleCacheProvider for MemoryModuleCacheProvider {
     async fn set(&self, specifier: &ModuleSpecifier, source: ModuleSource) {
-        let cache = &mut self.0.lock();
-        cache.insert(specifier.clone(), source);
+        let cache = &mut self.cache.lock();
+        if let Some(capacity) = self.capacity {
+            if capacity == 0 {
+                return;
+            }
+
+            while !cache.entries.contains_key(specifier) && cache.entries.len() >= capacity {
+                let oldest = cache
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (last_used, _))| *last_used)
+                    .map(|(specifier, _)| specifier.clone());
+                match oldest {
+                    Some(oldest) => cache.entries.remove(&oldest),
+                    None => break,
+                };
+            }
+        }
+
+        let now = cache.tick();
+        cache.entries.insert(specifier.clone(), (now, source));
     }
 
     async fn get(&self, specifier: &ModuleSpecifier) -> Option<ModuleSource> {
-        let cache = &self.0.lock();
-        let source = cache.get(specifier)?;
+        let cache = &mut self.cache.lock();
+        let now = cache.tick();
+        let (last_used, source) = cache.entries.get_mut(specifier)?;
+        *last_used = now;
         Some(Self::clone_source(self, specifier, source))
     }
 }
+
+/// Wraps another cache provider, and forgets modules once they have been cached for too long
+/// Useful for remote modules that may change, such as scripts hosted on a CDN - an expired
+/// module is fetched again the next time it is imported
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ MemoryModuleCacheProvider, RuntimeOptions, TtlModuleCacheProvider };
+/// use std::{ rc::Rc, time::Duration };
+///
+/// let options = RuntimeOptions {
+///     module_cache: Rc::new(TtlModuleCacheProvider::new(
+///         Duration::from_secs(60),
+///         MemoryModuleCacheProvider::default(),
+///     )),
+///     ..Default::default()
+/// };
+/// ```
+pub struct TtlModuleCacheProvider<C> {
+    ttl: Duration,
+    inner: C,
+    cached_at: Mutex<HashMap<ModuleSpecifier, Instant>>,
+}
+
+impl<C> TtlModuleCacheProvider<C>
+where
+    C: ModuleCacheProvider,
+{
+    /// Wrap a cache provider, so that its modules expire after `ttl`
+    pub fn new(ttl: Duration, inner: C) -> Self {
+        Self {
+            ttl,
+            inner,
+            cached_at: Default::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> ModuleCacheProvider for TtlModuleCacheProvider<C>
+where
+    C: ModuleCacheProvider + Send + Sync,
+{
+    async fn set(&self, specifier: &ModuleSpecifier, source: ModuleSource) {
+        self.cached_at
+            .lock()
+            .insert(specifier.clone(), Instant::now());
+        self.inner.set(specifier, source).await;
+    }
+
+    async fn get(&self, specifier: &ModuleSpecifier) -> Option<ModuleSource> {
+        let fresh = self
+            .cached_at
+            .lock()
+            .get(specifier)
+            .is_some_and(|cached_at| cached_at.elapsed() < self.ttl);
+        if !fresh {
+            return None;
+        }
+
+        self.inner.get(specifier).await
+    }
+
+    fn should_cache(&self, specifier: &ModuleSpecifier) -> bool {
+        self.inner.should_cache(specifier)
+    }
+
+    fn stores_code_cache(&self) -> bool {
+        self.inner.stores_code_cache()
+    }
+
+    fn set_code_cache(&self, specifier: &ModuleSpecifier, hash: u64, code_cache: &[u8]) {
+        self.inner.set_code_cache(specifier, hash, code_cache);
+    }
+
+    fn clone_source(&self, specifier: &ModuleSpecifier, source: &ModuleSource) -> ModuleSource {
+        self.inner.clone_source(specifier, source)
+    }
+}
+
+/// In-memory module cache provider that also keeps V8's compiled code cache
+/// for each module. Sources returned from the cache carry the code cache, which
+/// allows V8 to skip parsing and compiling them when they are loaded again
+#[derive(Default)]
+pub struct CompiledModuleCacheProvider {
+    sources: Mutex<HashMap<ModuleSpecifier, ModuleSource>>,
+    code_cache: Mutex<HashMap<ModuleSpecifier, (u64, Vec<u8>)>>,
+}
+
+#[async_trait::async_trait]
+impl ModuleCacheProvider for CompiledModuleCacheProvider {
+    async fn set(&self, specifier: &ModuleSpecifier, source: ModuleSource) {
+        let cache = &mut self.sources.lock();
+        cache.insert(specifier.clone(), source);
+    }
+
+    async fn get(&self, specifier: &ModuleSpecifier) -> Option<ModuleSource> {
+        let source = {
+            let cache = &self.sources.lock();
+            Self::clone_source(self, specifier, cache.get(specifier)?)
+        };
+
+        let code_cache =
+            self.code_cache
+                .lock()
+                .get(specifier)
+                .map(|(hash, data)| SourceCodeCacheInfo {
+                    hash: *hash,
+                    data: Some(Cow::Owned(data.clone())),
+                });
+
+        Some(ModuleSource::new(
+            source.module_type,
+            source.code,
+            specifier,
+            code_cache.or(source.code_cache),
+        ))
+    }
+
+    fn stores_code_cache(&self) -> bool {
+        true
+    }
+
+    fn set_code_cache(&self, specifier: &ModuleSpecifier, hash: u64, code_cache: &[u8]) {
+        let cache = &mut self.code_cache.lock();
+        cache.insert(specifier.clone(), (hash, code_cache.to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod test_module_cache {
+    use super::*;
+    use crate::traits::ToModuleSpecifier;
+
+    fn source(specifier: &ModuleSpecifier) -> ModuleSource {
+        ModuleSource::new(
+            deno_core::ModuleType::JavaScript,
+            ModuleSourceCode::String("export const value = 2;".to_string().into()),
+            specifier,
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_capacity() {
+        let cache_provider = MemoryModuleCacheProvider::with_capacity(2);
+        let a = "file:///a.js".to_module_specifier().unwrap();
+        let b = "file:///b.js".to_module_specifier().unwrap();
+        let c = "file:///c.js".to_module_specifier().unwrap();
+
+        cache_provider.set(&a, source(&a)).await;
+        cache_provider.set(&b, source(&b)).await;
+
+        // Using `a` leaves `b` as the least recently used entry
+        assert!(cache_provider.get(&a).await.is_some());
+        cache_provider.set(&c, source(&c)).await;
+
+        assert!(cache_provider.get(&a).await.is_some());
+        assert!(cache_provider.get(&b).await.is_none());
+        assert!(cache_provider.get(&c).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_compiled_cache() {
+        let cache_provider = CompiledModuleCacheProvider::default();
+        let specifier = "file:///test.js".to_module_specifier().unwrap();
+        let source = ModuleSource::new(
+            deno_core::ModuleType::JavaScript,
+            ModuleSourceCode::String("export const value = 2;".to_string().into()),
+            &specifier,
+            Some(SourceCodeCacheInfo {
+                hash: 1,
+                data: None,
+            }),
+        );
+        cache_provider.set(&specifier, source).await;
+
+        let source = cache_provider.get(&specifier).await.unwrap();
+        assert!(source.code_cache.unwrap().data.is_none());
+
+        cache_provider.set_code_cache(&specifier, 1, &[1, 2, 3]);
+        let source = cache_provider.get(&specifier).await.unwrap();
+        let code_cache = source.code_cache.expect("Expected a code cache");
+        assert_eq!(1, code_cache.hash);
+        assert_eq!(Some(&[1u8, 2, 3][..]), code_cache.data.as_deref());
+    }
+}
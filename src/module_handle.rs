@@ -1,14 +1,29 @@
 use deno_core::v8;
-use deno_core::ModuleId;
+use deno_core::{serde_json, ModuleId};
+use std::collections::HashMap;
 
-use crate::Module;
+use crate::{Error, Module};
+
+/// Information a module can provide alongside its entrypoint
+/// `rustyscript.register_entrypoint(f, { name, description })`
+#[derive(Clone, Debug, Eq, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct EntrypointMetadata {
+    /// A name for the entrypoint
+    pub name: Option<String>,
+
+    /// A description of what the entrypoint does
+    pub description: Option<String>,
+}
 
 /// Represents a loaded instance of a module within a runtime
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct ModuleHandle {
     entrypoint: Option<v8::Global<v8::Function>>,
+    entrypoint_metadata: Option<EntrypointMetadata>,
+    named_entrypoints: HashMap<String, v8::Global<v8::Function>>,
     module_id: ModuleId,
     module: Module,
+    evaluation_result: Option<serde_json::Value>,
 }
 
 impl ModuleHandle {
@@ -21,10 +36,34 @@ impl ModuleHandle {
         Self {
             module_id,
             entrypoint,
+            entrypoint_metadata: None,
+            named_entrypoints: HashMap::new(),
             module: module.clone(),
+            evaluation_result: None,
         }
     }
 
+    /// Attach the result of evaluating the module
+    pub fn with_evaluation_result(mut self, evaluation_result: Option<serde_json::Value>) -> Self {
+        self.evaluation_result = evaluation_result;
+        self
+    }
+
+    /// Attach the metadata registered alongside the entrypoint
+    pub fn with_entrypoint_metadata(mut self, metadata: Option<EntrypointMetadata>) -> Self {
+        self.entrypoint_metadata = metadata;
+        self
+    }
+
+    /// Attach the functions the module registered by name
+    pub fn with_named_entrypoints(
+        mut self,
+        named_entrypoints: HashMap<String, v8::Global<v8::Function>>,
+    ) -> Self {
+        self.named_entrypoints = named_entrypoints;
+        self
+    }
+
     /// Return this module's contents
     pub fn module(&self) -> &Module {
         &self.module
@@ -39,4 +78,56 @@ impl ModuleHandle {
     pub fn entrypoint(&self) -> &Option<v8::Global<v8::Function>> {
         &self.entrypoint
     }
+
+    /// Returns true if the module has an entrypoint, either registered by the module
+    /// or found using the runtime's default entrypoint
+    pub fn has_entrypoint(&self) -> bool {
+        self.entrypoint.is_some()
+    }
+
+    /// Return a function the module registered with `rustyscript.register_named`
+    pub fn named_entrypoint(&self, name: &str) -> Option<&v8::Global<v8::Function>> {
+        self.named_entrypoints.get(name)
+    }
+
+    /// Return the metadata the module registered alongside its entrypoint, if any
+    pub fn entrypoint_metadata(&self) -> Option<&EntrypointMetadata> {
+        self.entrypoint_metadata.as_ref()
+    }
+
+    /// Return the module's exports as they were once its evaluation completed,
+    /// including any top-level `await`, deserialized into `T`
+    /// Only captured if `RuntimeOptions::capture_evaluation_result` is set
+    /// Exports that cannot be represented as JSON, such as functions, are left out
+    ///
+    /// # Returns
+    /// A `Result` containing the exports, or None if they were not captured,
+    /// or an error (`Error`) if they cannot be deserialized into `T`
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ serde_json, Runtime, RuntimeOptions, Module };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     capture_evaluation_result: true,
+    ///     ..Default::default()
+    /// })?;
+    /// let module = Module::new("test.js", "export const x = await Promise.resolve(2);");
+    /// let module = runtime.load_module(&module).await?;
+    /// let result: Option<serde_json::Value> = module.evaluation_result()?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn evaluation_result<T>(&self) -> Result<Option<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match &self.evaluation_result {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+            None => Ok(None),
+        }
+    }
 }
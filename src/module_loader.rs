@@ -1,16 +1,170 @@
-use crate::{module_cache::ModuleCacheProvider, transpiler};
+use crate::{
+    ext,
+    module_cache::ModuleCacheProvider,
+    source_map::SourceMap,
+    traits::ToModuleSpecifier,
+    transpiler::{self, EsTarget},
+    ArchiveModuleSource, Diagnostic, EmbeddedModuleProvider, Error,
+};
 use deno_core::{
     anyhow::{self, anyhow},
-    ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSourceCode, ModuleSpecifier, ModuleType,
+    serde_json, ModuleCodeBytes, ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSourceCode,
+    ModuleSpecifier, ModuleType, RequestedModuleType, SourceCodeCacheInfo,
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
-use std::{collections::HashSet, rc::Rc, sync::Mutex};
+
+/// Details of a module loaded by the runtime
+/// See `RuntimeOptions::on_module_load`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleLoadEvent {
+    /// The specifier the module was loaded from
+    pub specifier: ModuleSpecifier,
+
+    /// Length of the module's code in bytes, after transpiling
+    pub source_length: usize,
+
+    /// Time taken to read or fetch the module, and transpile it
+    pub duration: Duration,
+
+    /// True if the module was served by the module cache
+    pub cache_hit: bool,
+}
+
+impl ModuleLoadEvent {
+    fn new(
+        specifier: &ModuleSpecifier,
+        source: &ModuleSource,
+        start: Instant,
+        cache_hit: bool,
+    ) -> Self {
+        let source_length = match &source.code {
+            ModuleSourceCode::String(code) => code.as_str().len(),
+            ModuleSourceCode::Bytes(bytes) => bytes.as_bytes().len(),
+        };
+        Self {
+            specifier: specifier.clone(),
+            source_length,
+            duration: start.elapsed(),
+            cache_hit,
+        }
+    }
+}
+
+/// Called for each module loaded by the runtime
+pub type ModuleLoadCallback = Arc<dyn Fn(&ModuleLoadEvent)>;
+
+/// A function called with each diagnostic found in a module as it is loaded
+/// See `RuntimeOptions::on_diagnostic`
+pub type DiagnosticCallback = Arc<dyn Fn(&ModuleSpecifier, &Diagnostic)>;
+
+/// Warns about syntax newer than a target version, as modules are loaded
+#[derive(Clone)]
+struct TargetCheck {
+    target: EsTarget,
+    on_diagnostic: DiagnosticCallback,
+}
+
+impl TargetCheck {
+    /// Report any syntax in a module's code that is newer than the target
+    /// Code that cannot be parsed is skipped, since loading it reports the error
+    fn check(&self, ms: &ModuleSpecifier, code: &str) {
+        if let Ok(diagnostics) = transpiler::check_target(ms, code, self.target) {
+            for diagnostic in &diagnostics {
+                (self.on_diagnostic)(ms, diagnostic);
+            }
+        }
+    }
+}
+
+/// How the loader prepares a module's code before it runs
+#[derive(Clone, Default)]
+struct CodeOptions {
+    import_meta: Option<Rc<str>>,
+    commonjs: bool,
+    target_check: Option<TargetCheck>,
+}
+
+/// A set of in-memory modules, which imports are resolved against
+/// before the filesystem or network are consulted
+#[derive(Default)]
+pub struct VirtualModuleSource(HashMap<ModuleSpecifier, String>);
+
+impl VirtualModuleSource {
+    /// Create a new source from a map of filenames to module contents
+    /// Relative filenames are resolved to the current working dir
+    pub fn new(modules: HashMap<String, String>) -> Self {
+        Self(
+            modules
+                .into_iter()
+                .filter_map(|(filename, contents)| {
+                    Some((filename.to_module_specifier().ok()?, contents))
+                })
+                .collect(),
+        )
+    }
+
+    /// Add a set of embedded modules to the source
+    pub fn with_embedded(mut self, embedded: &EmbeddedModuleProvider) -> Self {
+        self.0
+            .extend(embedded.sources().filter_map(|(filename, contents)| {
+                Some((filename.to_module_specifier().ok()?, contents))
+            }));
+        self
+    }
+
+    /// Add the files of a module archive to the source
+    pub fn with_archive(mut self, archive: &ArchiveModuleSource) -> Self {
+        self.0
+            .extend(archive.sources().filter_map(|(filename, contents)| {
+                Some((filename.to_module_specifier().ok()?, contents.clone()))
+            }));
+        self
+    }
+
+    /// Returns true if a module exists for the given specifier
+    pub fn contains(&self, specifier: &ModuleSpecifier) -> bool {
+        self.0.contains_key(specifier)
+    }
+
+    /// Get the contents of the module for the given specifier, if it exists
+    pub fn get(&self, specifier: &ModuleSpecifier) -> Option<&str> {
+        self.0.get(specifier).map(String::as_str)
+    }
+}
 
 pub struct RustyLoader {
     fs_whlist: Mutex<HashSet<String>>,
     cache_provider: Rc<dyn ModuleCacheProvider>,
+    virtual_modules: VirtualModuleSource,
+    byte_modules: Mutex<HashMap<ModuleSpecifier, Vec<u8>>>,
+    referrers: Mutex<HashMap<ModuleSpecifier, ModuleSpecifier>>,
+    imports: Mutex<HashMap<ModuleSpecifier, Vec<ModuleSpecifier>>>,
+    source_maps: Rc<RefCell<HashMap<ModuleSpecifier, SourceMap>>>,
+    base_dir: RefCell<Option<PathBuf>>,
+    on_load: Option<ModuleLoadCallback>,
+    code_options: CodeOptions,
+
+    #[cfg(feature = "url_import")]
+    http_client: reqwest::Client,
+}
+
+/// The client used for URL imports when the runtime is not given one
+#[cfg(feature = "url_import")]
+fn default_http_client() -> reqwest::Client {
+    static CLIENT: std::sync::OnceLock<reqwest::Client> = std::sync::OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new).clone()
 }
 
-#[allow(unreachable_code)]
 impl ModuleLoader for RustyLoader {
     fn resolve(
         &self,
@@ -18,11 +172,246 @@ impl ModuleLoader for RustyLoader {
         referrer: &str,
         _kind: deno_core::ResolutionKind,
     ) -> Result<ModuleSpecifier, anyhow::Error> {
-        let url = deno_core::resolve_import(specifier, referrer)?;
+        let url = match self.base_dir.borrow().as_ref() {
+            // Paths imported without a module as referrer are relative to the base directory
+            Some(base_dir)
+                if ModuleSpecifier::parse(referrer).is_err()
+                    && ModuleSpecifier::parse(specifier).is_err() =>
+            {
+                deno_core::resolve_path(specifier, base_dir)?
+            }
+            _ => deno_core::resolve_import(specifier, referrer)?,
+        };
         if referrer == "." {
             self.whitelist_add(url.as_str());
+        } else if let Ok(referrer) = ModuleSpecifier::parse(referrer) {
+            self.referrer_add(&url, referrer);
+        }
+
+        self.check_import(&url, specifier)
+            .map_err(|e| self.with_import_chain(&url, e))?;
+        Ok(url)
+    }
+
+    fn load(
+        &self,
+        module_specifier: &ModuleSpecifier,
+        _maybe_referrer: Option<&ModuleSpecifier>,
+        _is_dyn_import: bool,
+        requested_module_type: RequestedModuleType,
+    ) -> deno_core::ModuleLoadResponse {
+        match self.load_module(module_specifier, requested_module_type) {
+            ModuleLoadResponse::Sync(result) => {
+                if let Ok(source) = &result {
+                    Self::source_map_add(&self.source_maps, module_specifier, source);
+                }
+                ModuleLoadResponse::Sync(
+                    result.map_err(|e| self.with_import_chain(module_specifier, e)),
+                )
+            }
+            ModuleLoadResponse::Async(future) => {
+                let chain = self.import_chain(module_specifier);
+                let source_maps = Rc::clone(&self.source_maps);
+                let specifier = module_specifier.clone();
+                ModuleLoadResponse::Async(Box::pin(async move {
+                    let source = future
+                        .await
+                        .map_err(|e| Self::append_import_chain(e, chain))?;
+                    Self::source_map_add(&source_maps, &specifier, &source);
+                    Ok(source)
+                }))
+            }
+        }
+    }
+
+    fn code_cache_ready(
+        &self,
+        module_specifier: ModuleSpecifier,
+        hash: u64,
+        code_cache: &[u8],
+    ) -> Pin<Box<dyn Future<Output = ()>>> {
+        self.cache_provider
+            .set_code_cache(&module_specifier, hash, code_cache);
+        Box::pin(async {})
+    }
+}
+
+#[allow(dead_code)]
+#[allow(unreachable_code)]
+impl RustyLoader {
+    pub fn new(
+        cache_provider: Rc<dyn ModuleCacheProvider>,
+        virtual_modules: VirtualModuleSource,
+    ) -> Self {
+        Self {
+            fs_whlist: Mutex::new(Default::default()),
+            cache_provider,
+            virtual_modules,
+            byte_modules: Mutex::new(Default::default()),
+            referrers: Mutex::new(Default::default()),
+            imports: Mutex::new(Default::default()),
+            source_maps: Default::default(),
+            base_dir: RefCell::new(None),
+            on_load: None,
+            code_options: CodeOptions::default(),
+
+            #[cfg(feature = "url_import")]
+            http_client: default_http_client(),
+        }
+    }
+
+    /// Call a function for each module that is loaded
+    pub fn with_load_callback(mut self, on_load: ModuleLoadCallback) -> Self {
+        self.on_load = Some(on_load);
+        self
+    }
+
+    /// Load local `.cjs` files as CommonJS, with `module.exports` as their default export
+    pub fn with_commonjs(mut self, commonjs: bool) -> Self {
+        self.code_options.commonjs = commonjs;
+        self
+    }
+
+    /// Add fields to the `import.meta` of each module loaded
+    pub fn with_import_meta(mut self, fields: &HashMap<String, serde_json::Value>) -> Self {
+        if !fields.is_empty() {
+            let fields = serde_json::Value::Object(fields.clone().into_iter().collect());
+            self.code_options.import_meta =
+                Some(format!("Object.assign(import.meta, {fields});").into());
+        }
+        self
+    }
+
+    /// Report syntax newer than `target` in each module loaded
+    pub fn with_target_check(
+        mut self,
+        target: EsTarget,
+        on_diagnostic: DiagnosticCallback,
+    ) -> Self {
+        self.code_options.target_check = Some(TargetCheck {
+            target,
+            on_diagnostic,
+        });
+        self
+    }
+
+    /// Report syntax newer than the configured target in a module's code, if there is a target
+    pub fn check_target(&self, ms: &ModuleSpecifier, code: &str) {
+        if let Some(target_check) = &self.code_options.target_check {
+            target_check.check(ms, code);
+        }
+    }
+
+    /// Add the configured `import.meta` fields to a module's code
+    /// The fields are assigned on the first line, so that line numbers are unchanged,
+    /// and the inline source map is shifted to match the columns of that line
+    pub fn add_import_meta(import_meta: Option<&str>, code: String) -> String {
+        let Some(import_meta) = import_meta else {
+            return code;
+        };
+
+        // A hashbang must stay at the very start of the module
+        let (line, code) = match code.strip_prefix("#!") {
+            Some(rest) => match rest.split_once('\n') {
+                Some((hashbang, rest)) => (1, format!("#!{hashbang}\n{import_meta}{rest}")),
+                None => return code,
+            },
+            None => (0, format!("{import_meta}{code}")),
+        };
+
+        let offset = u32::try_from(import_meta.encode_utf16().count()).unwrap_or(u32::MAX);
+        SourceMap::shift_inline(&code, line, offset).unwrap_or(code)
+    }
+
+    /// Returns the code assigning the configured `import.meta` fields, if there are any
+    pub fn import_meta(&self) -> Option<&str> {
+        self.code_options.import_meta.as_deref()
+    }
+
+    /// Report a loaded module to the load callback, if there is one
+    pub fn module_loaded(&self, event: ModuleLoadEvent) {
+        if let Some(on_load) = &self.on_load {
+            on_load(&event);
+        }
+    }
+
+    /// Use a specific client for URL imports, instead of the shared default
+    #[cfg(feature = "url_import")]
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Resolve relative module paths against a directory, instead of the working directory
+    pub fn with_base_dir(self, base_dir: PathBuf) -> Self {
+        self.set_base_dir(base_dir);
+        self
+    }
+
+    /// Change the directory relative module paths are resolved against
+    /// Modules that are already loaded are not affected
+    pub fn set_base_dir(&self, base_dir: PathBuf) {
+        self.base_dir.replace(Some(base_dir));
+    }
+
+    /// The directory relative paths are resolved against - the base directory if set,
+    /// or the working directory
+    pub fn current_dir(&self) -> Result<PathBuf, Error> {
+        let current_dir = std::env::current_dir()?;
+        Ok(match self.base_dir.borrow().as_ref() {
+            Some(base_dir) => current_dir.join(base_dir),
+            None => current_dir,
+        })
+    }
+
+    /// Convert a module's filename into a specifier, relative to the base directory if set
+    pub fn resolve_path(&self, path: &str) -> Result<ModuleSpecifier, Error> {
+        match self.base_dir.borrow().as_ref() {
+            Some(base_dir) => Ok(deno_core::resolve_path(path, base_dir)?),
+            None => path.to_module_specifier(),
         }
+    }
+
+    /// Read a module for `require`, from memory or the filesystem
+    /// Only local files can be required, and the same permissions as imports apply
+    ///
+    /// # Arguments
+    /// * `specifier` - The path passed to `require`
+    /// * `referrer` - The module calling `require`, or an empty string to resolve
+    ///   the path relative to the base directory
+    pub fn require_source(
+        &self,
+        specifier: &str,
+        referrer: &str,
+    ) -> Result<(ModuleSpecifier, String), Error> {
+        let url = if referrer.is_empty() {
+            let url = self.resolve_path(specifier)?;
+            self.check_import(&url, specifier)?;
+            url
+        } else {
+            self.resolve(specifier, referrer, deno_core::ResolutionKind::Import)?
+        };
 
+        if url.scheme() != "file" {
+            return Err(Error::Runtime(format!(
+                "require() can only load local files: {specifier}"
+            )));
+        }
+
+        if let Some(code) = self.virtual_modules.get(&url) {
+            return Ok((url, code.to_string()));
+        }
+
+        let path = url
+            .to_file_path()
+            .map_err(|_| Error::ModuleNotFound(format!("{specifier} is not a file path")))?;
+        let code = std::fs::read_to_string(path)
+            .map_err(|e| Error::ModuleNotFound(format!("{specifier}: {e}")))?;
+        Ok((url, code))
+    }
+
+    /// Check that an import is permitted
+    fn check_import(&self, url: &ModuleSpecifier, specifier: &str) -> Result<(), anyhow::Error> {
         // We check permissions first
         match url.scheme() {
             // Remote fetch imports
@@ -35,7 +424,7 @@ impl ModuleLoader for RustyLoader {
             "file" =>
             {
                 #[cfg(not(feature = "fs_import"))]
-                if !self.whitelist_has(url.as_str()) {
+                if !self.whitelist_has(url.as_str()) && !self.virtual_modules.contains(&url) {
                     return Err(anyhow!("requested module is not loaded: {specifier}"));
                 }
             }
@@ -51,27 +440,67 @@ impl ModuleLoader for RustyLoader {
             }
         }
 
-        Ok(url)
+        Ok(())
     }
 
-    fn load(
+    fn load_module(
         &self,
         module_specifier: &ModuleSpecifier,
-        _maybe_referrer: Option<&ModuleSpecifier>,
-        _is_dyn_import: bool,
-        _requested_module_type: deno_core::RequestedModuleType,
-    ) -> deno_core::ModuleLoadResponse {
+        requested_module_type: RequestedModuleType,
+    ) -> ModuleLoadResponse {
+        let start = Instant::now();
+
+        // Import attributes take priority over the extension
+        let module_type = match Self::module_type(module_specifier, &requested_module_type) {
+            Ok(module_type) => module_type,
+            Err(e) => return ModuleLoadResponse::Sync(Err(e)),
+        };
+
+        // Binary modules loaded directly by the runtime
+        if let Some(bytes) = self.bytes_get(module_specifier) {
+            let source = ModuleSource::new(
+                module_type,
+                ModuleSourceCode::Bytes(ModuleCodeBytes::Boxed(bytes.into())),
+                module_specifier,
+                None,
+            );
+            self.module_loaded(ModuleLoadEvent::new(
+                module_specifier,
+                &source,
+                start,
+                false,
+            ));
+            return ModuleLoadResponse::Sync(Ok(source));
+        }
+
+        // In-memory modules take priority over the filesystem and network
+        if let Some(code) = self.virtual_modules.get(module_specifier) {
+            let result =
+                Self::load_virtual(module_specifier, module_type, code, &self.code_options);
+            if let Ok(source) = &result {
+                self.module_loaded(ModuleLoadEvent::new(module_specifier, source, start, false));
+            }
+            return ModuleLoadResponse::Sync(result);
+        }
+
         // We check permissions first
         match module_specifier.scheme() {
             // Remote fetch imports
             #[cfg(feature = "url_import")]
             "https" | "http" => {
+                let client = self.http_client.clone();
                 let future = Self::load_external(
                     module_specifier.clone(),
+                    module_type,
                     Rc::clone(&self.cache_provider),
-                    |specifier| async {
-                        let response = reqwest::get(specifier).await?;
-                        Ok(response.text().await?)
+                    self.on_load.clone(),
+                    self.code_options.clone(),
+                    move |specifier| {
+                        let client = client.clone();
+                        async move {
+                            let response = client.get(specifier).send().await?;
+                            Ok(response.text().await?)
+                        }
                     },
                 );
                 ModuleLoadResponse::Async(Box::pin(future))
@@ -81,7 +510,10 @@ impl ModuleLoader for RustyLoader {
             "file" => {
                 let future = Self::load_external(
                     module_specifier.clone(),
+                    module_type,
                     Rc::clone(&self.cache_provider),
+                    self.on_load.clone(),
+                    self.code_options.clone(),
                     |specifier| async move {
                         let path = specifier
                             .to_file_path()
@@ -99,14 +531,138 @@ impl ModuleLoader for RustyLoader {
             ))),
         }
     }
-}
 
-#[allow(dead_code)]
-impl RustyLoader {
-    pub fn new(cache_provider: Rc<dyn ModuleCacheProvider>) -> Self {
-        Self {
-            fs_whlist: Mutex::new(Default::default()),
-            cache_provider,
+    /// Record the module that first imported a specifier
+    /// and add the specifier to the referrer's imports
+    fn referrer_add(&self, specifier: &ModuleSpecifier, referrer: ModuleSpecifier) {
+        if let Ok(mut imports) = self.imports.lock() {
+            let imports = imports.entry(referrer.clone()).or_default();
+            if !imports.contains(specifier) {
+                imports.push(specifier.clone());
+            }
+        }
+
+        if let Ok(mut referrers) = self.referrers.lock() {
+            if specifier != &referrer {
+                referrers.entry(specifier.clone()).or_insert(referrer);
+            }
+        }
+    }
+
+    /// Every module imported by a module, directly or through other imports
+    /// The module's own imports come first, in the order they were resolved,
+    /// followed by their imports, and so on
+    ///
+    /// deno_core does not expose its module map, so this walks the imports
+    /// recorded as the loader resolves them
+    pub fn dependencies(&self, specifier: &ModuleSpecifier) -> Vec<ModuleSpecifier> {
+        let Ok(imports) = self.imports.lock() else {
+            return vec![];
+        };
+
+        let mut dependencies: Vec<ModuleSpecifier> = vec![];
+        let mut pending = VecDeque::from([specifier]);
+        while let Some(next) = pending.pop_front() {
+            for import in imports.get(next).into_iter().flatten() {
+                if import != specifier && !dependencies.contains(import) {
+                    dependencies.push(import.clone());
+                    pending.push_back(import);
+                }
+            }
+        }
+
+        dependencies
+    }
+
+    /// Describe the chain of imports that led to a module
+    /// For example `file:///main.js -> file:///a.js -> file:///broken.js`
+    /// Returns `None` if the module was not imported by another
+    fn import_chain(&self, specifier: &ModuleSpecifier) -> Option<String> {
+        let referrers = self.referrers.lock().ok()?;
+
+        let mut chain = vec![specifier];
+        while let Some(referrer) = referrers.get(chain[chain.len() - 1]) {
+            if chain.contains(&referrer) {
+                break;
+            }
+            chain.push(referrer);
+        }
+
+        if chain.len() < 2 {
+            return None;
+        }
+
+        let chain: Vec<&str> = chain.iter().rev().map(|s| s.as_str()).collect();
+        Some(chain.join(" -> "))
+    }
+
+    /// Add the chain of imports that led to a module to an error
+    fn with_import_chain(&self, specifier: &ModuleSpecifier, e: anyhow::Error) -> anyhow::Error {
+        Self::append_import_chain(e, self.import_chain(specifier))
+    }
+
+    fn append_import_chain(e: anyhow::Error, chain: Option<String>) -> anyhow::Error {
+        match chain {
+            Some(chain) => anyhow!("{e}\nImport chain: {chain}"),
+            None => e,
+        }
+    }
+
+    /// Store the inline source map of a loaded module, if it has one
+    fn source_map_add(
+        source_maps: &RefCell<HashMap<ModuleSpecifier, SourceMap>>,
+        specifier: &ModuleSpecifier,
+        source: &ModuleSource,
+    ) {
+        if let ModuleSourceCode::String(code) = &source.code {
+            Self::source_map_add_code(source_maps, specifier, code.as_str());
+        }
+    }
+
+    fn source_map_add_code(
+        source_maps: &RefCell<HashMap<ModuleSpecifier, SourceMap>>,
+        specifier: &ModuleSpecifier,
+        code: &str,
+    ) {
+        if let Some(source_map) = SourceMap::from_inline(code) {
+            source_maps
+                .borrow_mut()
+                .insert(specifier.clone(), source_map);
+        }
+    }
+
+    /// Store the inline source map of code loaded directly by the runtime
+    pub fn code_add(&self, specifier: &ModuleSpecifier, code: &str) {
+        Self::source_map_add_code(&self.source_maps, specifier, code);
+    }
+
+    /// Translate a 1-based position in a loaded module's transpiled code
+    /// to a 1-based position in its original source
+    pub fn original_position(
+        &self,
+        specifier: &ModuleSpecifier,
+        line: u32,
+        column: u32,
+    ) -> Option<(u32, u32)> {
+        self.source_maps
+            .borrow()
+            .get(specifier)?
+            .original_position(line, column)
+    }
+
+    /// Register the binary contents of a module, to be served by the next load
+    pub fn bytes_add(&self, specifier: &ModuleSpecifier, bytes: Vec<u8>) {
+        self.whitelist_add(specifier.as_str());
+        if let Ok(mut byte_modules) = self.byte_modules.lock() {
+            byte_modules.insert(specifier.clone(), bytes);
+        }
+    }
+
+    pub fn bytes_get(&self, specifier: &ModuleSpecifier) -> Option<Vec<u8>> {
+        if let Ok(byte_modules) = self.byte_modules.lock() {
+            byte_modules.get(specifier).cloned()
+        } else {
+            None
         }
     }
 
@@ -124,36 +680,129 @@ impl RustyLoader {
         }
     }
 
+    /// Determine the type of a module, using the import attributes
+    /// if there are any, or the extension otherwise
+    fn module_type(
+        ms: &ModuleSpecifier,
+        requested_module_type: &RequestedModuleType,
+    ) -> Result<ModuleType, deno_core::error::AnyError> {
+        match requested_module_type {
+            RequestedModuleType::Json => Ok(ModuleType::Json),
+            RequestedModuleType::Other(t) => {
+                Err(anyhow!("unsupported module type `{t}` requested for {ms}"))
+            }
+            RequestedModuleType::None if ms.path().ends_with(".json") => Ok(ModuleType::Json),
+            RequestedModuleType::None => Ok(ModuleType::JavaScript),
+        }
+    }
+
+    /// Prepare the code of a module for the runtime
+    /// JSON is validated, local `.cjs` files are wrapped as CommonJS if enabled,
+    /// and anything else is transpiled, and given any `import.meta` fields
+    fn prepare_code(
+        ms: &ModuleSpecifier,
+        module_type: &ModuleType,
+        code: &str,
+        options: &CodeOptions,
+    ) -> Result<String, deno_core::error::AnyError> {
+        let import_meta = options.import_meta.as_deref();
+        let target_check = |code: &str| {
+            if let Some(target_check) = &options.target_check {
+                target_check.check(ms, code);
+            }
+        };
+        let is_commonjs = ms.scheme() == "file"
+            && Path::new(ms.path())
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("cjs"));
+
+        match module_type {
+            ModuleType::Json => {
+                deno_core::serde_json::from_str::<deno_core::serde_json::Value>(code).map_err(
+                    |e| anyhow!("{ms} was imported as JSON, but is not valid JSON: {e}"),
+                )?;
+                Ok(code.to_string())
+            }
+            _ if options.commonjs && is_commonjs => {
+                target_check(code);
+                Ok(Self::add_import_meta(
+                    import_meta,
+                    ext::rustyscript::commonjs_module(ms, code)?,
+                ))
+            }
+            _ => {
+                let code = transpiler::transpile(ms, code)?;
+                target_check(&code);
+                Ok(Self::add_import_meta(import_meta, code))
+            }
+        }
+    }
+
+    fn load_virtual(
+        ms: &ModuleSpecifier,
+        module_type: ModuleType,
+        code: &str,
+        options: &CodeOptions,
+    ) -> Result<ModuleSource, deno_core::error::AnyError> {
+        let code = Self::prepare_code(ms, &module_type, code, options)?;
+        Ok(ModuleSource::new(
+            module_type,
+            ModuleSourceCode::String(code.into()),
+            ms,
+            None,
+        ))
+    }
+
     async fn load_external<F, Fut>(
         ms: ModuleSpecifier,
+        module_type: ModuleType,
         cp: Rc<dyn ModuleCacheProvider>,
+        on_load: Option<ModuleLoadCallback>,
+        options: CodeOptions,
         handler: F,
     ) -> Result<ModuleSource, deno_core::error::AnyError>
     where
         F: Fn(ModuleSpecifier) -> Fut,
         Fut: std::future::Future<Output = Result<String, deno_core::error::AnyError>>,
     {
+        let start = Instant::now();
         match cp.get(&ms).await {
-            Some(source) => Ok(source),
+            Some(source) => {
+                if let Some(on_load) = on_load {
+                    on_load(&ModuleLoadEvent::new(&ms, &source, start, true));
+                }
+                Ok(source)
+            }
             _ => {
-                let module_type = if ms.path().ends_with(".json") {
-                    ModuleType::Json
+                let code = handler(ms.clone()).await?;
+                let code = Self::prepare_code(&ms, &module_type, &code, &options)?;
+
+                // Ask V8 for a code cache if the provider can store one
+                let code_cache = if cp.stores_code_cache() {
+                    let mut hasher = DefaultHasher::new();
+                    code.hash(&mut hasher);
+                    Some(SourceCodeCacheInfo {
+                        hash: hasher.finish(),
+                        data: None,
+                    })
                 } else {
-                    ModuleType::JavaScript
+                    None
                 };
 
-                let code = handler(ms.clone()).await?;
-                let code = transpiler::transpile(&ms, &code)?;
-
                 let source = ModuleSource::new(
                     module_type,
                     ModuleSourceCode::String(code.into()),
                     &ms,
-                    None,
+                    code_cache,
                 );
 
-                cp.set(&ms, cp.clone_source(&ms, &source)).await;
+                if cp.should_cache(&ms) {
+                    cp.set(&ms, cp.clone_source(&ms, &source)).await;
+                }
 
+                if let Some(on_load) = on_load {
+                    on_load(&ModuleLoadEvent::new(&ms, &source, start, false));
+                }
                 Ok(source)
             }
         }
@@ -163,7 +812,7 @@ impl RustyLoader {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{module_cache::MemoryModuleCacheProvider, traits::ToModuleSpecifier};
+    use crate::module_cache::{MemoryModuleCacheProvider, TtlModuleCacheProvider};
 
     #[tokio::test]
     async fn test_loader() {
@@ -185,7 +834,7 @@ mod test {
             .await
             .expect("Expected to get cached source");
 
-        let loader = RustyLoader::new(Rc::new(cache_provider));
+        let loader = RustyLoader::new(Rc::new(cache_provider), Default::default());
         let response = loader.load(
             &specifier,
             None,
@@ -211,4 +860,151 @@ mod test {
             _ => panic!("Unexpected response"),
         }
     }
+
+    #[tokio::test]
+    async fn test_ttl_cache() {
+        let dir = std::env::temp_dir().join("rustyscript_ttl_cache");
+        std::fs::create_dir_all(&dir).expect("Could not create dir");
+        let path = dir.join("remote.js");
+        let specifier = ModuleSpecifier::from_file_path(&path).unwrap();
+
+        let cache_provider = TtlModuleCacheProvider::new(
+            std::time::Duration::from_millis(100),
+            MemoryModuleCacheProvider::default(),
+        );
+        let loader = RustyLoader::new(Rc::new(cache_provider), Default::default());
+        let load = |loader: &RustyLoader| {
+            let response = loader.load(
+                &specifier,
+                None,
+                false,
+                deno_core::RequestedModuleType::None,
+            );
+            async move {
+                match response {
+                    ModuleLoadResponse::Async(future) => {
+                        match future.await.expect("Expected to get source").code {
+                            ModuleSourceCode::String(s) => s.as_str().to_string(),
+                            _ => panic!("Unexpected source code type"),
+                        }
+                    }
+                    _ => panic!("Unexpected response"),
+                }
+            }
+        };
+
+        std::fs::write(&path, "export const value = 1;").expect("Could not write file");
+        assert_eq!("export const value = 1;", load(&loader).await);
+
+        // Still cached, so the change is not seen
+        std::fs::write(&path, "export const value = 2;").expect("Could not write file");
+        assert_eq!("export const value = 1;", load(&loader).await);
+
+        // Expired, so the module is read again
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+        assert_eq!("export const value = 2;", load(&loader).await);
+    }
+
+    #[tokio::test]
+    async fn test_should_cache() {
+        struct SelectiveCache(MemoryModuleCacheProvider);
+
+        #[async_trait::async_trait]
+        impl ModuleCacheProvider for SelectiveCache {
+            async fn set(&self, specifier: &ModuleSpecifier, source: ModuleSource) {
+                self.0.set(specifier, source).await
+            }
+
+            async fn get(&self, specifier: &ModuleSpecifier) -> Option<ModuleSource> {
+                self.0.get(specifier).await
+            }
+
+            fn should_cache(&self, specifier: &ModuleSpecifier) -> bool {
+                !specifier.path().ends_with("uncached.js")
+            }
+        }
+
+        let dir = std::env::temp_dir().join("rustyscript_should_cache");
+        std::fs::create_dir_all(&dir).expect("Could not create dir");
+
+        let cache_provider = Rc::new(SelectiveCache(MemoryModuleCacheProvider::default()));
+        let loader = RustyLoader::new(cache_provider.clone(), Default::default());
+        for filename in ["cached.js", "uncached.js"] {
+            let path = dir.join(filename);
+            std::fs::write(&path, "export const value = 1;").expect("Could not write file");
+
+            let specifier = ModuleSpecifier::from_file_path(&path).unwrap();
+            let response = loader.load(
+                &specifier,
+                None,
+                false,
+                deno_core::RequestedModuleType::None,
+            );
+            match response {
+                ModuleLoadResponse::Async(future) => {
+                    future.await.expect("Expected to get source");
+                }
+                _ => panic!("Unexpected response"),
+            }
+        }
+
+        let cached = ModuleSpecifier::from_file_path(dir.join("cached.js")).unwrap();
+        let uncached = ModuleSpecifier::from_file_path(dir.join("uncached.js")).unwrap();
+        assert!(cache_provider.get(&cached).await.is_some());
+        assert!(cache_provider.get(&uncached).await.is_none());
+    }
+
+    #[test]
+    fn test_virtual_modules() {
+        let virtual_modules = VirtualModuleSource::new(HashMap::from([(
+            "virtual.js".to_string(),
+            "export const value = 2;".to_string(),
+        )]));
+        let loader = RustyLoader::new(Rc::new(()), virtual_modules);
+
+        let specifier = "virtual.js".to_module_specifier().unwrap();
+        let response = loader.load(
+            &specifier,
+            None,
+            false,
+            deno_core::RequestedModuleType::None,
+        );
+        match response {
+            ModuleLoadResponse::Sync(source) => {
+                let source = source.expect("Expected to get source");
+                match source.code {
+                    ModuleSourceCode::String(s) => {
+                        assert_eq!("export const value = 2;", s.as_str())
+                    }
+                    _ => panic!("Unexpected source code type"),
+                }
+            }
+            _ => panic!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn test_import_meta_source_map() {
+        let specifier = "file:///test.ts".to_module_specifier().unwrap();
+        let code = transpiler::transpile(
+            &specifier,
+            "export const sum = (a: number, b: number): number => a + b;",
+        )
+        .expect("Could not transpile");
+        let first_line = code.lines().next().expect("No code");
+        let column = first_line.find("a + b").expect("No function body") as u32 + 1;
+        let expected = SourceMap::from_inline(&code)
+            .expect("No source map")
+            .original_position(1, column);
+        assert!(expected.is_some());
+
+        // Positions after the fields on the first line still map to the same source
+        let import_meta = "Object.assign(import.meta, {\"mode\":\"é\"});";
+        let code = RustyLoader::add_import_meta(Some(import_meta), code);
+        let offset = import_meta.encode_utf16().count() as u32;
+        let position = SourceMap::from_inline(&code)
+            .expect("No source map")
+            .original_position(1, column + offset);
+        assert_eq!(expected, position);
+    }
 }
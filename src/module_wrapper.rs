@@ -1,4 +1,5 @@
 use deno_core::{serde_json, v8::GetPropertyNamesArgs};
+use std::{future::Future, pin::Pin};
 
 use crate::{Error, JsFunction, Module, ModuleHandle, Runtime, RuntimeOptions};
 
@@ -6,6 +7,7 @@ use crate::{Error, JsFunction, Module, ModuleHandle, Runtime, RuntimeOptions};
 pub struct ModuleWrapper {
     module_context: ModuleHandle,
     runtime: Runtime,
+    path: Option<String>,
 }
 
 impl ModuleWrapper {
@@ -25,6 +27,7 @@ impl ModuleWrapper {
         Ok(Self {
             module_context,
             runtime,
+            path: None,
         })
     }
 
@@ -40,7 +43,56 @@ impl ModuleWrapper {
     /// A `Result` containing `Self` on success or an `Error` on failure.
     pub async fn new_from_file(path: &str, options: RuntimeOptions) -> Result<Self, Error> {
         let module = Module::load(path)?;
-        Self::new_from_module(&module, options).await
+        let mut wrapper = Self::new_from_module(&module, options).await?;
+        wrapper.path = Some(path.to_string());
+        Ok(wrapper)
+    }
+
+    /// Re-reads the module's file, and replaces the module with the new version
+    /// See `Runtime::reload_module` for what is, and is not, reloaded
+    ///
+    /// The runtime is kept, so every old version of the module stays in memory.
+    /// For long-running watchers, use `reload_with` to start over with a fresh runtime
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that is an error if the wrapper was not created with `new_from_file`,
+    /// or if the file could not be read or loaded
+    pub async fn reload(&mut self) -> Result<(), Error> {
+        let module = self.reload_source()?;
+        self.module_context = self.runtime.reload_module(&module).await?;
+        Ok(())
+    }
+
+    /// Re-reads the module's file, and loads it into a new runtime built from the given options
+    /// Nothing is kept from the old runtime - see `Runtime::reload_module_with`
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The runtime options for the new runtime.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that is an error if the wrapper was not created with `new_from_file`,
+    /// or if the file could not be read or loaded
+    pub async fn reload_with(&mut self, options: RuntimeOptions) -> Result<(), Error> {
+        let module = self.reload_source()?;
+        self.module_context = self
+            .runtime
+            .reload_module_with(&module, options, |_, _| {})
+            .await?;
+        Ok(())
+    }
+
+    /// Read the current version of the module's file
+    fn reload_source(&self) -> Result<Module, Error> {
+        let path = self.path.as_deref().ok_or_else(|| {
+            Error::Runtime(format!(
+                "{} was not loaded from a file, and cannot be reloaded",
+                self.module_context.module()
+            ))
+        })?;
+        Module::load(path)
     }
 
     /// Returns a reference to the module context.
@@ -102,6 +154,30 @@ impl ModuleWrapper {
             .await
     }
 
+    /// Calls a function in the module with the given name and arguments, returning a boxed future
+    /// The future owns its arguments, and borrows only the wrapper, so it can be
+    /// stored, or passed to async combinators such as `tokio::time::timeout`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the function to call.
+    /// * `args` - The arguments to pass to the function.
+    ///
+    /// # Returns
+    ///
+    /// A future resolving to the deserialized result of type `T` on success or an `Error` on failure.
+    pub fn call_boxed<T>(
+        &mut self,
+        name: &str,
+        args: Vec<serde_json::Value>,
+    ) -> Pin<Box<dyn Future<Output = Result<T, Error>> + '_>>
+    where
+        T: serde::de::DeserializeOwned + 'static,
+    {
+        let name = name.to_string();
+        Box::pin(async move { self.call(&name, &args).await })
+    }
+
     /// Calls a function using the module's runtime that was previously stored
     /// as a JsFunction object
     ///
@@ -184,6 +260,26 @@ mod test_runtime {
         assert_eq!(4, value);
     }
 
+    #[tokio::test]
+    async fn test_call_boxed() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function add(a, b) { return a + b; }
+        ",
+        );
+
+        let mut module = ModuleWrapper::new_from_module(&module, RuntimeOptions::default())
+            .await
+            .expect("Could not create wrapper");
+        let future = module.call_boxed::<usize>("add", vec![1.into(), 2.into()]);
+        let value = tokio::time::timeout(std::time::Duration::from_secs(5), future)
+            .await
+            .expect("Call timed out")
+            .expect("Could not call function");
+        assert_eq!(3, value);
+    }
+
     #[tokio::test]
     async fn test_get() {
         let module = Module::new(
@@ -219,6 +315,52 @@ mod test_runtime {
         assert!(!module.is_callable("value").await);
     }
 
+    #[tokio::test]
+    async fn test_reload() {
+        let dir = std::env::temp_dir().join("rustyscript_module_wrapper_reload");
+        std::fs::create_dir_all(&dir).expect("Could not create directory");
+        let path = dir.join("config.js");
+        std::fs::write(&path, "export const port = 80;").expect("Could not write file");
+
+        let mut module =
+            ModuleWrapper::new_from_file(&path.to_string_lossy(), RuntimeOptions::default())
+                .await
+                .expect("Could not create wrapper");
+        let port: u16 = module.get("port").await.expect("Could not get value");
+        assert_eq!(80, port);
+
+        std::fs::write(
+            &path,
+            "export const port = 8080;\nexport const host = 'localhost';",
+        )
+        .expect("Could not write file");
+        module.reload().await.expect("Could not reload module");
+
+        let port: u16 = module.get("port").await.expect("Could not get value");
+        assert_eq!(8080, port);
+        let host: String = module.get("host").await.expect("Could not get value");
+        assert_eq!("localhost", host);
+
+        std::fs::write(&path, "export const port = 443;").expect("Could not write file");
+        module
+            .reload_with(RuntimeOptions::default())
+            .await
+            .expect("Could not reload module");
+        let port: u16 = module.get("port").await.expect("Could not get value");
+        assert_eq!(443, port);
+
+        let mut module = ModuleWrapper::new_from_module(
+            &Module::new("test.js", "export const value = 3;"),
+            RuntimeOptions::default(),
+        )
+        .await
+        .expect("Could not create wrapper");
+        module
+            .reload()
+            .await
+            .expect_err("Did not detect a module without a file");
+    }
+
     #[tokio::test]
     async fn test_keys() {
         let module = Module::new(
@@ -0,0 +1,107 @@
+use crate::Error;
+use deno_core::{v8, JsRuntime};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+/// Set once the V8 platform has been initialized, either explicitly or by the
+/// creation of the first runtime. Held while the platform is set up, so that
+/// explicit initialization cannot race the creation of a runtime
+static PLATFORM_INITIALIZED: Mutex<bool> = Mutex::new(false);
+
+/// The V8 flags applied to the process, by the first runtime that set any
+static V8_FLAGS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Represents the set of options used to initialize the V8 platform
+/// See `Runtime::initialize_platform`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PlatformOptions {
+    /// Number of worker threads used by the platform
+    /// 0 lets V8 pick a size based on the number of cores
+    pub thread_pool_size: u32,
+
+    /// Enables support for idle tasks on the platform
+    pub idle_task_support: bool,
+}
+
+/// Initialize the V8 platform with the given options
+/// Can only succeed once per process, and only before any runtime is created
+pub fn initialize(options: PlatformOptions) -> Result<(), Error> {
+    let mut initialized = platform_lock();
+    if *initialized {
+        return Err(Error::Runtime(
+            "The V8 platform has already been initialized".to_string(),
+        ));
+    }
+
+    let platform =
+        v8::new_default_platform(options.thread_pool_size, options.idle_task_support).make_shared();
+    JsRuntime::init_platform(Some(platform));
+    *initialized = true;
+    Ok(())
+}
+
+/// Apply flags to V8, such as `--expose-gc`
+/// Flags are process-global, so only the first non-empty set of flags is applied.
+/// Later calls with the same flags succeed, and calls with different flags fail
+///
+/// V8 freezes its flags once the platform is initialized, so flags can only be
+/// applied before then - usually by the first runtime created in the process
+pub fn set_v8_flags(flags: &[String]) -> Result<(), Error> {
+    if flags.is_empty() {
+        return Ok(());
+    }
+
+    // Held until the flags are applied, so the platform cannot be initialized meanwhile
+    let initialized = platform_lock();
+    if V8_FLAGS.get().is_none() && *initialized {
+        return Err(Error::Runtime(
+            "V8 flags must be set before the V8 platform is initialized".to_string(),
+        ));
+    }
+
+    let mut result = Ok(());
+    let applied = V8_FLAGS.get_or_init(|| {
+        // V8 ignores the first argument, which is usually the binary name
+        let args = std::iter::once(String::new())
+            .chain(flags.iter().cloned())
+            .collect();
+        let unrecognized = deno_core::v8_set_flags(args);
+        if unrecognized.len() > 1 {
+            result = Err(Error::Runtime(format!(
+                "Unrecognized V8 flags: {}",
+                unrecognized[1..].join(", ")
+            )));
+        }
+        flags.to_vec()
+    });
+
+    if applied != flags {
+        return Err(Error::Runtime(format!(
+            "V8 flags have already been set for this process: {}",
+            applied.join(" ")
+        )));
+    }
+    result
+}
+
+/// Set up the default platform, unless one was already initialized
+/// Called before creating a runtime, which would otherwise set one up lazily
+/// without going through the lock
+pub fn ensure_initialized() {
+    let mut initialized = platform_lock();
+    if !*initialized {
+        JsRuntime::init_platform(None);
+        *initialized = true;
+    }
+}
+
+/// Returns true if the platform has already been initialized
+pub fn is_initialized() -> bool {
+    *platform_lock()
+}
+
+/// Lock the platform state - it stays consistent even if a holder panicked
+fn platform_lock() -> MutexGuard<'static, bool> {
+    PLATFORM_INITIALIZED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
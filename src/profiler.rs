@@ -0,0 +1,76 @@
+//! Types describing CPU profiles, as produced by the V8 inspector's profiler
+//! They serialize to the standard `.cpuprofile` format, which can be opened
+//! in Chrome DevTools or any compatible viewer
+use serde::{Deserialize, Serialize};
+
+/// A CPU profile captured with `Runtime::start_cpu_profile` and `Runtime::stop_cpu_profile`
+///
+/// Serializing it to JSON produces a `.cpuprofile` file:
+/// ```rust
+/// use rustyscript::{ serde_json, CpuProfile };
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let profile = CpuProfile::default();
+/// let json = serde_json::to_string(&profile)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuProfile {
+    /// The nodes of the call tree
+    pub nodes: Vec<CpuProfileNode>,
+
+    /// Time the profile was started, in microseconds
+    pub start_time: i64,
+
+    /// Time the profile was stopped, in microseconds
+    pub end_time: i64,
+
+    /// The id of the topmost node for each sample
+    #[serde(default)]
+    pub samples: Vec<i64>,
+
+    /// Time between each sample and the one before it, in microseconds
+    #[serde(default)]
+    pub time_deltas: Vec<i64>,
+}
+
+/// A node in the call tree of a `CpuProfile`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuProfileNode {
+    /// Unique id of the node
+    pub id: i64,
+
+    /// The function this node represents
+    pub call_frame: CallFrame,
+
+    /// Number of samples in which this node was on top of the stack
+    #[serde(default)]
+    pub hit_count: i64,
+
+    /// Ids of the nodes called from this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<i64>,
+}
+
+/// The location of a function in a `CpuProfile`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// Name of the function, or an empty string for anonymous functions
+    pub function_name: String,
+
+    /// Id of the script containing the function
+    pub script_id: String,
+
+    /// URL of the script containing the function
+    pub url: String,
+
+    /// 0-based line number of the function
+    pub line_number: i64,
+
+    /// 0-based column number of the function
+    pub column_number: i64,
+}
@@ -0,0 +1,215 @@
+use crate::{Error, Runtime, RuntimeOptions};
+use deno_core::{serde_json, v8};
+
+/// The result of feeding a line of input to a `Repl`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReplOutput {
+    /// The input was evaluated - contains the pretty-printed result
+    Value(String),
+
+    /// The input is not yet a complete statement (an open brace, for example)
+    /// The next line will be appended to it before evaluation
+    Incomplete,
+}
+
+/// A read-eval-print loop helper wrapping a runtime
+/// Each line is evaluated in the persistent global context, so declarations
+/// made on one line can be used by the next
+pub struct Repl {
+    runtime: Runtime,
+    buffer: String,
+}
+
+impl Repl {
+    /// Creates a new REPL, backed by a runtime with the given options
+    ///
+    /// # Arguments
+    /// * `options` - A `RuntimeOptions` struct that specifies the configuration options for the runtime.
+    ///
+    /// # Returns
+    /// A `Result` containing either the REPL instance on success (`Ok`) or an error on failure (`Err`).
+    pub fn new(options: RuntimeOptions) -> Result<Self, Error> {
+        Ok(Self {
+            runtime: Runtime::new(options)?,
+            buffer: String::new(),
+        })
+    }
+
+    /// Returns a mutable reference to the underlying runtime.
+    pub fn runtime(&mut self) -> &mut Runtime {
+        &mut self.runtime
+    }
+
+    /// Returns true if previous input is waiting for more lines
+    pub fn is_pending(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Evaluate a line of input
+    /// If the input (together with any pending lines) is incomplete,
+    /// `ReplOutput::Incomplete` is returned and the line is kept for the next call
+    ///
+    /// # Arguments
+    /// * `line` - A line of javascript
+    ///
+    /// # Returns
+    /// A `Result` containing the pretty-printed value of the input,
+    /// or an error (`Error`) if evaluation failed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Repl, ReplOutput, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut repl = Repl::new(Default::default())?;
+    /// assert_eq!(ReplOutput::Incomplete, repl.eval_line("function f() {")?);
+    /// repl.eval_line("return 2; }")?;
+    /// assert_eq!(ReplOutput::Value("2".to_string()), repl.eval_line("f()")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eval_line(&mut self, line: &str) -> Result<ReplOutput, Error> {
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(line);
+
+        let source = self.buffer.clone();
+        match self.runtime.deno_runtime().execute_script("repl", source) {
+            Ok(value) => {
+                self.buffer.clear();
+
+                let mut scope = self.runtime.deno_runtime().handle_scope();
+                let value = v8::Local::new(&mut scope, value);
+                Ok(ReplOutput::Value(format_value(&mut scope, value)))
+            }
+
+            Err(e) => {
+                let e = Error::from(e);
+                if is_incomplete(&e) {
+                    return Ok(ReplOutput::Incomplete);
+                }
+
+                self.buffer.clear();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Determines if an error was caused by the input ending too soon
+fn is_incomplete(e: &Error) -> bool {
+    match e {
+        Error::JsError(e) if e.name.as_deref() == Some("SyntaxError") => matches!(
+            e.message.as_deref(),
+            Some("Unexpected end of input") | Some("Unterminated template literal")
+        ),
+        _ => false,
+    }
+}
+
+/// Pretty-print a value, in roughly the way a browser console would
+fn format_value(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> String {
+    if value.is_undefined() {
+        return "undefined".to_string();
+    }
+
+    if let Ok(function) = v8::Local::<v8::Function>::try_from(value) {
+        let name = function.get_name(scope).to_rust_string_lossy(scope);
+        return if name.is_empty() {
+            "[Function (anonymous)]".to_string()
+        } else {
+            format!("[Function: {name}]")
+        };
+    }
+
+    match deno_core::serde_v8::from_v8::<serde_json::Value>(scope, value) {
+        Ok(value) => format_json(&value),
+        Err(_) => value.to_rust_string_lossy(scope),
+    }
+}
+
+fn format_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => format!("{s:?}"),
+        serde_json::Value::Array(a) if a.is_empty() => "[]".to_string(),
+        serde_json::Value::Array(a) => {
+            let parts = a.iter().map(format_json).collect::<Vec<_>>().join(", ");
+            format!("[ {parts} ]")
+        }
+        serde_json::Value::Object(o) if o.is_empty() => "{}".to_string(),
+        serde_json::Value::Object(o) => {
+            let parts = o
+                .iter()
+                .map(|(k, v)| format!("{k}: {}", format_json(v)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{{ {parts} }}")
+        }
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test_repl {
+    use super::*;
+
+    #[test]
+    fn test_eval_line() {
+        let mut repl = Repl::new(Default::default()).expect("Could not create REPL");
+        assert_eq!(
+            ReplOutput::Value("2".to_string()),
+            repl.eval_line("1+1").expect("Could not eval")
+        );
+        assert_eq!(
+            ReplOutput::Value("\"test\"".to_string()),
+            repl.eval_line("'test'").expect("Could not eval")
+        );
+        assert_eq!(
+            ReplOutput::Value("{ a: [ 1, 2 ] }".to_string()),
+            repl.eval_line("({a: [1, 2]})").expect("Could not eval")
+        );
+
+        // `5;+-` is only incomplete, since an operand on the next line would finish it
+        match repl.eval_line("5 +* 2") {
+            Err(Error::JsError(e)) => {
+                assert_eq!(Some("SyntaxError"), e.name.as_deref());
+                assert_eq!(Some("Unexpected token '*'"), e.message.as_deref());
+            }
+            other => panic!("Did not detect invalid input: {other:?}"),
+        }
+        assert!(!repl.is_pending());
+    }
+
+    #[test]
+    fn test_persistent_globals() {
+        let mut repl = Repl::new(Default::default()).expect("Could not create REPL");
+        assert_eq!(
+            ReplOutput::Value("undefined".to_string()),
+            repl.eval_line("let x = 5;").expect("Could not eval")
+        );
+        assert_eq!(
+            ReplOutput::Value("10".to_string()),
+            repl.eval_line("x * 2").expect("Could not eval")
+        );
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        let mut repl = Repl::new(Default::default()).expect("Could not create REPL");
+        assert_eq!(
+            ReplOutput::Incomplete,
+            repl.eval_line("function f() {").expect("Could not eval")
+        );
+        assert!(repl.is_pending());
+        assert_eq!(
+            ReplOutput::Value("undefined".to_string()),
+            repl.eval_line("return 3; }").expect("Could not eval")
+        );
+        assert!(!repl.is_pending());
+        assert_eq!(
+            ReplOutput::Value("3".to_string()),
+            repl.eval_line("f()").expect("Could not eval")
+        );
+    }
+}
@@ -1,8 +1,22 @@
 use crate::{
-    inner_runtime::{InnerRuntime, InnerRuntimeOptions},
-    Error, FunctionArguments, JsFunction, Module, ModuleHandle,
+    inner_runtime::{GlobalSnapshot, InnerRuntime, InnerRuntimeOptions},
+    platform, CapturedOutput, CpuProfile, Error, FunctionArgument, FunctionArguments, JsFunction,
+    JsValue, Module, ModuleHandle, PlatformOptions, SyntaxError, TemplateHandle, TypeDescriptor,
+    WorkerHandle,
+};
+use deno_core::{error::JsError, serde_json, ModuleSpecifier};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
 };
-use deno_core::serde_json;
 
 /// Represents the set of options accepted by the runtime constructor
 pub type RuntimeOptions = InnerRuntimeOptions;
@@ -10,6 +24,41 @@ pub type RuntimeOptions = InnerRuntimeOptions;
 /// For functions returning nothing
 pub type Undefined = serde_json::Value;
 
+/// A comparable summary of a runtime's global state
+/// See `Runtime::global_fingerprint`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Fingerprint(pub u64);
+
+/// Memory usage of a runtime's heap, in bytes
+/// See `Runtime::heap_stats`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HeapStats {
+    /// Memory used by live and not-yet-collected objects
+    pub used_heap_size: usize,
+
+    /// Memory currently reserved for the heap
+    pub total_heap_size: usize,
+
+    /// Maximum size the heap can grow to
+    pub heap_size_limit: usize,
+
+    /// Memory held outside of the heap by JS objects, such as `ArrayBuffer` contents
+    pub external_memory: usize,
+}
+
+/// The result of `Runtime::try_eval`, separating the ways an expression can fail
+#[derive(Debug)]
+pub enum EvalOutcome<T> {
+    /// The expression was evaluated - contains its deserialized result
+    Value(T),
+
+    /// The expression ran, but threw an exception
+    Thrown(JsError),
+
+    /// The expression could not be parsed, and was not run
+    ParseError(SyntaxError),
+}
+
 /// Represents a configured runtime ready to run modules
 pub struct Runtime(InnerRuntime);
 
@@ -25,6 +74,7 @@ impl Runtime {
     ///
     /// # Returns
     /// A `Result` containing either the initialized runtime instance on success (`Ok`) or an error on failure (`Err`).
+    /// Options that conflict are an error - see `RuntimeOptions::validate`
     ///
     /// # Example
     /// ```rust
@@ -56,7 +106,48 @@ impl Runtime {
     /// ```
     ///
     pub fn new(options: RuntimeOptions) -> Result<Self, Error> {
-        Ok(Self(InnerRuntime::new(options)))
+        platform::set_v8_flags(&options.v8_flags)?;
+        let mut runtime = InnerRuntime::new(options)?;
+        runtime.start_inspector()?;
+        Ok(Self(runtime))
+    }
+
+    /// Initializes the V8 platform used by all runtimes in this process
+    ///
+    /// This is optional - a default platform is set up automatically when the first
+    /// runtime is created. It is only useful for embedders that need to control the
+    /// platform's thread pool.
+    ///
+    /// It can only be called once, and must be called before any call to `Runtime::new`.
+    /// Later calls will return an error, and leave the existing platform in place.
+    ///
+    /// # Arguments
+    /// * `options` - A `PlatformOptions` struct that specifies the configuration of the platform.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, PlatformOptions };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// Runtime::initialize_platform(PlatformOptions {
+    ///     thread_pool_size: 2,
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let runtime = Runtime::new(Default::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn initialize_platform(options: PlatformOptions) -> Result<(), Error> {
+        platform::initialize(options)
+    }
+
+    /// Returns the address the V8 inspector is listening on, if it was enabled
+    /// with `RuntimeOptions::inspector`. Useful when listening on port 0
+    ///
+    /// Connect Chrome DevTools by visiting `chrome://inspect`, and adding this address
+    pub fn inspector_address(&self) -> Option<std::net::SocketAddr> {
+        self.0.inspector_address()
     }
 
     /// Access the underlying deno runtime instance directly
@@ -139,6 +230,33 @@ impl Runtime {
         serde_json::Value::from(value)
     }
 
+    /// Encode binary data as a function argument, delivered to JS as a `Uint8Array`
+    /// The data is moved into the array's buffer instead of being copied or encoded as JSON,
+    /// so this is much faster for large buffers. See `Runtime::call_function_with_args`
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const length = (data) => data.byteLength;");
+    /// let module = runtime.load_module(&module).await?;
+    /// let args = [Runtime::arg_bytes(vec![1, 2, 3])];
+    /// let length: usize = runtime.call_function_with_args(&module, "length", &args).await?;
+    /// assert_eq!(3, length);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn arg_bytes(data: Vec<u8>) -> FunctionArgument {
+        platform::ensure_initialized();
+        FunctionArgument::Bytes(
+            deno_core::v8::ArrayBuffer::new_backing_store_from_vec(data).make_shared(),
+        )
+    }
+
     /// Remove and return a value from the state, if one exists
     /// ```rust
     /// use rustyscript::{ Runtime };
@@ -179,554 +297,3919 @@ impl Runtime {
         self.0.put(value)
     }
 
-    /// Evaluate a piece of non-ECMAScript-module JavaScript code
-    /// The expression is evaluated in the global context, so changes persist
-    ///
-    /// # Arguments
-    /// * `expr` - A string representing the JavaScript expression to evaluate
-    ///
-    /// # Returns
-    /// A `Result` containing the deserialized result of the expression (`T`)
-    /// or an error (`Error`) if the expression cannot be evaluated or if the
-    /// result cannot be deserialized.
-    ///
-    /// # Example
+    /// Set the locale used by `Intl` and `toLocaleString` calls that do not specify one
+    /// Overrides `RuntimeOptions::default_locale`
     /// ```rust
-    /// use rustyscript::{ Runtime, Error };
+    /// use rustyscript::{ Runtime };
     ///
-    /// # fn main() -> Result<(), Error> {
+    /// # fn main() -> Result<(), rustyscript::Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let value:
-    ///    usize = runtime.eval("2 + 2")?;
-    /// assert_eq!(4, value);
+    /// runtime.set_default_locale("de-DE")?;
+    /// let value: String = runtime.eval("new Intl.NumberFormat().format(1234.5)")?;
+    /// assert_eq!(value, "1.234,5");
     /// # Ok(())
     /// # }
     /// ```
-    pub fn eval<T>(&mut self, expr: &str) -> Result<T, Error>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        self.0.eval(expr)
-    }
-
-    /// Calls a stored javascript function and deserializes its return value.
-    ///
-    /// # Arguments
-    /// * `function` - A The function object
-    ///
-    /// # Returns
-    /// A `Result` containing the deserialized result of the function call (`T`)
-    /// or an error (`Error`) if the function cannot be found, if there are issues with
-    /// calling the function, or if the result cannot be deserialized.
-    pub async fn call_stored_function<'a, T>(
-        &'a mut self,
-        module_context: &ModuleHandle,
-        function: &JsFunction<'a>,
-        args: &FunctionArguments,
-    ) -> Result<T, Error>
-    where
-        T: deno_core::serde::de::DeserializeOwned,
-    {
+    pub fn set_default_locale(&mut self, locale: &str) -> Result<(), Error> {
         self.0
-            .call_stored_function(module_context, function, args)
-            .await
+            .put(crate::ext::rustyscript::DefaultLocale(locale.to_string()))
     }
 
-    /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
+    /// Define a read-only global, whose value is computed by Rust each time a script reads it
     ///
     /// # Arguments
-    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `name` - The name of the property to define on `globalThis`
+    /// * `f` - A function returning the current value, which is passed to JS as JSON
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the function call (`T`)
-    /// or an error (`Error`) if the function cannot be found, if there are issues with
-    /// calling the function, or if the result cannot be deserialized.
+    /// A `Result` that is an error if the property cannot be defined
     ///
     /// # Example
-    ///
     /// ```rust
-    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    /// use rustyscript::{ Runtime, serde_json::json };
+    /// use std::rc::Rc;
     ///
-    /// # fn main() -> Result<(), Error> {
-    /// # tokio_test::block_on(async {
+    /// # fn main() -> Result<(), rustyscript::Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("/path/to/module.js", "export function f() { return 2; };");
-    /// let module = runtime.load_module(&module).await?;
-    /// let value: usize = runtime.call_function(&module, "f", json_args!()).await?;
+    /// runtime.define_global_getter("answer", Rc::new(|| json!(42)))?;
+    /// let value: usize = runtime.eval("answer")?;
+    /// assert_eq!(42, value);
     /// # Ok(())
-    /// # })
     /// # }
     /// ```
-    pub async fn call_function<T>(
+    pub fn define_global_getter(
         &mut self,
-        module_context: &ModuleHandle,
         name: &str,
-        args: &FunctionArguments,
-    ) -> Result<T, Error>
-    where
-        T: deno_core::serde::de::DeserializeOwned,
-    {
-        self.0.call_function(module_context, name, args).await
+        f: Rc<dyn Fn() -> serde_json::Value>,
+    ) -> Result<(), Error> {
+        self.0.define_global_getter(name, f)
     }
 
-    /// Get a value from a runtime instance
+    /// Store a value under a key, that JS can read with `rustyscript.getData(key)`
+    /// Values are passed to JS as JSON. Reading a missing key returns `null`
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::json };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.set_global_data("config", json!({ "retries": 3 }))?;
+    /// let value: usize = runtime.eval("rustyscript.getData('config').retries")?;
+    /// assert_eq!(3, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_global_data(&mut self, key: &str, value: serde_json::Value) -> Result<(), Error> {
+        self.0.set_global_data(key, value)
+    }
+
+    /// Start capturing output printed by scripts, such as with `console.log`,
+    /// instead of writing it to stdout and stderr
+    /// Anything captured so far is discarded
+    /// ```rust
+    /// use rustyscript::{ Runtime, Undefined };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.start_output_capture()?;
+    /// runtime.eval::<Undefined>("console.log('hello')")?;
+    /// assert_eq!("hello\n", runtime.take_output().stdout);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start_output_capture(&mut self) -> Result<(), Error> {
+        self.0.start_output_capture()
+    }
+
+    /// Stop capturing printed output, and return what was captured since
+    /// `Runtime::start_output_capture` was called
+    pub fn take_output(&mut self) -> CapturedOutput {
+        self.0.take_output()
+    }
+
+    /// Change the directory that module filenames, and the relative imports in them,
+    /// are resolved against - like `std::env::set_current_dir`, but only for this runtime
+    /// Paths read with `rustyscript.readTextFile` are resolved against it too
+    /// Modules that are already loaded are not affected
     ///
     /// # Arguments
-    /// * `name` - A string representing the name of the value to find
+    /// * `path` - The new directory. Relative paths are resolved against the process's working directory
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result or an error (`Error`) if the
-    /// value cannot be found, if there are issues with, or if the result cannot be
-    ///  deserialized.
+    /// A `Result` containing nothing, or an error if the path is not a directory
     ///
     /// # Example
-    ///
     /// ```rust
-    /// use rustyscript::{ Runtime, Module, Error };
+    /// use rustyscript::Runtime;
     ///
-    /// # fn main() -> Result<(), Error> {
-    /// # tokio_test::block_on(async {
+    /// # fn main() -> Result<(), rustyscript::Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
-    /// let module = runtime.load_module(&module).await?;
-    /// let value: usize = runtime.get_value(&module, "my_value").await?;
+    /// runtime.set_current_dir(std::env::temp_dir())?;
     /// # Ok(())
-    /// # })
     /// # }
     /// ```
-    pub async fn get_value<T>(
-        &mut self,
-        module_context: &ModuleHandle,
-        name: &str,
-    ) -> Result<T, Error>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        self.0.get_value(module_context, name).await
+    pub fn set_current_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        self.0.set_current_dir(path.as_ref())
     }
 
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// And call functions
+    /// Start listening for values sent by scripts with `rustyscript.emit(value)`,
+    /// such as progress updates or the items of a generator
     ///
-    /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// Values are sent as JSON, in the order they are emitted, and can be received
+    /// from another thread while a call is still running. Values emitted while no
+    /// receiver is listening are discarded, and taking a new receiver disconnects
+    /// the previous one
     ///
     /// # Returns
-    /// A `Result` containing a handle for the loaded module
-    /// or an error (`Error`) if there are issues with loading modules, executing the
-    /// module, or if the result cannot be deserialized.
+    /// A `Result` containing the receiving end of the channel,
+    /// or an error if the runtime's state could not be borrowed
     ///
     /// # Example
-    ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{Runtime, Module, Error};
+    /// use rustyscript::{ Runtime, Undefined };
     ///
-    /// # fn main() -> Result<(), Error> {
-    /// # tokio_test::block_on(async {
+    /// # fn main() -> Result<(), rustyscript::Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
-    /// runtime.load_module(&module).await?;
+    /// let receiver = runtime.take_emit_receiver()?;
+    /// runtime.eval::<Undefined>("rustyscript.emit({ progress: 50 })")?;
+    /// assert_eq!(50, receiver.recv().unwrap()["progress"]);
     /// # Ok(())
-    /// # })
     /// # }
     /// ```
-    pub async fn load_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
-        self.0.load_modules(None, vec![module]).await
+    pub fn take_emit_receiver(&mut self) -> Result<mpsc::Receiver<serde_json::Value>, Error> {
+        self.0.take_emit_receiver()
     }
 
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// And call functions.
+    /// Lists every module a loaded module imports, directly or through its imports
+    /// Useful for deciding what to invalidate when a file changes
     ///
-    /// This will load 'module' as the main module, and the others as side-modules.
-    /// Only one main module can be loaded per runtime
+    /// Imports are recorded as the module loader resolves them, since deno_core
+    /// does not expose its module map
     ///
     /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
-    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    /// * `module_context` - A handle returned by loading a module into the runtime
     ///
     /// # Returns
-    /// A `Result` containing a handle for the loaded module
-    /// or an error (`Error`) if there are issues with loading modules, executing the
-    /// module, or if the result cannot be deserialized.
+    /// The specifiers of the imported modules - the module's own imports first,
+    /// in the order they appear, then their imports
     ///
     /// # Example
-    ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{Runtime, Module, Error};
+    /// use rustyscript::{ Runtime, Module };
     ///
-    /// # fn main() -> Result<(), Error> {
+    /// # fn main() -> Result<(), rustyscript::Error> {
     /// # tokio_test::block_on(async {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
-    /// runtime.load_modules(&module, vec![]).await?;
+    /// let lib = Module::new("lib.js", "export const value = 2;");
+    /// let main = Module::new("main.js", "export { value } from './lib.js';");
+    /// let module = runtime.load_modules(&main, vec![&lib]).await?;
+    /// assert_eq!(1, runtime.module_dependencies(&module).len());
     /// # Ok(())
     /// # })
     /// # }
     /// ```
-    pub async fn load_modules(
-        &mut self,
-        module: &Module,
-        side_modules: Vec<&Module>,
-    ) -> Result<ModuleHandle, Error> {
-        self.0.load_modules(Some(module), side_modules).await
+    pub fn module_dependencies(&self, module_context: &ModuleHandle) -> Vec<ModuleSpecifier> {
+        self.0.module_dependencies(module_context)
     }
 
-    /// Executes the entrypoint function of a module within the Deno runtime.
+    /// Translate a position in a loaded module's transpiled code back to the
+    /// matching position in its original source, such as a TypeScript file
+    /// Useful for positions reported by V8, which refer to the transpiled code
     ///
     /// # Arguments
-    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `module` - A module loaded into the runtime
+    /// * `line` - A 1-based line number in the transpiled code
+    /// * `column` - A 1-based column number in the transpiled code
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
+    /// The 1-based line and column in the original source, or None if the module
+    /// was not loaded, was not transpiled, or the position has no mapping
+    pub fn original_position(&self, module: &Module, line: u32, column: u32) -> Option<(u32, u32)> {
+        self.0.original_position(module, line, column)
+    }
+
+    /// Start recording a CPU profile of everything the runtime executes
+    /// Requires `RuntimeOptions::cpu_profiling`
     ///
-    /// # Example
+    /// # Returns
+    /// A `Result` that is an error if profiling is not enabled, or a profile is already running
     ///
+    /// # Example
     /// ```rust
-    /// use rustyscript::{json_args, Runtime, Module, Error};
+    /// use rustyscript::{ json_args, Runtime, RuntimeOptions, Module };
     ///
-    /// # fn main() -> Result<(), Error> {
+    /// # fn main() -> Result<(), rustyscript::Error> {
     /// # tokio_test::block_on(async {
-    /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
+    /// let module = Module::new("test.js", "export const f = () => 2;");
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     cpu_profiling: true,
+    ///     ..Default::default()
+    /// })?;
     /// let module = runtime.load_module(&module).await?;
     ///
-    /// // Run the entrypoint and handle the result
-    /// let value: String = runtime.call_entrypoint(&module, json_args!()).await?;
+    /// runtime.start_cpu_profile().await?;
+    /// let value: usize = runtime.call_function(&module, "f", json_args!()).await?;
+    /// let profile = runtime.stop_cpu_profile().await?;
     /// # Ok(())
     /// # })
     /// # }
     /// ```
-    pub async fn call_entrypoint<T>(
-        &mut self,
-        module_context: &ModuleHandle,
-        args: &FunctionArguments,
-    ) -> Result<T, Error>
-    where
-        T: deno_core::serde::de::DeserializeOwned,
-    {
-        if let Some(entrypoint) = module_context.entrypoint() {
-            let value: serde_json::Value = self
-                .0
-                .call_function_by_ref_async(module_context, entrypoint.clone(), args)
-                .await?;
-            Ok(serde_json::from_value(value)?)
-        } else {
-            Err(Error::MissingEntrypoint(module_context.module().clone()))
-        }
+    pub async fn start_cpu_profile(&mut self) -> Result<(), Error> {
+        self.0.start_cpu_profile().await
     }
 
-    /// Loads a module into a new runtime, executes the entry function and returns the
-    /// result of the module's execution, deserialized into the specified Rust type (`T`).
+    /// Stop recording the CPU profile started by `Runtime::start_cpu_profile`
     ///
-    /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
-    /// * `side_modules` - A set of additional modules to be loaded into memory for use
-    /// * `runtime_options` - Options for the creation of the runtime
-    /// * `entrypoint_args` - Arguments to pass to the entrypoint function
+    /// # Returns
+    /// A `Result` containing the profile, which serializes to the `.cpuprofile` format,
+    /// or an error if no profile is running
+    pub async fn stop_cpu_profile(&mut self) -> Result<CpuProfile, Error> {
+        self.0.stop_cpu_profile().await
+    }
+
+    /// Save the current value of each enumerable own property of `globalThis`
+    /// Restoring the snapshot with `Runtime::restore_globals` later undoes any changes
+    /// made to globals in between, which isolates requests that share a runtime
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
+    /// A snapshot which can only be restored into this runtime
     ///
     /// # Example
-    ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{json_args, Runtime, Module, Error};
+    /// use rustyscript::{ Runtime, Undefined };
     ///
-    /// # fn main() -> Result<(), Error> {
-    /// # tokio_test::block_on(async {
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 2)");
-    /// let value: usize = Runtime::execute_module(&module, vec![], Default::default(), json_args!()).await?;
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let snapshot = runtime.snapshot_globals();
+    /// runtime.eval::<Undefined>("globalThis.user = 'alice'")?;
+    /// runtime.restore_globals(&snapshot)?;
+    /// let user: Option<String> = runtime.eval("globalThis.user")?;
+    /// assert_eq!(None, user);
     /// # Ok(())
-    /// # })
     /// # }
     /// ```
-    pub async fn execute_module<T>(
-        module: &Module,
-        side_modules: Vec<&Module>,
-        runtime_options: RuntimeOptions,
-        entrypoint_args: &FunctionArguments,
+    pub fn snapshot_globals(&mut self) -> GlobalSnapshot {
+        self.0.snapshot_globals()
+    }
+
+    /// Return `globalThis` to the state saved by `Runtime::snapshot_globals`
+    /// Properties added since the snapshot are deleted, and changed or deleted properties
+    /// get their saved values back. Objects are restored by reference, so changes made
+    /// inside a global object are not undone
+    ///
+    /// # Arguments
+    /// * `snapshot` - A snapshot taken from this runtime
+    ///
+    /// # Returns
+    /// A `Result` that is an error if an added property could not be deleted,
+    /// such as one declared with `var`
+    pub fn restore_globals(&mut self, snapshot: &GlobalSnapshot) -> Result<(), Error> {
+        self.0.restore_globals(snapshot)
+    }
+
+    /// Returns the current value of every counter and gauge set by scripts
+    /// using `rustyscript.incr(name, by = 1)` and `rustyscript.gauge(name, value)`
+    /// Metrics are integers - scripts passing any other number get an error
+    /// ```rust
+    /// use rustyscript::{ Runtime, Undefined };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("rustyscript.incr('emails_sent')")?;
+    /// assert_eq!(Some(&1), runtime.metrics_snapshot().get("emails_sent"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn metrics_snapshot(&mut self) -> HashMap<String, i64> {
+        self.0.metrics_snapshot()
+    }
+
+    /// Summarize the runtime's global state as a comparable value
+    /// Useful for asserting that two runtimes ended up in the same logical state
+    ///
+    /// The name and JSON value of each global is included, except for functions,
+    /// which are skipped. Globals that cannot be serialized contribute only their name
+    ///
+    /// # Returns
+    /// A `Result` containing the fingerprint, or an error (`Error`) if the globals
+    /// could not be read
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Undefined };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut a = Runtime::new(Default::default())?;
+    /// let mut b = Runtime::new(Default::default())?;
+    /// a.eval::<Undefined>("globalThis.x = 1")?;
+    /// b.eval::<Undefined>("globalThis.x = 2 - 1")?;
+    /// assert_eq!(a.global_fingerprint()?, b.global_fingerprint()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn global_fingerprint(&mut self) -> Result<Fingerprint, Error> {
+        let globals: Vec<(String, Option<String>)> = self.eval(
+            "Object.getOwnPropertyNames(globalThis).sort().flatMap((name) => {
+                try {
+                    const value = globalThis[name];
+                    if (typeof value === 'function') return [];
+                    return [[name, JSON.stringify(value) ?? null]];
+                } catch {
+                    return [[name, null]];
+                }
+            })",
+        )?;
+
+        let mut hasher = DefaultHasher::new();
+        globals.hash(&mut hasher);
+        Ok(Fingerprint(hasher.finish()))
+    }
+
+    /// Returns the current memory usage of the runtime's heap
+    /// Snapshot it before and after a call to detect memory growth
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::Runtime;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let stats = runtime.heap_stats();
+    /// assert!(stats.used_heap_size <= stats.total_heap_size);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn heap_stats(&mut self) -> HeapStats {
+        let mut stats = deno_core::v8::HeapStatistics::default();
+        self.deno_runtime()
+            .v8_isolate()
+            .get_heap_statistics(&mut stats);
+
+        HeapStats {
+            used_heap_size: stats.used_heap_size(),
+            total_heap_size: stats.total_heap_size(),
+            heap_size_limit: stats.heap_size_limit(),
+            external_memory: stats.external_memory(),
+        }
+    }
+
+    /// Returns the names of every op registered with the runtime, including
+    /// deno_core's built-in ops, rustyscript's own, and any user-provided extensions
+    /// Useful for debugging conflicts between extensions
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::Runtime;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let runtime = Runtime::new(Default::default())?;
+    /// for name in runtime.op_names() {
+    ///     println!("{name}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn op_names(&self) -> Vec<String> {
+        self.0.op_names().to_vec()
+    }
+
+    /// The error for a module without an entrypoint, listing the default entrypoints tried
+    pub(crate) fn missing_entrypoint(&self, module: &Module) -> Error {
+        self.0.missing_entrypoint(module)
+    }
+
+    /// Ask V8 to collect as much garbage as it can, right away
+    /// This is best-effort - V8 may still keep some unreachable objects alive
+    /// Mostly useful for benchmarks and leak detection, alongside `Runtime::heap_stats`
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::Runtime;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.request_gc();
+    /// let stats = runtime.heap_stats();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn request_gc(&mut self) {
+        self.deno_runtime().v8_isolate().low_memory_notification();
+    }
+
+    /// Read a file, and evaluate it as non-ECMAScript-module JavaScript code
+    /// Useful for configuration scripts, which are not modules
+    /// The script is evaluated in the global context, so changes persist
+    ///
+    /// # Arguments
+    /// * `path` - Path to the script. Relative paths are resolved against the
+    ///   runtime's current directory - see `Runtime::set_current_dir`
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized value of the script's last expression (`T`)
+    /// or an error (`Error`) if the file cannot be read, the script cannot be evaluated,
+    /// or the result cannot be deserialized. Errors thrown by the script include its filename
+    ///
+    /// # Example
+    /// ```no_run
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let port: u16 = runtime.eval_file("config/server.js")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eval_file<T>(&mut self, path: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.eval_file(path)
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code
+    /// The expression is evaluated in the global context, so changes persist
+    ///
+    /// # Arguments
+    /// * `expr` - A string representing the JavaScript expression to evaluate
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the expression (`T`)
+    /// or an error (`Error`) if the expression cannot be evaluated or if the
+    /// result cannot be deserialized.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let value:
+    ///    usize = runtime.eval("2 + 2")?;
+    /// assert_eq!(4, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eval<T>(&mut self, expr: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.eval(expr)
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code, like `Runtime::eval`,
+    /// but report a syntax error separately from an exception thrown while running
+    /// Useful for REPLs, which display the result or a formatted error
+    ///
+    /// # Arguments
+    /// * `expr` - A string representing the JavaScript expression to evaluate
+    ///
+    /// # Returns
+    /// A `Result` containing the outcome of the expression, or an error (`Error`)
+    /// if it timed out, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ EvalOutcome, Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// match runtime.try_eval::<usize>("2 +")? {
+    ///     EvalOutcome::Value(value) => println!("{value}"),
+    ///     EvalOutcome::Thrown(e) => println!("Uncaught {}", e.exception_message),
+    ///     EvalOutcome::ParseError(e) => println!("Invalid input: {e}"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_eval<T>(&mut self, expr: &str) -> Result<EvalOutcome<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if let Err(e) = self.0.check_script_syntax(expr) {
+            return Ok(EvalOutcome::ParseError(e));
+        }
+
+        match self.0.eval(expr) {
+            Ok(value) => Ok(EvalOutcome::Value(value)),
+            Err(Error::JsError(e)) => Ok(EvalOutcome::Thrown(e)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Performs exactly one iteration of the runtime's event loop
+    /// (one macrotask, and the microtasks it queues)
+    ///
+    /// This allows a host to interleave the work of several runtimes on a
+    /// single thread, by stepping each of them in turn
+    ///
+    /// # Returns
+    /// A `Result` containing true if work remains in the event loop,
+    /// or an error (`Error`) if a task failed
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// while runtime.step()? {
+    ///     // Do other work here
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn step(&mut self) -> Result<bool, Error> {
+        self.0.step()
+    }
+
+    /// An alias of `Runtime::step`, for hosts driving the runtime from an external event loop
+    /// Call it whenever the host loop ticks, until it returns false
+    pub fn poll_event_loop_once(&mut self) -> Result<bool, Error> {
+        self.step()
+    }
+
+    /// Calls a stored javascript function and deserializes its return value.
+    ///
+    /// # Arguments
+    /// * `function` - A The function object
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    pub async fn call_stored_function<'a, T>(
+        &'a mut self,
+        module_context: &ModuleHandle,
+        function: &JsFunction<'a>,
+        args: &FunctionArguments,
     ) -> Result<T, Error>
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        let mut runtime = Runtime::new(runtime_options)?;
-        let module = runtime.load_modules(module, side_modules).await?;
-        let value: T = runtime.call_entrypoint(&module, entrypoint_args).await?;
-        Ok(value)
+        self.0
+            .call_stored_function(module_context, function, args)
+            .await
     }
-}
 
-#[cfg(test)]
-mod test_runtime {
-    use crate::json_args;
-    use std::time::Duration;
-
-    use super::*;
-    use deno_core::extension;
+    /// Calls a javascript function, and checks its result against a type only known at runtime
+    /// Useful for generic dispatchers, where the expected type comes from a schema or config
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    /// * `expected` - A description of the type the function should return
+    ///
+    /// # Returns
+    /// A `Result` containing the validated result of the function call,
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result does not match the description.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, TypeDescriptor, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export function f() { return [1, 2]; };");
+    /// let module = runtime.load_module(&module).await?;
+    /// let expected = TypeDescriptor::array(TypeDescriptor::Number);
+    /// let value = runtime.call_function_dynamic(&module, "f", json_args!(), &expected).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn call_function_dynamic(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        args: &FunctionArguments,
+        expected: &TypeDescriptor,
+    ) -> Result<serde_json::Value, Error> {
+        let value: serde_json::Value = self.call_function(module_context, name, args).await?;
+        expected.validate(value)
+    }
 
-    #[test]
-    fn test_new() {
-        Runtime::new(Default::default()).expect("Could not create the runtime");
+    /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
+    ///
+    /// # Arguments
+    /// * `name` - A string representing the name of the javascript function to call.
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export function f() { return 2; };");
+    /// let module = runtime.load_module(&module).await?;
+    /// let value: usize = runtime.call_function(&module, "f", json_args!()).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn call_function<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.0.call_function(module_context, name, args).await
+    }
 
-        extension!(test_extension);
-        Runtime::new(RuntimeOptions {
-            extensions: vec![test_extension::init_ops_and_esm()],
+    /// Returns the number of parameters a function expects, which is the value of its `length`
+    /// Parameters with a default value, and any after them, are not counted, nor are rest parameters
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `name` - A string representing the name of the javascript function
+    ///
+    /// # Returns
+    /// A `Result` containing the function's arity, or an error (`Error`)
+    /// if the function cannot be found, or the value is not a function
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export function f(a, b) { return a + b; };");
+    /// let module = runtime.load_module(&module).await?;
+    /// assert_eq!(2, runtime.function_arity(&module, "f")?);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn function_arity(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<usize, Error> {
+        self.0.function_arity(module_context, name)
+    }
+
+    /// Calls a javascript function by its name, with arguments given as a JSON array
+    /// Useful when the arguments are already serialized, such as the body of an HTTP request,
+    /// since V8 parses the array directly instead of serializing each argument separately.
+    /// The runtime's `argument_serializer` is not used for these arguments
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `raw_json` - A JSON array, whose elements are passed to the function as arguments
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the arguments are not a JSON array, if the function
+    /// cannot be found, if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export function f(a, b) { return a + b; };");
+    /// let module = runtime.load_module(&module).await?;
+    /// let value: usize = runtime.call_function_raw(&module, "f", "[1, 2]").await?;
+    /// assert_eq!(3, value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn call_function_raw<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        raw_json: &str,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let args = self.0.parse_json_arguments(raw_json)?;
+        self.0.call_function(module_context, name, &args).await
+    }
+
+    /// Calls a javascript function by its name, with arguments that can include binary data
+    /// Use `Runtime::arg_bytes` to pass a `Uint8Array`, and `FunctionArgument::from`
+    /// for any JSON argument
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ FunctionArgument, Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const at = (data, i) => data[i];");
+    /// let module = runtime.load_module(&module).await?;
+    /// let args = [Runtime::arg_bytes(vec![1, 2, 3]), FunctionArgument::from(Runtime::into_arg(1))];
+    /// let value: u8 = runtime.call_function_with_args(&module, "at", &args).await?;
+    /// assert_eq!(2, value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn call_function_with_args<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        args: &[FunctionArgument],
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.0.call_function(module_context, name, args).await
+    }
+
+    /// Calls a javascript function by its name, without running the event loop
+    ///
+    /// If the function returns a promise, it is settled using only the microtask queue;
+    /// pending timers, and other tasks in the event loop, are deferred until the next
+    /// call that runs the event loop. This means that a promise which depends on a
+    /// timer or an async op cannot be settled by this call, and results in an error.
+    ///
+    /// # Arguments
+    /// * `name` - A string representing the name of the javascript function to call.
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, if the returned promise cannot be settled, or if the result
+    /// cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export async function f() { return 2; };");
+    /// let module = runtime.load_module(&module).await?;
+    /// let value: usize = runtime.call_function_immediate(&module, "f", json_args!())?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn call_function_immediate<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let function = self.0.get_function_by_name(module_context, name)?;
+        self.0
+            .call_function_by_ref_immediate(module_context, function, args)
+    }
+
+    /// Get a value from a runtime instance
+    /// See `Runtime::get_js_value` to tell `undefined` and `null` apart
+    ///
+    /// # Arguments
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result or an error (`Error`) if the
+    /// value cannot be found, if there are issues with, or if the result cannot be
+    ///  deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
+    /// let module = runtime.load_module(&module).await?;
+    /// let value: usize = runtime.get_value(&module, "my_value").await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn get_value<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.get_value(module_context, name).await
+    }
+
+    /// Get a value from a runtime instance as a `JsValue`
+    /// Unlike `Runtime::get_value`, this tells `undefined` and `null` apart,
+    /// and finds exports that are declared with either value
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the value, or an error (`Error`) if the value
+    /// cannot be found, or if it contains a reference to itself
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ JsValue, Module, Runtime };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const missing = undefined;");
+    /// let module = runtime.load_module(&module).await?;
+    /// let value = runtime.get_js_value(&module, "missing").await?;
+    /// assert_eq!(JsValue::Undefined, value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn get_js_value(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<JsValue, Error> {
+        self.0.get_js_value(module_context, name).await
+    }
+
+    /// Get several values from a runtime instance at once
+    /// Faster than calling `Runtime::get_value` for each one, since the event loop
+    /// is only driven a single time to resolve all of them
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `names` - The names of the values to find
+    ///
+    /// # Returns
+    /// A `Result` containing the values, in the same order as `names`,
+    /// or an error (`Error`) naming the first value that cannot be found
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const a = 1; export const b = 'two';");
+    /// let module = runtime.load_module(&module).await?;
+    /// let values = runtime.get_values(&module, &["a", "b"]).await?;
+    /// assert_eq!(vec![1.into(), "two".into()], values);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn get_values(
+        &mut self,
+        module_context: &ModuleHandle,
+        names: &[&str],
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        self.0.get_values(module_context, names).await
+    }
+
+    /// Get the default export of a module - the value of `export default`
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized default export (`T`), or an error (`Error`)
+    /// if the module has no default export, or it cannot be deserialized
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default { a: 1 };");
+    /// let module = runtime.load_module(&module).await?;
+    /// let value: rustyscript::serde_json::Value = runtime.get_default_export(&module)?;
+    /// assert_eq!(1, value["a"]);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn get_default_export<T>(&mut self, module_context: &ModuleHandle) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.get_default_export(module_context)?.ok_or_else(|| {
+            Error::ValueNotFound(format!(
+                "the default export of {}",
+                module_context.module().filename()
+            ))
+        })
+    }
+
+    /// Get a value from a runtime instance, without deserializing it
+    /// Useful for values serde cannot represent, such as circular objects,
+    /// or for implementing custom conversions with `Runtime::deno_runtime`
+    /// Promises are resolved before the value is returned
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing a handle to the value,
+    /// or an error (`Error`) if the value cannot be found
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error, deno_core::v8 };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const node = {}; node.parent = node;");
+    /// let module = runtime.load_module(&module).await?;
+    /// let value = runtime.get_value_raw(&module, "node").await?;
+    ///
+    /// let mut scope = runtime.deno_runtime().handle_scope();
+    /// assert!(v8::Local::new(&mut scope, value).is_object());
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn get_value_raw(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+    ) -> Result<deno_core::v8::Global<deno_core::v8::Value>, Error> {
+        self.0.get_value_ref_async(module_context, name).await
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// And call functions
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading modules, executing the
+    /// module, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
+    /// runtime.load_module(&module).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn load_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        self.0.load_modules(None, vec![module]).await
+    }
+
+    /// Evaluate a piece of ECMAScript module code, which may use `import` and `export`
+    /// The module-mode equivalent of `Runtime::eval` - the code is loaded as a new module,
+    /// under a generated filename in the current working directory
+    ///
+    /// # Arguments
+    /// * `source` - The module's contents
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading or executing the module
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = runtime.eval_module("export const value = 2 + 2;").await?;
+    /// let value: usize = runtime.get_value(&module, "value").await?;
+    /// assert_eq!(4, value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn eval_module(&mut self, source: &str) -> Result<ModuleHandle, Error> {
+        // Modules cannot be loaded twice, so each snippet needs its own filename
+        static EVAL_MODULE_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = EVAL_MODULE_ID.fetch_add(1, Ordering::Relaxed);
+
+        let module = Module::new(&format!("rustyscript_eval_module_{id}.js"), source);
+        self.load_module(&module).await
+    }
+
+    /// Load a module into the runtime, and return its default export as the module's result
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module, along with the deserialized
+    /// default export (`T`), or `None` if the module has no default export.
+    /// Returns an error (`Error`) if there are issues with loading or executing the module,
+    /// or if the default export cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default 2 + 2;");
+    /// let (module, value) = runtime.load_module_with_result::<usize>(&module).await?;
+    /// assert_eq!(Some(4), value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn load_module_with_result<T>(
+        &mut self,
+        module: &Module,
+    ) -> Result<(ModuleHandle, Option<T>), Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let module_context = self.load_module(module).await?;
+        let value = self.0.get_default_export(&module_context)?;
+        Ok((module_context, value))
+    }
+
+    /// Load a changed version of a module that was already loaded, such as
+    /// when a file is edited while a dev server is running
+    ///
+    /// V8 cannot unload or replace a module, so the new version is loaded alongside
+    /// the old one, under a new specifier. This means:
+    /// - The old version stays in memory, and handles to it keep working
+    /// - Modules that already imported the old version keep using it
+    /// - Modules the reloaded module imports are not themselves reloaded
+    ///
+    /// State outside the module, such as `globalThis` and values stored with
+    /// [Runtime::put], is left as-is
+    ///
+    /// Since every version stays loaded, memory grows with each reload.
+    /// Use [Runtime::reload_module_with] to start over with a fresh runtime instead
+    ///
+    /// # Arguments
+    /// * `module` - The new version of the module, with the same filename as before
+    ///
+    /// # Returns
+    /// A `Result` containing a handle to the new version of the module,
+    /// or an error if it could not be loaded
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.load_module(&Module::new("config.js", "export const port = 80;")).await?;
+    ///
+    /// let module = Module::new("config.js", "export const port = 8080;");
+    /// let handle = runtime.reload_module(&module).await?;
+    /// let port: u16 = runtime.get_value(&handle, "port").await?;
+    /// assert_eq!(8080, port);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn reload_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        self.0.reload_module(module).await
+    }
+
+    /// Replace this runtime with a fresh one, and load a new version of a module into it
+    ///
+    /// Unlike [Runtime::reload_module], nothing is kept from the old runtime, so memory
+    /// does not grow with each reload. Values stored with [Runtime::put] are carried over
+    /// by `snapshot`, which is given the old runtime's state to copy from, and the new
+    /// runtime's state to copy into, before the module is loaded
+    ///
+    /// If the new runtime cannot be created, or the module fails to load,
+    /// this runtime is left unchanged
+    ///
+    /// # Arguments
+    /// * `module` - The new version of the module
+    /// * `options` - Options for the new runtime
+    /// * `snapshot` - Copies state from the old runtime into the new one
+    ///
+    /// # Returns
+    /// A `Result` containing a handle to the module in the new runtime,
+    /// or an error if it could not be loaded
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module };
+    ///
+    /// #[derive(Clone)]
+    /// struct Config(u16);
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.put(Config(80))?;
+    /// runtime.load_module(&Module::new("config.js", "export const port = 80;")).await?;
+    ///
+    /// let module = Module::new("config.js", "export const port = 8080;");
+    /// let handle = runtime
+    ///     .reload_module_with(&module, Default::default(), |old, new| {
+    ///         if let Some(config) = old.try_borrow::<Config>() {
+    ///             new.put(config.clone());
+    ///         }
+    ///     })
+    ///     .await?;
+    /// let port: u16 = runtime.get_value(&handle, "port").await?;
+    /// assert_eq!(8080, port);
+    /// assert_eq!(80, runtime.take::<Config>().unwrap().0);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn reload_module_with<F>(
+        &mut self,
+        module: &Module,
+        options: RuntimeOptions,
+        snapshot: F,
+    ) -> Result<ModuleHandle, Error>
+    where
+        F: FnOnce(&deno_core::OpState, &mut deno_core::OpState),
+    {
+        let mut runtime = Runtime::new(options)?;
+        {
+            let old = self.deno_runtime().op_state();
+            let new = runtime.deno_runtime().op_state();
+            snapshot(&old.try_borrow()?, &mut new.try_borrow_mut()?);
+        }
+
+        let handle = runtime.load_module(module).await?;
+        *self = runtime;
+        Ok(handle)
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// And call functions.
+    ///
+    /// This will load 'module' as the main module, and the others as side-modules.
+    /// Only one main module can be loaded per runtime
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading modules, executing the
+    /// module, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
+    /// runtime.load_modules(&module, vec![]).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn load_modules(
+        &mut self,
+        module: &Module,
+        side_modules: Vec<&Module>,
+    ) -> Result<ModuleHandle, Error> {
+        self.0.load_modules(Some(module), side_modules).await
+    }
+
+    /// Checks if a module has an entrypoint, without calling it
+    /// The entrypoint may have been registered by the module, or found using
+    /// the runtime's default entrypoint - see `RuntimeOptions::default_entrypoint`
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    ///
+    /// # Returns
+    /// True if `Runtime::call_entrypoint` would find an entrypoint to call
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export function run() { return 'test'; }");
+    /// let module = runtime.load_module(&module).await?;
+    ///
+    /// let value: String = if runtime.has_entrypoint(&module) {
+    ///     runtime.call_entrypoint(&module, json_args!()).await?
+    /// } else {
+    ///     runtime.call_function(&module, "run", json_args!()).await?
+    /// };
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn has_entrypoint(&self, module_context: &ModuleHandle) -> bool {
+        module_context.has_entrypoint()
+    }
+
+    /// Executes the entrypoint function of a module within the Deno runtime.
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
+    /// let module = runtime.load_module(&module).await?;
+    ///
+    /// // Run the entrypoint and handle the result
+    /// let value: String = runtime.call_entrypoint(&module, json_args!()).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn call_entrypoint<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        if let Some(entrypoint) = module_context.entrypoint() {
+            let value: serde_json::Value = self
+                .0
+                .call_function_by_ref_async(module_context, entrypoint.clone(), args)
+                .await?;
+            Ok(serde_json::from_value(value)?)
+        } else {
+            Err(self.0.missing_entrypoint(module_context.module()))
+        }
+    }
+
+    /// Calls the default export of a module, such as `export default function() { ... }`
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function (`T`)
+    /// if successful, or an error (`Error`) if the module has no default export,
+    /// the default export is not a function, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default (name) => `hello ${name}`;");
+    /// let module = runtime.load_module(&module).await?;
+    ///
+    /// let value: String = runtime.call_default_export(&module, json_args!("world")).await?;
+    /// assert_eq!("hello world", value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn call_default_export<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let description = format!(
+            "the default export of {}",
+            module_context.module().filename()
+        );
+        let function = match self.0.get_function_by_name(module_context, "default") {
+            Ok(function) => function,
+            Err(Error::ValueNotFound(_)) => return Err(Error::ValueNotFound(description)),
+            Err(Error::ValueNotCallable(_)) => return Err(Error::ValueNotCallable(description)),
+            Err(e) => return Err(e),
+        };
+
+        self.0
+            .call_function_by_ref_async(module_context, function, args)
+            .await
+    }
+
+    /// Executes a function registered by a module using `rustyscript.register_named`
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `name` - The name the function was registered under
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function (`T`)
+    /// if successful, or an error (`Error`) if no function was registered under that name,
+    /// the execution fails, or the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "rustyscript.register_named('onLoad', () => 'loaded')");
+    /// let module = runtime.load_module(&module).await?;
+    ///
+    /// let value: String = runtime.call_named_entrypoint(&module, "onLoad", json_args!()).await?;
+    /// assert_eq!("loaded", value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn call_named_entrypoint<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        if let Some(entrypoint) = module_context.named_entrypoint(name) {
+            let value: serde_json::Value = self
+                .0
+                .call_function_by_ref_async(module_context, entrypoint.clone(), args)
+                .await?;
+            Ok(serde_json::from_value(value)?)
+        } else {
+            Err(Error::Runtime(format!(
+                "{} has no entrypoint named {name}",
+                module_context.module()
+            )))
+        }
+    }
+
+    /// Loads a template module, whose entrypoint can then be called repeatedly
+    /// with different data, without reloading the module
+    ///
+    /// Globals created during a render are removed before the next one
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object that registers an entrypoint
+    ///
+    /// # Returns
+    /// A `Result` containing a handle to the template, or an error (`Error`)
+    /// if the module could not be loaded, or has no entrypoint
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("template.js", "rustyscript.register_entrypoint((n) => `Value: ${n}`)");
+    /// let mut template = runtime.instantiate_template(&module).await?;
+    ///
+    /// let value: String = template.render(&2).await?;
+    /// assert_eq!("Value: 2", value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn instantiate_template(
+        &mut self,
+        module: &Module,
+    ) -> Result<TemplateHandle<'_>, Error> {
+        TemplateHandle::new(self, module).await
+    }
+
+    /// Starts a module as a worker, running in its own isolate on a dedicated thread
+    ///
+    /// Messages sent with `WorkerHandle::post` are passed to the worker's global
+    /// `onmessage` function, and the worker can reply by calling `postMessage`.
+    /// Messages are serialized as JSON in both directions.
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the worker's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle to the worker, or an error (`Error`) if the
+    /// worker's thread could not be started
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let module = Module::new("worker.js", "globalThis.onmessage = (n) => postMessage(n * 2);");
+    /// let mut worker = Runtime::spawn_worker(&module)?;
+    /// worker.post(&2)?;
+    /// let value: usize = worker.recv()?;
+    /// assert_eq!(4, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spawn_worker(module: &Module) -> Result<WorkerHandle, Error> {
+        WorkerHandle::new(module)
+    }
+
+    /// Loads a module into a new runtime, executes the entry function and returns the
+    /// result of the module's execution, deserialized into the specified Rust type (`T`).
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    /// * `runtime_options` - Options for the creation of the runtime
+    /// * `entrypoint_args` - Arguments to pass to the entrypoint function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 2)");
+    /// let value: usize = Runtime::execute_module(&module, vec![], Default::default(), json_args!()).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn execute_module<T>(
+        module: &Module,
+        side_modules: Vec<&Module>,
+        runtime_options: RuntimeOptions,
+        entrypoint_args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let mut runtime = Runtime::new(runtime_options)?;
+        let module = runtime.load_modules(module, side_modules).await?;
+        let value: T = runtime.call_entrypoint(&module, entrypoint_args).await?;
+        Ok(value)
+    }
+
+    /// Loads a module into a new runtime, and runs a closure against it
+    /// The runtime is dropped once the closure's future completes
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `runtime_options` - Options for the creation of the runtime
+    /// * `f` - A closure given the runtime and a handle to the loaded module
+    ///
+    /// # Returns
+    /// A `Result` containing the closure's result, or an error (`Error`) if the runtime
+    /// could not be created, or the module could not be loaded
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # tokio_test::block_on(async {
+    /// let module = Module::new("test.js", "export const double = (n) => n * 2;");
+    /// let value: usize = Runtime::with_module(&module, Default::default(), |runtime, module| {
+    ///     Box::pin(async move { runtime.call_function(module, "double", json_args!(2)).await })
+    /// })
+    /// .await?;
+    /// assert_eq!(4, value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn with_module<T, F>(
+        module: &Module,
+        runtime_options: RuntimeOptions,
+        f: F,
+    ) -> Result<T, Error>
+    where
+        F: for<'a> FnOnce(
+            &'a mut Runtime,
+            &'a ModuleHandle,
+        ) -> Pin<Box<dyn Future<Output = Result<T, Error>> + 'a>>,
+    {
+        let mut runtime = Runtime::new(runtime_options)?;
+        let module = runtime.load_module(module).await?;
+        f(&mut runtime, &module).await
+    }
+}
+
+#[cfg(test)]
+mod test_runtime {
+    use crate::{json_args, traits::ToModuleSpecifier, MappedError, Operation, RustyExtension};
+    use std::time::Duration;
+
+    use super::*;
+    use deno_core::{extension, op2, Extension, ExtensionFileSource};
+
+    #[test]
+    fn test_new() {
+        Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        extension!(test_extension);
+        Runtime::new(RuntimeOptions {
+            extensions: vec![test_extension::init_ops_and_esm()],
+            ..Default::default()
+        })
+        .expect("Could not create runtime with extensions");
+    }
+
+    #[cfg(feature = "fs_import")]
+    #[tokio::test]
+    async fn test_set_current_dir() {
+        let main = Module::new("main.js", "export { value } from './lib.js';");
+        let mut values = vec![];
+        for name in ["first", "second"] {
+            let dir = std::env::temp_dir().join(format!("rustyscript_current_dir_{name}"));
+            std::fs::create_dir_all(&dir).expect("Could not create directory");
+            std::fs::write(
+                dir.join("lib.js"),
+                format!("export const value = '{name}';"),
+            )
+            .expect("Could not write module");
+
+            let mut runtime =
+                Runtime::new(Default::default()).expect("Could not create the runtime");
+            runtime
+                .set_current_dir(&dir)
+                .expect("Could not set directory");
+            let module = runtime
+                .load_module(&main)
+                .await
+                .expect("Could not load module");
+            let value: String = runtime
+                .get_value(&module, "value")
+                .await
+                .expect("Could not get value");
+            values.push(value);
+        }
+
+        assert_eq!(vec!["first", "second"], values);
+    }
+
+    #[tokio::test]
+    async fn test_read_text_file() {
+        let dir = std::env::temp_dir().join("rustyscript_read_allowlist");
+        let allowed_dir = dir.join("allowed");
+        std::fs::create_dir_all(&allowed_dir).expect("Could not create directory");
+        std::fs::write(allowed_dir.join("data.txt"), "hello").expect("Could not write file");
+        std::fs::write(dir.join("secret.txt"), "secret").expect("Could not write file");
+
+        // Relative paths are resolved against the runtime's current directory
+        let module = Module::new(
+            "test.js",
+            "
+            export const allowed = await rustyscript.readTextFile('allowed/data.txt');
+            export const denied = await rustyscript.readTextFile('secret.txt')
+                .then(() => 'read', (e) => e.message);
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            read_allowlist: vec![allowed_dir],
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        runtime
+            .set_current_dir(&dir)
+            .expect("Could not set current directory");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let allowed: String = runtime
+            .get_value(&module, "allowed")
+            .await
+            .expect("Could not get value");
+        assert_eq!("hello", allowed);
+
+        let denied: String = runtime
+            .get_value(&module, "denied")
+            .await
+            .expect("Could not get value");
+        assert!(denied.contains("Permission denied"));
+    }
+
+    #[test]
+    fn test_eval_file() {
+        let dir = std::env::temp_dir().join("rustyscript_eval_file");
+        std::fs::create_dir_all(&dir).expect("Could not create directory");
+        let config = dir.join("config.js");
+        std::fs::write(&config, "const base = 40;\nbase + 2").expect("Could not write file");
+        let broken = dir.join("broken.js");
+        std::fs::write(&broken, "throw new Error('bad config');").expect("Could not write file");
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let value: usize = runtime
+            .eval_file(&config.to_string_lossy())
+            .expect("Could not eval file");
+        assert_eq!(42, value);
+
+        let e = runtime
+            .eval_file::<Undefined>(&broken.to_string_lossy())
+            .expect_err("Did not detect error");
+        assert!(e.to_string().contains("broken.js"));
+
+        runtime
+            .eval_file::<Undefined>(&dir.join("missing.js").to_string_lossy())
+            .expect_err("Did not detect missing file");
+    }
+
+    #[test]
+    fn test_env_vars() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            env_vars: HashMap::from([("API_URL".to_string(), "http://localhost".to_string())]),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let value: String = runtime
+            .eval("rustyscript.env.get('API_URL')")
+            .expect("Could not read variable");
+        assert_eq!("http://localhost", value);
+
+        let missing: bool = runtime
+            .eval("rustyscript.env.get('HOME') === undefined")
+            .expect("Could not read variable");
+        assert!(missing);
+    }
+
+    #[tokio::test]
+    async fn test_get_value_raw() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const node = { name: 'root' };
+            node.parent = node;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let value = runtime
+            .get_value_raw(&module, "node")
+            .await
+            .expect("Could not get value");
+
+        let mut scope = runtime.deno_runtime().handle_scope();
+        let node = deno_core::v8::Local::new(&mut scope, value)
+            .to_object(&mut scope)
+            .expect("Value was not an object");
+        let key = deno_core::v8::String::new(&mut scope, "parent").unwrap();
+        let parent = node
+            .get(&mut scope, key.into())
+            .expect("Could not get property");
+        assert!(parent.strict_equals(node.into()));
+    }
+
+    #[tokio::test]
+    async fn test_arg_bytes() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const describe = (data, label) =>
+                `${label}: ${data instanceof Uint8Array}, ${data.byteLength}, ${data[data.length - 1]}`;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let mut data = vec![0u8; 1024 * 1024];
+        data[1024 * 1024 - 1] = 9;
+        let args = [Runtime::arg_bytes(data), Runtime::into_arg("buffer").into()];
+        let value: String = runtime
+            .call_function_with_args(&module, "describe", &args)
+            .await
+            .expect("Could not call function");
+        assert_eq!("buffer: true, 1048576, 9", value);
+    }
+
+    #[tokio::test]
+    async fn test_commonjs() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            commonjs: true,
+            virtual_modules: HashMap::from([
+                (
+                    "math.cjs".to_string(),
+                    "
+                    const double = (n) => n * 2;
+                    module.exports = { add: (a, b) => a + b, double };
+                    "
+                    .to_string(),
+                ),
+                (
+                    "lib/math.cjs".to_string(),
+                    "module.exports = { name: 'lib' };".to_string(),
+                ),
+                (
+                    "lib/index.js".to_string(),
+                    "export const name = require('./math.cjs').name;".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "main.js",
+            "
+            const math = require('./math.cjs');
+            export const addThenDouble = (a, b) => math.double(math.add(a, b));
+        ",
+        );
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .call_function(&module, "addThenDouble", json_args!(1, 2))
+            .await
+            .expect("Could not call function");
+        assert_eq!(6, value);
+
+        let module = Module::new("lib.cjs", "exports.answer = 42;");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load CommonJS module");
+        let value: serde_json::Value = runtime
+            .get_value(&module, "default")
+            .await
+            .expect("Could not get value");
+        assert_eq!(serde_json::json!({ "answer": 42 }), value);
+
+        // `require` resolves relative to the calling module, and imported `.cjs` files are wrapped
+        let module = Module::new(
+            "imports.js",
+            "
+            import { name } from './lib/index.js';
+            import math from './lib/math.cjs';
+            export const names = [name, math.name];
+        ",
+        );
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let names: Vec<String> = runtime
+            .get_value(&module, "names")
+            .await
+            .expect("Could not get value");
+        assert_eq!(vec!["lib", "lib"], names);
+
+        // Without the option, there is no `require`
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let has_require: bool = runtime
+            .eval("typeof require !== 'undefined'")
+            .expect("Could not eval");
+        assert!(!has_require);
+    }
+
+    #[tokio::test]
+    async fn test_eval_module() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let first = runtime
+            .eval_module("export const value = 2;")
+            .await
+            .expect("Could not eval module");
+        let second = runtime
+            .eval_module("export const value = 3;")
+            .await
+            .expect("Could not eval second module");
+
+        let value: usize = runtime
+            .get_value(&first, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(2, value);
+
+        let value: usize = runtime
+            .get_value(&second, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(3, value);
+    }
+
+    #[tokio::test]
+    async fn test_with_module() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const add = (a, b) => a + b;
+            export const double = (n) => n * 2;
+        ",
+        );
+
+        let value: usize = Runtime::with_module(&module, Default::default(), |runtime, module| {
+            Box::pin(async move {
+                let sum: usize = runtime
+                    .call_function(module, "add", json_args!(1, 2))
+                    .await?;
+                runtime
+                    .call_function(module, "double", json_args!(sum))
+                    .await
+            })
+        })
+        .await
+        .expect("Could not run closure");
+        assert_eq!(6, value);
+    }
+
+    #[tokio::test]
+    async fn test_get_values() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const a = 1;
+            export const b = Promise.resolve('two');
+            globalThis.c = [3];
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let values = runtime
+            .get_values(&module, &["a", "b", "c"])
+            .await
+            .expect("Could not get values");
+        assert_eq!(
+            vec![
+                serde_json::json!(1),
+                serde_json::json!("two"),
+                serde_json::json!([3])
+            ],
+            values
+        );
+
+        match runtime.get_values(&module, &["a", "missing"]).await {
+            Err(Error::ValueNotFound(name)) => assert_eq!("missing", name),
+            other => panic!("Expected ValueNotFound, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_freeze_globals() {
+        let module = Module::new(
+            "test.js",
+            "
+            Array.prototype.push = function() { return 0; };
+            export const length = [].push(1);
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            freeze_globals: true,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        runtime
+            .load_module(&module)
+            .await
+            .expect_err("Frozen intrinsic was modified");
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not modify intrinsic");
+        let length: usize = runtime
+            .get_value(&module, "length")
+            .await
+            .expect("Could not get value");
+        assert_eq!(0, length);
+    }
+
+    #[test]
+    fn test_extension_factories() {
+        #[op2(fast)]
+        fn op_factory_answer() -> u32 {
+            42
+        }
+        extension!(factory_extension, ops = [op_factory_answer]);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_factories: vec![Box::new(factory_extension::init_ops)],
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let value: u32 = runtime
+            .eval("Deno.core.ops.op_factory_answer()")
+            .expect("Could not call op");
+        assert_eq!(42, value);
+    }
+
+    #[tokio::test]
+    async fn test_error_mapper() {
+        #[op2]
+        fn op_always_fails() -> Result<(), Error> {
+            Err(Error::ValueNotFound("config".to_string()))
+        }
+
+        #[op2(async)]
+        async fn op_async_fails(#[string] name: String) -> Result<(), Error> {
+            Err(Error::Timeout {
+                operation: Operation::Other,
+                message: name,
+                elapsed: None,
+            })
+        }
+        extension!(failing_extension, ops = [op_always_fails, op_async_fails]);
+
+        let module = Module::new(
+            "test.js",
+            "
+            let caught;
+            try {
+                Deno.core.ops.op_always_fails();
+            } catch (e) {
+                caught = `${e.name}: ${e.message}`;
+            }
+            export const result = caught;
+
+            // Errors resolved together each keep their own class and message
+            const settled = await Promise.allSettled([
+                Deno.core.ops.op_async_fails('first'),
+                Deno.core.ops.op_async_fails('second'),
+            ]);
+            export const results = settled.map((r) => `${r.reason.name}: ${r.reason.message}`);
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extensions: vec![failing_extension::init_ops()],
+            error_mapper: Some(Rc::new(|e| MappedError {
+                name: match e {
+                    Error::ValueNotFound(_) => "NotFoundError".to_string(),
+                    Error::Timeout { .. } => "TimeoutError".to_string(),
+                    _ => "Error".to_string(),
+                },
+                message: e.to_string(),
+            })),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let result: String = runtime
+            .get_value(&module, "result")
+            .await
+            .expect("Could not get value");
+        assert_eq!(
+            "NotFoundError: config could not be found in global, or module exports",
+            result
+        );
+
+        let results: Vec<String> = runtime
+            .get_value(&module, "results")
+            .await
+            .expect("Could not get value");
+        assert_eq!(
+            vec![
+                "TimeoutError: Operation timed out: first",
+                "TimeoutError: Operation timed out: second"
+            ],
+            results
+        );
+    }
+
+    #[test]
+    fn test_rusty_extensions() {
+        #[op2(fast)]
+        fn op_untranspiled_answer() -> u32 {
+            42
+        }
+        extension!(ops_only_extension, ops = [op_untranspiled_answer]);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            rusty_extensions: vec![RustyExtension::without_transpile(
+                ops_only_extension::init_ops(),
+            )],
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let value: u32 = runtime
+            .eval("Deno.core.ops.op_untranspiled_answer()")
+            .expect("Could not call op");
+        assert_eq!(42, value);
+    }
+
+    #[test]
+    fn test_broken_extension() {
+        let extension = Extension {
+            name: "broken_extension",
+            esm_files: std::borrow::Cow::Owned(vec![ExtensionFileSource::new(
+                "ext:broken_extension/broken.ts",
+                "export const value: = ;",
+            )]),
+            ..Default::default()
+        };
+
+        let result = Runtime::new(RuntimeOptions {
+            extensions: vec![extension],
+            ..Default::default()
+        });
+        match result {
+            Err(Error::Extension { name, .. }) => assert_eq!("broken_extension", name),
+            Err(e) => panic!("Unexpected error: {e}"),
+            Ok(_) => panic!("Broken extension was loaded"),
+        }
+    }
+
+    #[test]
+    fn test_initialize_platform() {
+        // Other tests may have already created a runtime in this process
+        if !platform::is_initialized() {
+            Runtime::initialize_platform(PlatformOptions {
+                thread_pool_size: 2,
+                idle_task_support: false,
+            })
+            .expect("Could not initialize the platform");
+        }
+
+        Runtime::initialize_platform(Default::default())
+            .expect_err("Did not detect double initialization");
+
+        Runtime::new(Default::default()).expect("Could not create the runtime");
+        Runtime::new(Default::default()).expect("Could not create a second runtime");
+    }
+
+    #[test]
+    fn test_crate_info() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let version: String = runtime
+            .eval("rustyscript.version")
+            .expect("Could not read version");
+        assert_eq!(env!("CARGO_PKG_VERSION"), version);
+
+        let web: bool = runtime
+            .eval("rustyscript.features.web")
+            .expect("Could not read features");
+        assert_eq!(cfg!(feature = "web"), web);
+    }
+
+    #[test]
+    fn test_default_locale() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            default_locale: Some("en-US".to_string()),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let value: String = runtime
+            .eval("new Intl.NumberFormat().format(1234.5)")
+            .expect("Could not format number");
+        assert_eq!("1,234.5", value);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            default_locale: Some("de-DE".to_string()),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let value: String = runtime
+            .eval("new Intl.NumberFormat().format(1234.5)")
+            .expect("Could not format number");
+        assert_eq!("1.234,5", value);
+
+        // Constructors that may be called without `new` still can
+        let value: String = runtime
+            .eval("Intl.NumberFormat().format(1234.5)")
+            .expect("Could not format number");
+        assert_eq!("1.234,5", value);
+        let instance: bool = runtime
+            .eval("new Intl.DateTimeFormat() instanceof Intl.DateTimeFormat")
+            .expect("Could not check instance");
+        assert!(instance);
+
+        runtime
+            .set_default_locale("en-US")
+            .expect("Could not set locale");
+        let value: String = runtime
+            .eval("(1234.5).toLocaleString()")
+            .expect("Could not format number");
+        assert_eq!("1,234.5", value);
+    }
+
+    #[tokio::test]
+    async fn test_call_function_raw() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function add(a, b) { return a + b; }
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let value: usize = runtime
+            .call_function_raw(&module, "add", "[1,2]")
+            .await
+            .expect("Could not call function");
+        assert_eq!(3, value);
+
+        let e = runtime
+            .call_function_raw::<usize>(&module, "add", "{\"a\": 1}")
+            .await
+            .expect_err("Non-array arguments were accepted");
+        assert!(matches!(e, Error::JsonDecode(_)));
+
+        let e = runtime
+            .call_function_raw::<usize>(&module, "add", "[1,")
+            .await
+            .expect_err("Invalid JSON was accepted");
+        assert!(matches!(e, Error::JsonDecode(_)));
+    }
+
+    #[tokio::test]
+    async fn test_take_emit_receiver() {
+        let module = Module::new(
+            "test.js",
+            "
+            for (let i = 1; i <= 3; i++) {
+                rustyscript.emit({ step: i });
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let receiver = runtime
+            .take_emit_receiver()
+            .expect("Could not take the receiver");
+        runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let steps: Vec<u64> = receiver
+            .try_iter()
+            .map(|value| value["step"].as_u64().unwrap_or_default())
+            .collect();
+        assert_eq!(vec![1, 2, 3], steps);
+    }
+
+    #[test]
+    fn test_late_v8_flags() {
+        // No test in this binary sets flags - see tests/v8_flags.rs for that
+        Runtime::new(Default::default()).expect("Could not create the runtime");
+        Runtime::new(RuntimeOptions {
+            v8_flags: vec!["--expose-gc".to_string()],
+            ..Default::default()
+        })
+        .expect_err("Flags were accepted after the platform was initialized");
+    }
+
+    #[test]
+    fn test_max_stack_size() {
+        let depth = "
+            let depth = 0;
+            try { (function recurse() { depth++; recurse(); })(); } catch {}
+            depth
+        ";
+
+        let mut unlimited = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let default_depth: usize = unlimited.eval(depth).expect("Could not eval");
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            max_stack_size: Some(128 * 1024),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let limited_depth: usize = runtime.eval(depth).expect("Could not eval");
+        assert!(limited_depth < default_depth / 2);
+
+        let e = runtime
+            .eval::<Undefined>("(function recurse() { return recurse() + 1; })()")
+            .expect_err("Unbounded recursion did not fail");
+        assert!(e.to_string().contains("Maximum call stack size exceeded"));
+
+        // The runtime is still usable afterwards
+        let value: usize = runtime.eval("1 + 1").expect("Could not eval");
+        assert_eq!(2, value);
+
+        // The limit is measured from each call, not from where the runtime was created
+        let deep_depth: usize = with_deeper_stack(256, || runtime.eval(depth))
+            .expect("Could not eval from a deeper stack");
+        assert!(deep_depth > limited_depth / 2);
+    }
+
+    /// Run a function with roughly `kilobytes` more of the thread's stack in use
+    fn with_deeper_stack<T>(kilobytes: usize, f: impl FnOnce() -> T) -> T {
+        if kilobytes == 0 {
+            return f();
+        }
+        let padding = std::hint::black_box([0u8; 1024]);
+        let result = with_deeper_stack(kilobytes - 1, f);
+        std::hint::black_box(&padding);
+        result
+    }
+
+    #[tokio::test]
+    async fn test_has_entrypoint() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&Module::new(
+                "with_entrypoint.js",
+                "rustyscript.register_entrypoint(() => 2);",
+            ))
+            .await
+            .expect("Could not load module");
+        assert!(runtime.has_entrypoint(&module));
+
+        let module = runtime
+            .load_module(&Module::new(
+                "without_entrypoint.js",
+                "export function run() { return 2; }",
+            ))
+            .await
+            .expect("Could not load module");
+        assert!(!runtime.has_entrypoint(&module));
+    }
+
+    #[tokio::test]
+    async fn test_json_module() {
+        let module = Module::new("config.json", r#"{ "name": "server", "ports": [80, 443] }"#);
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let config: serde_json::Value = runtime
+            .get_value(&module, "default")
+            .await
+            .expect("Could not get default export");
+        assert_eq!("server", config["name"]);
+        assert_eq!(443, config["ports"][1]);
+    }
+
+    #[tokio::test]
+    async fn test_reload_module() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let original = runtime
+            .load_module(&Module::new(
+                "reload.js",
+                "export const version = 1; export const label = () => 'first';",
+            ))
+            .await
+            .expect("Could not load module");
+
+        let reloaded = runtime
+            .reload_module(&Module::new(
+                "reload.js",
+                "export const version = 2; export const label = () => 'second';",
+            ))
+            .await
+            .expect("Could not reload module");
+
+        let version: usize = runtime
+            .get_value(&reloaded, "version")
+            .await
+            .expect("Could not get value");
+        assert_eq!(2, version);
+        let label: String = runtime
+            .call_function(&reloaded, "label", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!("second", label);
+
+        // The old version is still loaded
+        let version: usize = runtime
+            .get_value(&original, "version")
+            .await
+            .expect("Could not get value");
+        assert_eq!(1, version);
+    }
+
+    #[tokio::test]
+    async fn test_reload_module_with() {
+        #[derive(Clone)]
+        struct Counter(usize);
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime.put(Counter(5)).expect("Could not put value");
+        runtime
+            .eval::<Undefined>("globalThis.leftover = true;")
+            .expect("Could not eval");
+        runtime
+            .load_module(&Module::new("reload_with.js", "export const version = 1;"))
+            .await
+            .expect("Could not load module");
+
+        let handle = runtime
+            .reload_module_with(
+                &Module::new("reload_with.js", "export const version = 2;"),
+                Default::default(),
+                |old, new| {
+                    if let Some(counter) = old.try_borrow::<Counter>() {
+                        new.put(counter.clone());
+                    }
+                },
+            )
+            .await
+            .expect("Could not reload module");
+
+        let version: usize = runtime
+            .get_value(&handle, "version")
+            .await
+            .expect("Could not get value");
+        assert_eq!(2, version);
+
+        // Only the state copied by the snapshot survives
+        assert_eq!(5, runtime.take::<Counter>().expect("State was not kept").0);
+        let leftover: bool = runtime
+            .eval("typeof globalThis.leftover !== 'undefined'")
+            .expect("Could not eval");
+        assert!(!leftover);
+    }
+
+    #[tokio::test]
+    async fn test_module_dependencies() {
+        let first = Module::new("dependency_a.js", "export const a = 1;");
+        let second = Module::new(
+            "dependency_b.js",
+            "export { a } from './dependency_a.js'; export const b = 2;",
+        );
+        let main = Module::new(
+            "dependency_main.js",
+            "
+            import { a } from './dependency_a.js';
+            import { b } from './dependency_b.js';
+            export const sum = a + b;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&main, vec![&first, &second])
+            .await
+            .expect("Could not load modules");
+
+        let dependencies: Vec<String> = runtime
+            .module_dependencies(&module)
+            .iter()
+            .map(|s| s.path().rsplit('/').next().unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(vec!["dependency_a.js", "dependency_b.js"], dependencies);
+    }
+
+    #[tokio::test]
+    async fn test_original_position() {
+        let module = Module::new(
+            "test.ts",
+            "
+            interface Point {
+                x: number;
+                y: number;
+            }
+
+            export function sum(p: Point): number {
+                return p.x + p.y;
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        assert_eq!(None, runtime.original_position(&module, 1, 1));
+        runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        // Find the return statement in the transpiled code
+        let specifier = module
+            .filename()
+            .to_module_specifier()
+            .expect("Invalid filename");
+        let code = crate::transpiler::transpile(&specifier, module.contents())
+            .expect("Could not transpile");
+        let (line, text) = code
+            .lines()
+            .enumerate()
+            .find(|(_, text)| text.contains("return"))
+            .expect("No return statement");
+        let column = text.find("return").expect("No return statement");
+
+        let position = runtime.original_position(&module, line as u32 + 1, column as u32 + 1);
+        assert_eq!(Some((8, 17)), position);
+    }
+
+    #[tokio::test]
+    async fn test_call_function_dynamic() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const user = () => ({ id: 1, name: 'test', admin: false });
+            export const broken = () => ({ id: '1', name: 'test' });
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let expected = TypeDescriptor::object([
+            ("id", TypeDescriptor::Number),
+            ("name", TypeDescriptor::String),
+        ]);
+        let value = runtime
+            .call_function_dynamic(&module, "user", json_args!(), &expected)
+            .await
+            .expect("Could not call function");
+        assert_eq!(serde_json::json!({ "id": 1, "name": "test" }), value);
+
+        runtime
+            .call_function_dynamic(&module, "broken", json_args!(), &expected)
+            .await
+            .expect_err("Did not detect mismatched type");
+    }
+
+    #[tokio::test]
+    async fn test_cpu_profile() {
+        let module = Module::new(
+            "test.js",
+            "
+            export function busy() {
+                let n = 0;
+                const end = Date.now() + 100;
+                while (Date.now() < end) n++;
+                return n > 0;
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            cpu_profiling: true,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        runtime
+            .stop_cpu_profile()
+            .await
+            .expect_err("Stopped a profile that was not running");
+        runtime
+            .start_cpu_profile()
+            .await
+            .expect("Could not start profiling");
+        let result: bool = runtime
+            .call_function(&module, "busy", json_args!())
+            .await
+            .expect("Could not call function");
+        assert!(result);
+
+        let profile = runtime
+            .stop_cpu_profile()
+            .await
+            .expect("Could not stop profiling");
+        assert!(!profile.samples.is_empty());
+        assert!(profile
+            .nodes
+            .iter()
+            .any(|node| node.call_frame.function_name == "busy"));
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .start_cpu_profile()
+            .await
+            .expect_err("Profiled without enabling profiling");
+    }
+
+    #[test]
+    fn test_shared_array_buffer() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let e = runtime
+            .eval::<usize>("new SharedArrayBuffer(8).byteLength")
+            .expect_err("SharedArrayBuffer was available");
+        assert!(e.to_string().contains("allow_shared_array_buffer"));
+
+        // Probing for it does not throw
+        let probe: (String, bool) = runtime
+            .eval("[typeof SharedArrayBuffer, new ArrayBuffer(8) instanceof SharedArrayBuffer]")
+            .expect("Could not probe for SharedArrayBuffer");
+        assert_eq!(("function".to_string(), false), probe);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            allow_shared_array_buffer: true,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let length: usize = runtime
+            .eval("new SharedArrayBuffer(8).byteLength")
+            .expect("SharedArrayBuffer was not available");
+        assert_eq!(8, length);
+    }
+
+    #[test]
+    fn test_heap_stats() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let before = runtime.heap_stats();
+        assert!(before.heap_size_limit > 0);
+
+        runtime
+            .eval::<Undefined>("globalThis.big = Array.from({ length: 100000 }, (_, i) => ({ i }))")
+            .expect("Could not allocate");
+        let after = runtime.heap_stats();
+        assert!(after.used_heap_size > before.used_heap_size);
+    }
+
+    #[test]
+    fn test_request_gc() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .eval::<Undefined>("globalThis.big = Array.from({ length: 100000 }, (_, i) => ({ i }))")
+            .expect("Could not allocate");
+        runtime
+            .eval::<Undefined>("delete globalThis.big")
+            .expect("Could not release");
+        let before = runtime.heap_stats();
+
+        runtime.request_gc();
+        let after = runtime.heap_stats();
+        assert!(after.used_heap_size < before.used_heap_size);
+    }
+
+    #[test]
+    fn test_define_global_getter() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let counter = Rc::new(std::cell::Cell::new(0));
+        let next = counter.clone();
+        runtime
+            .define_global_getter(
+                "now",
+                Rc::new(move || {
+                    next.set(next.get() + 1);
+                    serde_json::json!(next.get())
+                }),
+            )
+            .expect("Could not define getter");
+
+        let values: Vec<usize> = runtime.eval("[now, now]").expect("Could not read getter");
+        assert_eq!(vec![1, 2], values);
+        assert_eq!(2, counter.get());
+
+        let value: usize = runtime
+            .eval("now = 10; now")
+            .expect("Could not read getter");
+        assert_eq!(3, value);
+    }
+
+    #[test]
+    fn test_into_arg() {
+        assert_eq!(2, Runtime::into_arg(2));
+        assert_eq!("test", Runtime::into_arg("test"));
+        assert_ne!("test", Runtime::into_arg(2));
+    }
+
+    #[tokio::test]
+    async fn test_get_value() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.a = 2;
+            export const b = 'test';
+            export const fnc = null;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .await
+            .expect("Could not load module");
+
+        assert_eq!(
+            2,
+            runtime
+                .get_value::<usize>(&module, "a")
+                .await
+                .expect("Could not find global")
+        );
+        assert_eq!(
+            "test",
+            runtime
+                .get_value::<String>(&module, "b")
+                .await
+                .expect("Could not find export")
+        );
+        runtime
+            .get_value::<Undefined>(&module, "c")
+            .await
+            .expect_err("Could not detect null");
+        runtime
+            .get_value::<Undefined>(&module, "d")
+            .await
+            .expect_err("Could not detect undeclared");
+    }
+
+    #[tokio::test]
+    async fn test_load_module() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.register_entrypoint(() => 2);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .await
+            .expect("Could not load module");
+        assert_ne!(0, module.id());
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module1 = Module::new(
+            "importme.js",
+            "
+            export const value = 2;
+        ",
+        );
+        let module2 = Module::new(
+            "test.js",
+            "
+            import { value } from './importme.js';
+            rustyscript.register_entrypoint(() => value);
+        ",
+        );
+        runtime
+            .load_module(&module1)
+            .await
+            .expect("Could not load modules");
+        let module = runtime
+            .load_module(&module2)
+            .await
+            .expect("Could not load modules");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .await
+            .expect("Could not call exported fn");
+        assert_eq!(2, value);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            await new Promise(r => setTimeout(r, 2000));
+        ",
+        );
+        runtime
+            .load_modules(&module, vec![])
+            .await
+            .expect_err("Did not interupt after timeout");
+    }
+
+    #[tokio::test]
+    async fn test_load_module_bytes() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new_bytes("data.json", br#"{"value": 2}"#.to_vec());
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        #[derive(serde::Deserialize)]
+        struct Data {
+            value: usize,
+        }
+        let data: Data = runtime
+            .get_value(&module, "default")
+            .await
+            .expect("Could not get default export");
+        assert_eq!(2, data.value);
+
+        let module = Module::new_bytes("invalid.json", vec![0xff, 0xfe, 0xfd]);
+        runtime
+            .load_module(&module)
+            .await
+            .expect_err("Did not reject invalid module");
+    }
+
+    #[tokio::test]
+    async fn test_virtual_modules() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            virtual_modules: std::collections::HashMap::from([
+                ("util.js".to_string(), "export const value = 2;".to_string()),
+                (
+                    "lib.ts".to_string(),
+                    "
+                    import { value } from './util.js';
+                    export const doubled: number = value * 2;
+                    "
+                    .to_string(),
+                ),
+            ]),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            import { doubled } from './lib.ts';
+            export const value = doubled;
+        ",
+        );
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .get_value(&module, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(4, value);
+    }
+
+    #[tokio::test]
+    async fn test_json_import_attributes() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            virtual_modules: std::collections::HashMap::from([
+                ("data.json".to_string(), r#"{"value": 2}"#.to_string()),
+                ("data.txt".to_string(), r#"{"value": 3}"#.to_string()),
+                (
+                    "bad.json".to_string(),
+                    "export const value = 2;".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            import data from './data.json' with { type: 'json' };
+            import other from './data.txt' with { type: 'json' };
+            export const value = data.value + other.value;
+        ",
+        );
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .get_value(&module, "value")
+            .await
+            .expect("Could not get value");
+        assert_eq!(5, value);
+
+        let module = Module::new(
+            "test2.js",
+            "
+            import data from './bad.json' with { type: 'json' };
+        ",
+        );
+        let e = runtime
+            .load_module(&module)
+            .await
+            .expect_err("Did not detect invalid JSON");
+        assert!(e.to_string().contains("not valid JSON"));
+    }
+
+    #[tokio::test]
+    async fn test_stack_filter() {
+        let module = Module::new("test.js", "rustyscript.register_entrypoint(5);");
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        match runtime.load_module(&module).await {
+            Err(Error::JsError(e)) => {
+                let stack = e.stack.expect("Error had no stack");
+                assert!(!stack.contains("ext:rustyscript"));
+                assert!(stack.contains("test.js"));
+                assert!(e.frames.iter().all(|f| !f
+                    .file_name
+                    .as_deref()
+                    .unwrap_or("")
+                    .starts_with("ext:")));
+            }
+            _ => panic!("Did not get a JsError"),
+        }
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            stack_filter: None,
             ..Default::default()
         })
-        .expect("Could not create runtime with extensions");
+        .expect("Could not create the runtime");
+        match runtime.load_module(&module).await {
+            Err(Error::JsError(e)) => {
+                let stack = e.stack.expect("Error had no stack");
+                assert!(stack.contains("ext:rustyscript"));
+                assert!(stack.contains("test.js"));
+            }
+            _ => panic!("Did not get a JsError"),
+        }
     }
 
-    #[test]
-    fn test_into_arg() {
-        assert_eq!(2, Runtime::into_arg(2));
-        assert_eq!("test", Runtime::into_arg("test"));
-        assert_ne!("test", Runtime::into_arg(2));
+    #[tokio::test]
+    async fn test_load_modules() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.register_entrypoint(() => 2);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .await
+            .expect("Could not load module");
+        assert_ne!(0, module.id());
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module1 = Module::new(
+            "importme.js",
+            "
+            export const value = 2;
+        ",
+        );
+        let module2 = Module::new(
+            "test.js",
+            "
+            import { value } from './importme.js';
+            rustyscript.register_entrypoint(() => value);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module2, vec![&module1])
+            .await
+            .expect("Could not load modules");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .await
+            .expect("Could not call exported fn");
+        assert_eq!(2, value);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            await new Promise(r => setTimeout(r, 5000));
+        ",
+        );
+        runtime
+            .load_modules(&module, vec![])
+            .await
+            .expect_err("Did not interupt after timeout");
     }
 
     #[tokio::test]
-    async fn test_get_value() {
+    async fn test_call_entrypoint() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
         let module = Module::new(
             "test.js",
             "
-            globalThis.a = 2;
-            export const b = 'test';
-            export const fnc = null;
+            rustyscript.register_entrypoint(() => 2);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .await
+            .expect("Could not call registered fn");
+        assert_eq!(2, value);
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            default_entrypoint: Some("load".to_string()),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export const load = () => 2;
         ",
         );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .call_entrypoint(&module, json_args!())
+            .await
+            .expect("Could not call exported fn");
+        assert_eq!(2, value);
 
         let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export const load = () => 2;
+        ",
+        );
         let module = runtime
             .load_modules(&module, vec![])
             .await
             .expect("Could not load module");
+        runtime
+            .call_entrypoint::<Undefined>(&module, json_args!())
+            .await
+            .expect_err("Did not detect no entrypoint");
+    }
 
-        assert_eq!(
-            2,
-            runtime
-                .get_value::<usize>(&module, "a")
-                .await
-                .expect("Could not find global")
+    #[tokio::test]
+    async fn test_execute_module() {
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.register_entrypoint(() => 2);
+        ",
         );
-        assert_eq!(
-            "test",
-            runtime
-                .get_value::<String>(&module, "b")
+        let value: usize =
+            Runtime::execute_module(&module, vec![], Default::default(), json_args!())
                 .await
-                .expect("Could not find export")
+                .expect("Could not exec module");
+        assert_eq!(2, value);
+
+        let module = Module::new(
+            "test.js",
+            "
+            function load() { return 2; }
+        ",
         );
+        Runtime::execute_module::<Undefined>(&module, vec![], Default::default(), json_args!())
+            .await
+            .expect_err("Could not detect no entrypoint");
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_poll_event_loop_once() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
         runtime
-            .get_value::<Undefined>(&module, "c")
+            .eval::<Undefined>(
+                "globalThis.fired = false; setTimeout(() => globalThis.fired = true, 20);",
+            )
+            .expect("Could not start timer");
+        assert!(runtime.poll_event_loop_once().expect("Could not poll"));
+
+        // Give the timer time to fire, then let the loop finish
+        let mut pending = true;
+        for _ in 0..10 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            pending = runtime.poll_event_loop_once().expect("Could not poll");
+            if !pending {
+                break;
+            }
+        }
+        assert!(!pending);
+
+        let fired: bool = runtime.eval("globalThis.fired").expect("Could not eval");
+        assert!(fired);
+    }
+
+    #[tokio::test]
+    async fn test_missing_entrypoint() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            default_entrypoint: Some("main".to_string()),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new("test.js", "export const run = () => 2;");
+        let module = runtime
+            .load_module(&module)
             .await
-            .expect_err("Could not detect null");
+            .expect("Could not load module");
+
+        let e = runtime
+            .call_entrypoint::<usize>(&module, json_args!())
+            .await
+            .expect_err("Did not detect a missing entrypoint");
+        assert!(e.to_string().contains("(tried main)"));
+        match e {
+            Error::MissingEntrypoint { module, attempted } => {
+                assert_eq!("test.js", module.filename());
+                assert_eq!(vec!["main".to_string()], attempted);
+            }
+            e => panic!("Unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn test_op_names() {
+        let runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let op_names = runtime.op_names();
+        assert!(op_names.iter().any(|name| name == "op_register_entrypoint"));
+        assert!(op_names.iter().any(|name| name == "op_print"));
+    }
+
+    #[test]
+    fn test_try_eval() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        match runtime.try_eval::<usize>("2 + 2") {
+            Ok(EvalOutcome::Value(value)) => assert_eq!(4, value),
+            other => panic!("Expected a value, got {other:?}"),
+        }
+
+        match runtime.try_eval::<usize>("JSON.parse('{')") {
+            Ok(EvalOutcome::Thrown(e)) => assert_eq!(Some("SyntaxError"), e.name.as_deref()),
+            other => panic!("Expected an exception, got {other:?}"),
+        }
+
+        match runtime.try_eval::<usize>("1 +\n(2 *") {
+            Ok(EvalOutcome::ParseError(e)) => {
+                assert_eq!(2, e.line);
+                assert_eq!("Unexpected end of input", e.message);
+            }
+            other => panic!("Expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_meta() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            import_meta: HashMap::from([("buildId".to_string(), serde_json::json!("abc123"))]),
+            virtual_modules: HashMap::from([(
+                "lib.ts".to_string(),
+                "export const libBuild: string = import.meta.buildId;".to_string(),
+            )]),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "main.js",
+            "
+            export { libBuild } from './lib.ts';
+            export const build = import.meta.buildId;
+            export const url = import.meta.url;
+        ",
+        );
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let build: String = runtime
+            .get_value(&module, "build")
+            .await
+            .expect("Could not get value");
+        assert_eq!("abc123", build);
+
+        let lib_build: String = runtime
+            .get_value(&module, "libBuild")
+            .await
+            .expect("Could not get value");
+        assert_eq!("abc123", lib_build);
+
+        let url: String = runtime
+            .get_value(&module, "url")
+            .await
+            .expect("Could not get value");
+        assert!(url.ends_with("main.js"));
+    }
+
+    #[tokio::test]
+    async fn test_on_module_load() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = events.clone();
+        let mut runtime = Runtime::new(RuntimeOptions {
+            virtual_modules: HashMap::from([(
+                "lib.ts".to_string(),
+                "export const value: number = 2;".to_string(),
+            )]),
+            on_module_load: Some(std::sync::Arc::new(
+                move |event: &crate::ModuleLoadEvent| {
+                    recorded.lock().unwrap().push(event.clone());
+                },
+            )),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new("main.js", "export { value } from './lib.ts';");
         runtime
-            .get_value::<Undefined>(&module, "d")
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let events = events.lock().unwrap();
+        let loaded: Vec<_> = events
+            .iter()
+            .map(|event| {
+                event
+                    .specifier
+                    .path()
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+            })
+            .collect();
+        assert_eq!(vec!["main.js", "lib.ts"], loaded);
+        assert!(events.iter().all(|event| !event.cache_hit));
+        assert!(events.iter().all(|event| event.source_length > 0));
+    }
+
+    #[tokio::test]
+    async fn test_transpile_target() {
+        let warnings = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = warnings.clone();
+        let mut runtime = Runtime::new(RuntimeOptions {
+            virtual_modules: HashMap::from([(
+                "lib.ts".to_string(),
+                "export const size = (o?: { n: number }): number => o?.n ?? 0;".to_string(),
+            )]),
+            transpile_target: Some(crate::EsTarget::Es2017),
+            on_diagnostic: Some(std::sync::Arc::new(
+                move |specifier: &deno_core::ModuleSpecifier, diagnostic: &crate::Diagnostic| {
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push((specifier.to_string(), diagnostic.clone()));
+                },
+            )),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        // The module still loads and runs, with the newer syntax left as-is
+        let module = Module::new("main.js", "export { size } from './lib.ts';");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+        let size: usize = runtime
+            .call_function(&module, "size", json_args!({ "n": 2 }))
+            .await
+            .expect("Could not call function");
+        assert_eq!(2, size);
+
+        let warnings = warnings.lock().unwrap();
+        assert_eq!(2, warnings.len());
+        assert!(warnings
+            .iter()
+            .all(|(specifier, _)| specifier.ends_with("lib.ts")));
+        assert!(warnings[0].1.message.contains("requires ES2020"));
+    }
+
+    #[test]
+    fn test_snapshot_globals() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .eval::<Undefined>("globalThis.counter = 1; globalThis.name = 'a';")
+            .expect("Could not set globals");
+        let snapshot = runtime.snapshot_globals();
+
+        runtime
+            .eval::<Undefined>(
+                "globalThis.counter = 2; delete globalThis.name; globalThis.leaked = true;",
+            )
+            .expect("Could not mutate globals");
+        runtime
+            .restore_globals(&snapshot)
+            .expect("Could not restore globals");
+
+        let state: serde_json::Value = runtime
+            .eval("[globalThis.counter, globalThis.name, 'leaked' in globalThis]")
+            .expect("Could not eval");
+        assert_eq!(serde_json::json!([1, "a", false]), state);
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_fetch_allowlist() {
+        use std::io::{Read, Write};
+
+        // Minimal server answering a single request
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Could not bind");
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+                );
+            }
+        });
+
+        let module = Module::new(
+            "test.js",
+            "export async function get(url) { return await (await fetch(url)).text(); }",
+        );
+        let mut runtime = Runtime::new(RuntimeOptions {
+            fetch_allowlist: Some(vec!["127.0.0.1".to_string()]),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let handle = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let e = runtime
+            .call_function::<String>(&handle, "get", json_args!("http://example.com/"))
+            .await
+            .expect_err("Fetch to a disallowed host succeeded");
+        assert!(e.to_string().contains("Permission denied"));
+
+        let body: String = runtime
+            .call_function(
+                &handle,
+                "get",
+                json_args!(format!("http://127.0.0.1:{port}/")),
+            )
+            .await
+            .expect("Could not fetch from an allowed host");
+        assert_eq!("hello", body);
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_step() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.ticks = 0;
+            export function start() {
+                setTimeout(() => globalThis.ticks++, 10);
+                setTimeout(() => globalThis.ticks++, 50);
+            }
+        ",
+        );
+
+        let mut runtimes = vec![];
+        for _ in 0..2 {
+            let mut runtime =
+                Runtime::new(Default::default()).expect("Could not create the runtime");
+            let handle = runtime
+                .load_module(&module)
+                .await
+                .expect("Could not load module");
+            runtime
+                .call_function::<Undefined>(&handle, "start", json_args!())
+                .await
+                .expect("Could not start timers");
+            runtimes.push(runtime);
+        }
+
+        // Round-robin between the runtimes until both are done
+        let mut progress = vec![];
+        let mut pending = [true, true];
+        while pending.iter().any(|p| *p) {
+            for (i, runtime) in runtimes.iter_mut().enumerate() {
+                if pending[i] {
+                    pending[i] = runtime.step().expect("Could not step runtime");
+                    let ticks: usize = runtime.eval("globalThis.ticks").unwrap();
+                    progress.push((i, ticks));
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // Both runtimes made progress, and both finished
+        assert!(progress.contains(&(0, 1)));
+        assert!(progress.contains(&(1, 1)));
+        for runtime in &mut runtimes {
+            let ticks: usize = runtime.eval("globalThis.ticks").unwrap();
+            assert_eq!(2, ticks);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_chain() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            virtual_modules: HashMap::from([
+                ("a.js".to_string(), "import './broken.js';".to_string()),
+                (
+                    "broken.js".to_string(),
+                    "import './missing.js';".to_string(),
+                ),
+            ]),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new("main.js", "import './a.js';");
+        let e = runtime
+            .load_module(&module)
             .await
-            .expect_err("Could not detect undeclared");
+            .expect_err("Did not detect missing module")
+            .to_string();
+
+        let chain = e
+            .lines()
+            .find_map(|line| line.strip_prefix("Import chain: "))
+            .expect("Error did not include the import chain");
+        let chain: Vec<&str> = chain.split(" -> ").collect();
+        assert_eq!(4, chain.len());
+        assert!(chain[0].ends_with("/main.js"));
+        assert!(chain[1].ends_with("/a.js"));
+        assert!(chain[2].ends_with("/broken.js"));
+        assert!(chain[3].ends_with("/missing.js"));
     }
 
     #[tokio::test]
-    async fn test_load_module() {
+    async fn test_named_entrypoints() {
         let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
         let module = Module::new(
             "test.js",
             "
-            rustyscript.register_entrypoint(() => 2);
+            let requests = 0;
+            rustyscript.register_named('onLoad', () => 'loaded');
+            rustyscript.register_named('onRequest', (n) => requests += n);
         ",
         );
         let module = runtime
-            .load_modules(&module, vec![])
+            .load_module(&module)
             .await
             .expect("Could not load module");
-        assert_ne!(0, module.id());
 
-        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
-        let module1 = Module::new(
-            "importme.js",
-            "
-            export const value = 2;
-        ",
-        );
-        let module2 = Module::new(
-            "test.js",
-            "
-            import { value } from './importme.js';
-            rustyscript.register_entrypoint(() => value);
-        ",
-        );
-        runtime
-            .load_module(&module1)
+        let value: String = runtime
+            .call_named_entrypoint(&module, "onLoad", json_args!())
             .await
-            .expect("Could not load modules");
-        let module = runtime
-            .load_module(&module2)
-            .await
-            .expect("Could not load modules");
+            .expect("Could not call onLoad");
+        assert_eq!("loaded", value);
+
         let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
+            .call_named_entrypoint(&module, "onRequest", json_args!(2))
             .await
-            .expect("Could not call exported fn");
+            .expect("Could not call onRequest");
         assert_eq!(2, value);
 
-        let mut runtime = Runtime::new(RuntimeOptions {
-            timeout: Duration::from_millis(50),
-            ..Default::default()
-        })
-        .expect("Could not create the runtime");
-        let module = Module::new(
-            "test.js",
-            "
-            await new Promise(r => setTimeout(r, 2000));
-        ",
-        );
         runtime
-            .load_modules(&module, vec![])
+            .call_named_entrypoint::<Undefined>(&module, "onClose", json_args!())
             .await
-            .expect_err("Did not interupt after timeout");
+            .expect_err("Did not detect missing entrypoint");
     }
 
     #[tokio::test]
-    async fn test_load_modules() {
+    async fn test_entrypoint_metadata() {
         let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+
         let module = Module::new(
             "test.js",
             "
-            rustyscript.register_entrypoint(() => 2);
+            rustyscript.register_entrypoint(() => 2, {
+                name: 'double',
+                description: 'Returns two',
+            });
         ",
         );
         let module = runtime
-            .load_modules(&module, vec![])
+            .load_module(&module)
             .await
             .expect("Could not load module");
-        assert_ne!(0, module.id());
+        let metadata = module
+            .entrypoint_metadata()
+            .expect("Did not find entrypoint metadata");
+        assert_eq!(Some("double"), metadata.name.as_deref());
+        assert_eq!(Some("Returns two"), metadata.description.as_deref());
 
-        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
-        let module1 = Module::new(
-            "importme.js",
-            "
-            export const value = 2;
-        ",
-        );
-        let module2 = Module::new(
-            "test.js",
-            "
-            import { value } from './importme.js';
-            rustyscript.register_entrypoint(() => value);
-        ",
-        );
+        let module = Module::new("test2.js", "rustyscript.register_entrypoint(() => 2);");
         let module = runtime
-            .load_modules(&module2, vec![&module1])
-            .await
-            .expect("Could not load modules");
-        let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
+            .load_module(&module)
             .await
-            .expect("Could not call exported fn");
-        assert_eq!(2, value);
+            .expect("Could not load module");
+        assert!(module.entrypoint().is_some());
+        assert_eq!(None, module.entrypoint_metadata());
+    }
 
+    #[tokio::test]
+    async fn test_argument_serializer() {
         let mut runtime = Runtime::new(RuntimeOptions {
-            timeout: Duration::from_millis(50),
+            argument_serializer: Some(std::rc::Rc::new(crate::TaggedArgumentSerializer)),
             ..Default::default()
         })
         .expect("Could not create the runtime");
+
         let module = Module::new(
             "test.js",
             "
-            await new Promise(r => setTimeout(r, 5000));
+            export const check = (date, bytes, map, plain) =>
+                date instanceof Date && date.getTime() === 86400000 &&
+                bytes instanceof Uint8Array && bytes[1] === 2 &&
+                map instanceof Map && map.get('a') === 1 &&
+                plain.$other === 1;
         ",
         );
-        runtime
-            .load_modules(&module, vec![])
+        let module = runtime
+            .load_module(&module)
             .await
-            .expect_err("Did not interupt after timeout");
+            .expect("Could not load module");
+
+        let value: bool = runtime
+            .call_function(
+                &module,
+                "check",
+                &[
+                    crate::TaggedArgumentSerializer::date(86400000.0),
+                    crate::TaggedArgumentSerializer::bytes(&[1, 2, 3]),
+                    crate::TaggedArgumentSerializer::map([(
+                        serde_json::json!("a"),
+                        serde_json::json!(1),
+                    )]),
+                    serde_json::json!({ "$other": 1 }),
+                ],
+            )
+            .await
+            .expect("Could not call function");
+        assert!(value);
     }
 
     #[tokio::test]
-    async fn test_call_entrypoint() {
+    async fn test_load_module_with_result() {
         let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
-        let module = Module::new(
-            "test.js",
-            "
-            rustyscript.register_entrypoint(() => 2);
-        ",
-        );
-        let module = runtime
-            .load_modules(&module, vec![])
+
+        let module = Module::new("test.js", "export default 42;");
+        let (_, value) = runtime
+            .load_module_with_result::<usize>(&module)
             .await
             .expect("Could not load module");
-        let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
+        assert_eq!(Some(42), value);
+
+        let module = Module::new("test2.js", "export const x = 42;");
+        let (_, value) = runtime
+            .load_module_with_result::<usize>(&module)
             .await
-            .expect("Could not call registered fn");
-        assert_eq!(2, value);
+            .expect("Could not load module");
+        assert_eq!(None, value);
+    }
+
+    #[test]
+    fn test_global_fingerprint() {
+        let mut a = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let mut b = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let mut c = Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        a.eval::<Undefined>("globalThis.config = { retries: 3 }; globalThis.f = () => 1;")
+            .expect("Could not eval");
+        b.eval::<Undefined>("globalThis.config = {}; config.retries = 1 + 2;")
+            .expect("Could not eval");
+        c.eval::<Undefined>("globalThis.config = { retries: 4 };")
+            .expect("Could not eval");
+
+        let a = a.global_fingerprint().expect("Could not fingerprint");
+        let b = b.global_fingerprint().expect("Could not fingerprint");
+        let c = c.global_fingerprint().expect("Could not fingerprint");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_node_compat() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let value: bool = runtime
+            .eval("typeof process === 'undefined' && typeof Buffer === 'undefined'")
+            .expect("Could not eval");
+        assert!(value);
 
         let mut runtime = Runtime::new(RuntimeOptions {
-            default_entrypoint: Some("load".to_string()),
+            node_compat: crate::NodeCompat {
+                process_env: true,
+                buffer: true,
+                global_alias: true,
+                env: HashMap::from([("FOO".to_string(), "bar".to_string())]),
+            },
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let value: String = runtime.eval("process.env.FOO").expect("Could not eval");
+        assert_eq!("bar", value);
+
+        let value: String = runtime
+            .eval("Buffer.from('hello').toString('base64')")
+            .expect("Could not eval");
+        assert_eq!("aGVsbG8=", value);
+
+        let value: String = runtime
+            .eval("Buffer.from('aGVsbG8=', 'base64').toString()")
+            .expect("Could not eval");
+        assert_eq!("hello", value);
+
+        let value: bool = runtime
+            .eval("global === globalThis")
+            .expect("Could not eval");
+        assert!(value);
+    }
+
+    #[test]
+    fn test_inspector() {
+        use std::io::{Read, Write};
+
+        let runtime = Runtime::new(RuntimeOptions {
+            inspector: Some(crate::InspectorOptions {
+                address: "127.0.0.1:0".parse().unwrap(),
+                wait_for_connection: false,
+            }),
             ..Default::default()
         })
         .expect("Could not create the runtime");
+        let address = runtime
+            .inspector_address()
+            .expect("Inspector is not listening");
+
+        let mut stream = std::net::TcpStream::connect(address).expect("Could not connect");
+        stream
+            .write_all(b"GET /json/version HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .expect("Could not send request");
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .expect("Could not read response");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Protocol-Version"));
+    }
+
+    #[tokio::test]
+    async fn test_global_data() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .set_global_data("config", serde_json::json!({ "greeting": "hello" }))
+            .expect("Could not set data");
+
         let module = Module::new(
             "test.js",
             "
-            export const load = () => 2;
+            export const greet = (name) => `${rustyscript.getData('config').greeting} ${name}`;
+            export const missing = () => rustyscript.getData('missing');
         ",
         );
         let module = runtime
-            .load_modules(&module, vec![])
+            .load_module(&module)
             .await
             .expect("Could not load module");
-        let value: usize = runtime
-            .call_entrypoint(&module, json_args!())
+
+        let value: String = runtime
+            .call_function(&module, "greet", json_args!("world"))
             .await
-            .expect("Could not call exported fn");
-        assert_eq!(2, value);
+            .expect("Could not call function");
+        assert_eq!("hello world", value);
+
+        let value: Option<String> = runtime
+            .call_function(&module, "missing", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(None, value);
+    }
 
+    #[test]
+    fn test_metrics_snapshot() {
         let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .eval::<Undefined>(
+                "
+                rustyscript.incr('emails_sent');
+                rustyscript.incr('emails_sent');
+                rustyscript.gauge('queue_depth', 5);
+            ",
+            )
+            .expect("Could not eval");
+
+        let metrics = runtime.metrics_snapshot();
+        assert_eq!(Some(&2), metrics.get("emails_sent"));
+        assert_eq!(Some(&5), metrics.get("queue_depth"));
+
+        // Metrics are integers, so fractions are rejected with a clear error
+        let e = runtime
+            .eval::<Undefined>("rustyscript.incr('emails_sent', 0.5)")
+            .expect_err("A fractional increment was accepted");
+        assert!(e.to_string().contains("must be changed by a safe integer"));
+        runtime
+            .eval::<Undefined>("rustyscript.gauge('queue_depth', NaN)")
+            .expect_err("A NaN gauge was accepted");
+        assert_eq!(Some(&2), runtime.metrics_snapshot().get("emails_sent"));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_remaining() {
         let module = Module::new(
             "test.js",
             "
-            export const load = () => 2;
+            export const remaining = () => rustyscript.deadlineRemainingMs();
         ",
         );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
         let module = runtime
-            .load_modules(&module, vec![])
+            .load_module(&module)
             .await
             .expect("Could not load module");
-        runtime
-            .call_entrypoint::<Undefined>(&module, json_args!())
+        let remaining: f64 = runtime
+            .call_function(&module, "remaining", json_args!())
             .await
-            .expect_err("Did not detect no entrypoint");
+            .expect("Could not call function");
+        assert!(remaining.is_finite());
+        assert!(remaining > 0.0 && remaining <= 10000.0);
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&Module::new(
+                "test.js",
+                "export const remaining = () => rustyscript.deadlineRemainingMs();",
+            ))
+            .await
+            .expect("Could not load module");
+        let remaining: f64 = runtime
+            .call_function(&module, "remaining", json_args!())
+            .await
+            .expect("Could not call function");
+        assert!(remaining.is_infinite());
+    }
+
+    #[test]
+    fn test_now() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let times: Vec<f64> = runtime
+            .eval("[rustyscript.now(), rustyscript.now(), rustyscript.now()]")
+            .expect("Could not eval");
+        assert!(times[0] >= 0.0);
+        assert!(times.windows(2).all(|w| w[0] <= w[1]));
     }
 
     #[tokio::test]
-    async fn test_execute_module() {
+    async fn test_get_default_export() {
+        #[derive(serde::Deserialize)]
+        struct Config {
+            a: usize,
+        }
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&Module::new("with_default.js", "export default { a: 1 };"))
+            .await
+            .expect("Could not load module");
+        let config: Config = runtime
+            .get_default_export(&module)
+            .expect("Could not get default export");
+        assert_eq!(1, config.a);
+
+        let module = runtime
+            .load_module(&Module::new("no_default.js", "export const a = 1;"))
+            .await
+            .expect("Could not load module");
+        let e = runtime
+            .get_default_export::<Config>(&module)
+            .expect_err("Found a default export that does not exist");
+        assert!(matches!(e, Error::ValueNotFound(_)));
+        assert!(e.to_string().contains("default export of"));
+    }
+
+    #[tokio::test]
+    async fn test_call_default_export() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&Module::new(
+                "function.js",
+                "export default async function (a, b) { return a * b; }",
+            ))
+            .await
+            .expect("Could not load module");
+        let value: usize = runtime
+            .call_default_export(&module, json_args!(3, 4))
+            .await
+            .expect("Could not call default export");
+        assert_eq!(12, value);
+
+        // Types that only serde_v8 can read work here too
+        let module = runtime
+            .load_module(&Module::new(
+                "bytes.js",
+                "export default () => new Uint8Array([1, 2]);",
+            ))
+            .await
+            .expect("Could not load module");
+        let value: crate::Bytes = runtime
+            .call_default_export(&module, json_args!())
+            .await
+            .expect("Could not call default export");
+        assert_eq!(vec![1, 2], value.into_inner());
+
+        let module = runtime
+            .load_module(&Module::new("value.js", "export default 2;"))
+            .await
+            .expect("Could not load module");
+        let e = runtime
+            .call_default_export::<usize>(&module, json_args!())
+            .await
+            .expect_err("Called a default export that is not a function");
+        assert!(matches!(e, Error::ValueNotCallable(_)));
+
+        let module = runtime
+            .load_module(&Module::new("none.js", "export const a = 1;"))
+            .await
+            .expect("Could not load module");
+        let e = runtime
+            .call_default_export::<usize>(&module, json_args!())
+            .await
+            .expect_err("Called a default export that does not exist");
+        assert!(matches!(e, Error::ValueNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_function_arity() {
         let module = Module::new(
             "test.js",
             "
-            rustyscript.register_entrypoint(() => 2);
+            export const none = () => 0;
+            export const one = (a) => a;
+            export function two(a, b) { return a + b; }
+            export function defaults(a, b = 1, c) { return a; }
+            export function rest(a, ...others) { return a; }
+            export const value = 2;
         ",
         );
-        let value: usize =
-            Runtime::execute_module(&module, vec![], Default::default(), json_args!())
-                .await
-                .expect("Could not exec module");
-        assert_eq!(2, value);
 
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        for (name, arity) in [
+            ("none", 0),
+            ("one", 1),
+            ("two", 2),
+            ("defaults", 1),
+            ("rest", 1),
+        ] {
+            let value = runtime
+                .function_arity(&module, name)
+                .expect("Could not get arity");
+            assert_eq!(arity, value, "Wrong arity for {name}");
+        }
+
+        assert!(matches!(
+            runtime.function_arity(&module, "value"),
+            Err(Error::ValueNotCallable(_))
+        ));
+    }
+
+    #[test]
+    fn test_random_seed() {
+        let sequence = |random_seed| {
+            let mut runtime = Runtime::new(RuntimeOptions {
+                random_seed,
+                ..Default::default()
+            })
+            .expect("Could not create the runtime");
+            runtime
+                .eval::<Vec<f64>>("Array.from({ length: 5 }, () => Math.random())")
+                .expect("Could not eval")
+        };
+
+        let first = sequence(Some(42));
+        assert_eq!(first, sequence(Some(42)));
+        assert_ne!(first, sequence(Some(7)));
+        assert!(first.iter().all(|n| (0.0..1.0).contains(n)));
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_call_function_immediate() {
         let module = Module::new(
             "test.js",
             "
-            function load() { return 2; }
+            export async function test() {
+                setTimeout(() => globalThis.side_effect = true, 0);
+                await null;
+                return 2;
+            }
+
+            export function slow() {
+                return new Promise((r) => setTimeout(() => r(2), 10));
+            }
         ",
         );
-        Runtime::execute_module::<Undefined>(&module, vec![], Default::default(), json_args!())
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
             .await
-            .expect_err("Could not detect no entrypoint");
+            .expect("Could not load module");
+
+        let value: usize = runtime
+            .call_function_immediate(&module, "test", json_args!())
+            .expect("Could not call function");
+        assert_eq!(2, value);
+
+        let side_effect: Option<bool> = runtime
+            .eval("globalThis.side_effect")
+            .expect("Could not read global");
+        assert_eq!(None, side_effect);
+
+        runtime
+            .call_function_immediate::<usize>(&module, "slow", json_args!())
+            .expect_err("Settled a promise that needs the event loop");
     }
 
     #[tokio::test]
@@ -0,0 +1,168 @@
+use crate::{Error, InspectorOptions, ModuleCacheProvider, RuntimeOptions, RustyExtension};
+use deno_core::Extension;
+use std::{rc::Rc, time::Duration};
+
+/// Builds a set of `RuntimeOptions` one option at a time, checking
+/// that the options make sense together before returning them
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ MemoryModuleCacheProvider, Runtime, RuntimeOptionsBuilder };
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// let options = RuntimeOptionsBuilder::new()
+///     .timeout(Duration::from_secs(5))
+///     .default_entrypoint("main")
+///     .module_cache(MemoryModuleCacheProvider::default())
+///     .build()?;
+/// let runtime = Runtime::new(options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct RuntimeOptionsBuilder {
+    options: RuntimeOptions,
+}
+
+impl RuntimeOptionsBuilder {
+    /// Start from the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Amount of time to run for before killing the thread
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// Measure the timeout with the shared watchdog thread, instead of with tokio's timer
+    /// Requires a timeout to be set
+    pub fn precise_timeout(mut self, precise_timeout: bool) -> Self {
+        self.options.precise_timeout = precise_timeout;
+        self
+    }
+
+    /// Function to use as entrypoint if the module does not provide one
+    pub fn default_entrypoint(mut self, name: &str) -> Self {
+        self.options.default_entrypoint = Some(name.to_string());
+        self
+    }
+
+    /// Add a deno_core extension to the runtime
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.options.extensions.push(extension);
+        self
+    }
+
+    /// Add a deno_core extension to the runtime, choosing whether to transpile its sources
+    pub fn rusty_extension(mut self, extension: RustyExtension) -> Self {
+        self.options.rusty_extensions.push(extension);
+        self
+    }
+
+    /// Cache used to store compiled modules between runtimes
+    pub fn module_cache<C>(mut self, module_cache: C) -> Self
+    where
+        C: ModuleCacheProvider + 'static,
+    {
+        self.options.module_cache = Rc::new(module_cache);
+        self
+    }
+
+    /// Locale to use for `Intl` and `toLocaleString` calls that do not specify one
+    pub fn default_locale(mut self, locale: &str) -> Self {
+        self.options.default_locale = Some(locale.to_string());
+        self
+    }
+
+    /// Add an in-memory module, that imports will be resolved against
+    pub fn virtual_module(mut self, filename: &str, contents: &str) -> Self {
+        self.options
+            .virtual_modules
+            .insert(filename.to_string(), contents.to_string());
+        self
+    }
+
+    /// Enable the V8 inspector, for debugging scripts with Chrome DevTools
+    pub fn inspector(mut self, inspector: InspectorOptions) -> Self {
+        self.options.inspector = Some(inspector);
+        self
+    }
+
+    /// Add a flag to pass to V8, such as `--expose-gc`
+    pub fn v8_flag(mut self, flag: &str) -> Self {
+        self.options.v8_flags.push(flag.to_string());
+        self
+    }
+
+    /// Limit the size of the runtime's JS stack, in bytes
+    /// Cannot be combined with V8's own `--stack-size` flag
+    pub fn max_stack_size(mut self, size: usize) -> Self {
+        self.options.max_stack_size = Some(size);
+        self
+    }
+
+    /// Check the options, and return them
+    /// `Runtime::new` makes the same checks - see `RuntimeOptions::validate`
+    ///
+    /// # Returns
+    /// A `Result` containing the options, or an error if they conflict
+    pub fn build(self) -> Result<RuntimeOptions, Error> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
+#[cfg(test)]
+mod test_runtime_builder {
+    use super::*;
+    use crate::{MemoryModuleCacheProvider, Runtime};
+
+    #[test]
+    fn test_build() {
+        let options = RuntimeOptionsBuilder::new()
+            .timeout(Duration::from_secs(5))
+            .precise_timeout(true)
+            .default_entrypoint("main")
+            .module_cache(MemoryModuleCacheProvider::default())
+            .virtual_module("config.js", "export const port = 8080;")
+            .build()
+            .expect("Could not build options");
+        assert_eq!(Duration::from_secs(5), options.timeout);
+        assert!(options.precise_timeout);
+        assert_eq!(Some("main".to_string()), options.default_entrypoint);
+
+        let mut runtime = Runtime::new(options).expect("Could not create the runtime");
+        let value: usize = runtime.eval("1 + 1").expect("Could not eval");
+        assert_eq!(2, value);
+
+        RuntimeOptionsBuilder::new()
+            .precise_timeout(true)
+            .build()
+            .expect_err("Precise timeout without a timeout was accepted");
+
+        RuntimeOptionsBuilder::new()
+            .timeout(Duration::from_secs(5))
+            .inspector(InspectorOptions {
+                address: "127.0.0.1:0".parse().unwrap(),
+                wait_for_connection: false,
+            })
+            .build()
+            .expect_err("Inspector with a timeout was accepted");
+
+        RuntimeOptionsBuilder::new()
+            .max_stack_size(128 * 1024)
+            .v8_flag("--stack-size=256")
+            .build()
+            .expect_err("Conflicting stack sizes were accepted");
+
+        // The same checks apply to options built by hand
+        Runtime::new(RuntimeOptions {
+            precise_timeout: true,
+            ..Default::default()
+        })
+        .expect_err("Precise timeout without a timeout was accepted");
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{
+    CapturedOutput, Error, FunctionArguments, Module, Operation, RuntimeOptions,
+    ThreadModuleHandle, ThreadRuntime,
+};
+use deno_core::futures::{channel::mpsc, lock::Mutex, StreamExt};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Represents the set of options accepted by the pool constructor
+pub struct RuntimePoolOptions {
+    /// Number of runtimes in the pool
+    pub size: usize,
+
+    /// Amount of time a call will wait for a runtime to become available
+    pub acquire_timeout: Duration,
+
+    /// Modules loaded into every runtime when the pool is created
+    pub modules: Vec<Module>,
+
+    /// Function creating the options for each runtime
+    /// It is called on each runtime's thread
+    pub runtime_options: Arc<dyn Fn() -> RuntimeOptions + Send + Sync>,
+}
+
+impl Default for RuntimePoolOptions {
+    fn default() -> Self {
+        Self {
+            size: std::thread::available_parallelism()
+                .map(usize::from)
+                .unwrap_or(1),
+            acquire_timeout: Duration::MAX,
+            modules: Vec::new(),
+            runtime_options: Arc::new(RuntimeOptions::default),
+        }
+    }
+}
+
+/// A runtime in the pool, along with its copies of the pool's modules
+struct PoolWorker {
+    runtime: ThreadRuntime,
+    modules: Vec<ThreadModuleHandle>,
+}
+
+/// A runtime taken from the pool, which is returned to it when dropped
+/// This keeps the runtime in the pool even if the caller's future is dropped mid-call
+struct PoolLease<'a> {
+    release: &'a mpsc::UnboundedSender<usize>,
+    index: usize,
+}
+
+impl Drop for PoolLease<'_> {
+    fn drop(&mut self) {
+        self.release.unbounded_send(self.index).ok();
+    }
+}
+
+/// A pool of reusable runtimes, each on its own thread, and each pre-loaded
+/// with the same set of modules
+///
+/// Calls are dispatched to whichever runtime is idle, making this suitable
+/// for use behind a request handler. The pool is `Send` and `Sync`.
+pub struct RuntimePool {
+    workers: Vec<PoolWorker>,
+    idle: Mutex<mpsc::UnboundedReceiver<usize>>,
+    release: mpsc::UnboundedSender<usize>,
+    acquire_timeout: Duration,
+}
+
+impl RuntimePool {
+    /// Creates a new pool of runtimes, and loads the pool's modules into each
+    ///
+    /// # Arguments
+    /// * `options` - A `RuntimePoolOptions` struct that specifies the configuration of the pool.
+    ///
+    /// # Returns
+    /// A `Result` containing either the pool on success (`Ok`) or an error on failure (`Err`).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, RuntimePool, RuntimePoolOptions, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # let tokio_runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    /// # tokio_runtime.block_on(async {
+    /// let module = Module::new("test.js", "export const f = (n) => n * 2;");
+    /// let pool = RuntimePool::new(RuntimePoolOptions {
+    ///     size: 2,
+    ///     modules: vec![module.clone()],
+    ///     ..Default::default()
+    /// }).await?;
+    ///
+    /// let value: usize = pool.call_function(&module, "f", json_args!(2)).await?;
+    /// assert_eq!(4, value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn new(options: RuntimePoolOptions) -> Result<Self, Error> {
+        if options.size == 0 {
+            return Err(Error::Runtime(
+                "A runtime pool must contain at least one runtime".to_string(),
+            ));
+        }
+
+        let (release, idle) = mpsc::unbounded();
+        let mut workers = Vec::with_capacity(options.size);
+        for index in 0..options.size {
+            let runtime_options = options.runtime_options.clone();
+            let runtime = ThreadRuntime::new(move || runtime_options())?;
+
+            let mut modules = Vec::with_capacity(options.modules.len());
+            for module in &options.modules {
+                modules.push(runtime.load_module(module).await?);
+            }
+
+            workers.push(PoolWorker { runtime, modules });
+            release
+                .unbounded_send(index)
+                .map_err(|e| Error::Runtime(e.to_string()))?;
+        }
+
+        Ok(Self {
+            workers,
+            idle: Mutex::new(idle),
+            release,
+            acquire_timeout: options.acquire_timeout,
+        })
+    }
+
+    /// Returns the number of runtimes in the pool
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Call a function in one of the pool's modules, on the next idle runtime
+    ///
+    /// # Arguments
+    /// * `module` - One of the modules the pool was created with
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if no runtime became available in time, if the function
+    /// cannot be found, if there are issues with calling the function,
+    /// or if the result cannot be deserialized.
+    pub async fn call_function<T>(
+        &self,
+        module: &Module,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let lease = self.lease().await?;
+        let worker = &self.workers[lease.index];
+
+        match worker.modules.iter().find(|h| h.module() == module) {
+            Some(handle) => worker.runtime.call_function(handle, name, args).await,
+            None => Err(Error::Runtime(format!(
+                "{module} was not loaded into the pool"
+            ))),
+        }
+    }
+
+    /// Call a function in one of the pool's modules, on the next idle runtime,
+    /// capturing anything it prints instead of writing it to stdout and stderr
+    ///
+    /// Output is captured separately for each call, so concurrent calls on other
+    /// runtimes in the pool never appear in each other's output
+    ///
+    /// # Arguments
+    /// * `module` - One of the modules the pool was created with
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// and the captured output, or an error (`Error`) if no runtime became available
+    /// in time, if the function cannot be found, if there are issues with calling
+    /// the function, or if the result cannot be deserialized.
+    pub async fn call_function_captured<T>(
+        &self,
+        module: &Module,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<(T, CapturedOutput), Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let lease = self.lease().await?;
+        let worker = &self.workers[lease.index];
+
+        match worker.modules.iter().find(|h| h.module() == module) {
+            Some(handle) => {
+                worker
+                    .runtime
+                    .call_function_captured(handle, name, args)
+                    .await
+            }
+            None => Err(Error::Runtime(format!(
+                "{module} was not loaded into the pool"
+            ))),
+        }
+    }
+
+    /// Wait for a runtime to become idle, and take it until the lease is dropped
+    async fn lease(&self) -> Result<PoolLease<'_>, Error> {
+        let index = self.acquire().await?;
+        Ok(PoolLease {
+            release: &self.release,
+            index,
+        })
+    }
+
+    /// Wait for a runtime to become idle, and take it
+    async fn acquire(&self) -> Result<usize, Error> {
+        let started = Instant::now();
+        let next = async { self.idle.lock().await.next().await };
+        match tokio::time::timeout(self.acquire_timeout, next).await {
+            Ok(Some(index)) => Ok(index),
+            Ok(None) => Err(Error::Runtime("The runtime pool has stopped".to_string())),
+            Err(_) => Err(Error::Timeout {
+                operation: Operation::CallFunction,
+                message: "No runtime in the pool became available".to_string(),
+                elapsed: Some(started.elapsed()),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_runtime_pool {
+    use super::*;
+    use crate::json_args;
+    use deno_core::futures::future::join_all;
+
+    #[tokio::test]
+    async fn test_concurrent_calls() {
+        let module = Module::new("test.js", "export const square = (n) => n * n;");
+        let pool = RuntimePool::new(RuntimePoolOptions {
+            size: 2,
+            modules: vec![module.clone()],
+            ..Default::default()
+        })
+        .await
+        .expect("Could not create the pool");
+        assert_eq!(2, pool.size());
+
+        let calls = (0..8usize).map(|n| {
+            let module = &module;
+            let pool = &pool;
+            async move {
+                pool.call_function::<usize>(module, "square", json_args!(n))
+                    .await
+            }
+        });
+        let results = join_all(calls).await;
+
+        for (n, result) in results.into_iter().enumerate() {
+            assert_eq!(n * n, result.expect("Could not call function"));
+        }
+    }
+
+    #[cfg(feature = "console")]
+    #[tokio::test]
+    async fn test_captured_output() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const greet = (name) => {
+                console.log(`hello ${name}`);
+                console.error(`bye ${name}`);
+                return name.length;
+            };
+        ",
+        );
+        let pool = RuntimePool::new(RuntimePoolOptions {
+            size: 2,
+            modules: vec![module.clone()],
+            ..Default::default()
+        })
+        .await
+        .expect("Could not create the pool");
+
+        let (first, second) = tokio::join!(
+            pool.call_function_captured::<usize>(&module, "greet", json_args!("a")),
+            pool.call_function_captured::<usize>(&module, "greet", json_args!("bb")),
+        );
+
+        let (value, output) = first.expect("Could not call function");
+        assert_eq!(1, value);
+        assert_eq!("hello a\n", output.stdout);
+        assert_eq!("bye a\n", output.stderr);
+
+        let (value, output) = second.expect("Could not call function");
+        assert_eq!(2, value);
+        assert_eq!("hello bb\n", output.stdout);
+        assert_eq!("bye bb\n", output.stderr);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_call() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const slow = () => { const end = Date.now() + 50; while (Date.now() < end); };
+            export const fast = () => 2;
+        ",
+        );
+        let pool = RuntimePool::new(RuntimePoolOptions {
+            size: 1,
+            acquire_timeout: Duration::from_secs(5),
+            modules: vec![module.clone()],
+            ..Default::default()
+        })
+        .await
+        .expect("Could not create the pool");
+
+        // The caller gives up mid-call, but the runtime must still go back to the pool
+        tokio::time::timeout(
+            Duration::from_millis(1),
+            pool.call_function::<crate::Undefined>(&module, "slow", json_args!()),
+        )
+        .await
+        .expect_err("Call finished before the timeout");
+
+        let value: usize = pool
+            .call_function(&module, "fast", json_args!())
+            .await
+            .expect("Runtime was not returned to the pool");
+        assert_eq!(2, value);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_module() {
+        let pool = RuntimePool::new(RuntimePoolOptions {
+            size: 1,
+            ..Default::default()
+        })
+        .await
+        .expect("Could not create the pool");
+
+        pool.call_function::<usize>(&Module::new("test.js", ""), "f", json_args!())
+            .await
+            .expect_err("Did not detect unknown module");
+    }
+}
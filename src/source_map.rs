@@ -0,0 +1,228 @@
+//! Decoding of the inline source maps emitted by the transpiler
+//! Used to translate positions in transpiled code back to the original source
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD as BASE64},
+    Engine,
+};
+use deno_core::serde_json;
+
+/// Marks the start of an inline source map
+const INLINE_MAP_PREFIX: &str = "//# sourceMappingURL=data:application/json;base64,";
+
+/// A single mapping from a generated column to a position in the original source
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Mapping {
+    column: u32,
+    source_line: u32,
+    source_column: u32,
+}
+
+/// A decoded source map, holding the mappings of each generated line
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap {
+    lines: Vec<Vec<Mapping>>,
+}
+
+impl SourceMap {
+    /// Find and decode the inline source map at the end of some transpiled code
+    /// Returns None if the code has no valid inline source map
+    pub fn from_inline(code: &str) -> Option<Self> {
+        let start = code.rfind(INLINE_MAP_PREFIX)? + INLINE_MAP_PREFIX.len();
+        let encoded = code[start..].lines().next()?.trim();
+        let json = BASE64.decode(encoded.trim_end_matches('=')).ok()?;
+
+        let map: serde_json::Value = serde_json::from_slice(&json).ok()?;
+        Self::from_mappings(map.get("mappings")?.as_str()?)
+    }
+
+    /// Shift the mappings of one 0-based line of some transpiled code's inline
+    /// source map to the right, for text inserted at the start of that line
+    /// The offset is in UTF-16 code units, as source map columns are
+    ///
+    /// Returns None if the code has no valid inline source map, or nothing maps from that line
+    pub fn shift_inline(code: &str, line: usize, offset: u32) -> Option<String> {
+        let start = code.rfind(INLINE_MAP_PREFIX)? + INLINE_MAP_PREFIX.len();
+        let encoded = code[start..].lines().next()?.trim_end();
+        let json = BASE64.decode(encoded.trim_end_matches('=')).ok()?;
+        let mut map: serde_json::Value = serde_json::from_slice(&json).ok()?;
+
+        // Only the first segment of a line holds an absolute column
+        let mut lines: Vec<String> = map
+            .get("mappings")?
+            .as_str()?
+            .split(';')
+            .map(str::to_string)
+            .collect();
+        let segments = lines.get_mut(line).filter(|s| !s.is_empty())?;
+        let (first, rest) = segments.split_once(',').unwrap_or((segments.as_str(), ""));
+        let mut fields = decode_vlq(first)?;
+        fields[0] += i64::from(offset);
+        *segments = match rest {
+            "" => encode_vlq(&fields),
+            rest => format!("{},{rest}", encode_vlq(&fields)),
+        };
+        map["mappings"] = serde_json::Value::String(lines.join(";"));
+
+        let json = serde_json::to_vec(&map).ok()?;
+        let end = start + encoded.len();
+        Some(format!(
+            "{}{}{}",
+            &code[..start],
+            STANDARD.encode(json),
+            &code[end..]
+        ))
+    }
+
+    /// Decode the `mappings` field of a source map
+    fn from_mappings(mappings: &str) -> Option<Self> {
+        let mut lines = Vec::new();
+
+        // Source positions are relative to the previous segment, across lines
+        let mut source = 0i64;
+        let mut source_line = 0i64;
+        let mut source_column = 0i64;
+
+        for line in mappings.split(';') {
+            let mut column = 0i64;
+            let mut segments = Vec::new();
+            for segment in line.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq(segment)?;
+                column += fields[0];
+
+                // Segments without a source position map to nothing
+                if fields.len() >= 4 {
+                    source += fields[1];
+                    source_line += fields[2];
+                    source_column += fields[3];
+                    if source == 0 {
+                        segments.push(Mapping {
+                            column: u32::try_from(column).ok()?,
+                            source_line: u32::try_from(source_line).ok()?,
+                            source_column: u32::try_from(source_column).ok()?,
+                        });
+                    }
+                }
+            }
+
+            lines.push(segments);
+        }
+
+        Some(Self { lines })
+    }
+
+    /// Translate a 1-based position in the generated code to a 1-based
+    /// position in the original source
+    pub fn original_position(&self, line: u32, column: u32) -> Option<(u32, u32)> {
+        let segments = self.lines.get(line.checked_sub(1)? as usize)?;
+        let column = column.checked_sub(1)?;
+
+        let mapping = segments.iter().take_while(|m| m.column <= column).last()?;
+        Some((mapping.source_line + 1, mapping.source_column + 1))
+    }
+}
+
+/// Decode a base64 VLQ encoded source map segment into its fields
+fn decode_vlq(segment: &str) -> Option<Vec<i64>> {
+    let mut fields = Vec::new();
+    let mut value = 0i64;
+    let mut shift = 0;
+
+    for c in segment.bytes() {
+        let digit = i64::from(base64_value(c)?);
+        value += (digit & 0b11111) << shift;
+
+        if digit & 0b100000 == 0 {
+            let magnitude = value >> 1;
+            fields.push(if value & 1 == 1 {
+                -magnitude
+            } else {
+                magnitude
+            });
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 5;
+        }
+    }
+
+    (!fields.is_empty() && shift == 0).then_some(fields)
+}
+
+/// Encode the fields of a source map segment as base64 VLQ
+fn encode_vlq(fields: &[i64]) -> String {
+    const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut segment = String::new();
+    for field in fields {
+        // The sign is stored in the lowest bit
+        let mut value = (field.unsigned_abs() << 1) | u64::from(*field < 0);
+        loop {
+            let mut digit = value & 0b11111;
+            value >>= 5;
+            if value > 0 {
+                digit |= 0b100000;
+            }
+            segment.push(char::from(DIGITS[digit as usize]));
+            if value == 0 {
+                break;
+            }
+        }
+    }
+
+    segment
+}
+
+/// The value of a single VLQ digit, which uses the standard base64 alphabet
+fn base64_value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_source_map {
+    use super::*;
+
+    #[test]
+    fn test_decode_vlq() {
+        assert_eq!(Some(vec![0, 0, 0, 0]), decode_vlq("AAAA"));
+        assert_eq!(Some(vec![1, -1, 16]), decode_vlq("CDgB"));
+        assert_eq!(None, decode_vlq("g"));
+    }
+
+    #[test]
+    fn test_encode_vlq() {
+        assert_eq!("AAAA", encode_vlq(&[0, 0, 0, 0]));
+        assert_eq!("CDgB", encode_vlq(&[1, -1, 16]));
+    }
+
+    #[test]
+    fn test_shift_inline() {
+        let map = STANDARD.encode(r#"{"version":3,"mappings":"AAAA,IAAI;AACJ"}"#);
+        let code = format!("let a = 1;\nlet b = 2;\n{INLINE_MAP_PREFIX}{map}");
+
+        let shifted = SourceMap::shift_inline(&code, 0, 4).expect("Could not shift the map");
+        let map = SourceMap::from_inline(&shifted).expect("Could not decode the map");
+        assert_eq!(None, map.original_position(1, 1));
+        assert_eq!(Some((1, 1)), map.original_position(1, 5));
+        assert_eq!(Some((1, 5)), map.original_position(1, 9));
+        assert_eq!(Some((2, 1)), map.original_position(2, 1));
+
+        assert_eq!(None, SourceMap::shift_inline("let a = 1;", 0, 4));
+    }
+
+    #[test]
+    fn test_original_position() {
+        // Line 1 maps to source line 1, line 2 maps columns 0 and 4 to source line 3
+        let map = SourceMap::from_mappings("AAAA;AAEA,IAAI").expect("Could not decode mappings");
+        assert_eq!(Some((1, 1)), map.original_position(1, 1));
+        assert_eq!(Some((3, 1)), map.original_position(2, 3));
+        assert_eq!(Some((3, 5)), map.original_position(2, 7));
+        assert_eq!(None, map.original_position(3, 1));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{Error, Module, ModuleHandle, Runtime, Undefined};
+use deno_core::serde_json;
+
+/// A module loaded once, whose entrypoint can be called repeatedly with different data
+/// See `Runtime::instantiate_template`
+///
+/// Globals created during a render are removed once it completes, so renders
+/// cannot observe each other. Changes to globals that already existed when
+/// the template was instantiated are not reverted.
+pub struct TemplateHandle<'a> {
+    runtime: &'a mut Runtime,
+    module_context: ModuleHandle,
+    globals: Vec<String>,
+}
+
+impl<'a> TemplateHandle<'a> {
+    /// Load a template module into the runtime
+    pub(crate) async fn new(runtime: &'a mut Runtime, module: &Module) -> Result<Self, Error> {
+        let module_context = runtime.load_module(module).await?;
+        if module_context.entrypoint().is_none() {
+            return Err(runtime.missing_entrypoint(module));
+        }
+
+        let globals = runtime.eval("Object.getOwnPropertyNames(globalThis)")?;
+        Ok(Self {
+            runtime,
+            module_context,
+            globals,
+        })
+    }
+
+    /// Returns the handle of the loaded template module
+    pub fn module_context(&self) -> &ModuleHandle {
+        &self.module_context
+    }
+
+    /// Returns a mutable reference to the underlying runtime
+    pub fn runtime(&mut self) -> &mut Runtime {
+        self.runtime
+    }
+
+    /// Call the template's entrypoint with the given data
+    ///
+    /// # Arguments
+    /// * `data` - The value to pass to the entrypoint
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint (`T`)
+    /// or an error (`Error`) if the data cannot be serialized, the call fails,
+    /// or the result cannot be deserialized.
+    pub async fn render<T, D>(&mut self, data: &D) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+        D: serde::Serialize,
+    {
+        let data = serde_json::to_value(data)?;
+        let result = self
+            .runtime
+            .call_entrypoint(&self.module_context, &[data])
+            .await;
+
+        self.reset_globals()?;
+        result
+    }
+
+    /// Remove any globals created since the template was instantiated
+    fn reset_globals(&mut self) -> Result<(), Error> {
+        let globals = serde_json::to_string(&self.globals)?;
+        self.runtime.eval::<Undefined>(&format!(
+            "{{
+                const keep = new Set({globals});
+                for (const name of Object.getOwnPropertyNames(globalThis)) {{
+                    if (!keep.has(name)) delete globalThis[name];
+                }}
+            }}"
+        ))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_template {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_render() {
+        let module = Module::new(
+            "template.js",
+            "
+            rustyscript.register_entrypoint((data) => {
+                globalThis.last_name = data.name;
+                return `Hello ${data.name}!`;
+            });
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let mut template = runtime
+            .instantiate_template(&module)
+            .await
+            .expect("Could not instantiate template");
+
+        let first: String = template
+            .render(&serde_json::json!({ "name": "Alice" }))
+            .await
+            .expect("Could not render");
+        let second: String = template
+            .render(&serde_json::json!({ "name": "Bob" }))
+            .await
+            .expect("Could not render");
+        assert_eq!("Hello Alice!", first);
+        assert_eq!("Hello Bob!", second);
+
+        let leaked: bool = template
+            .runtime()
+            .eval("'last_name' in globalThis")
+            .expect("Could not eval");
+        assert!(!leaked);
+    }
+
+    #[tokio::test]
+    async fn test_missing_entrypoint() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        match runtime
+            .instantiate_template(&Module::new("template.js", "export const x = 1;"))
+            .await
+        {
+            Err(Error::MissingEntrypoint { .. }) => {}
+            _ => panic!("Did not detect missing entrypoint"),
+        }
+    }
+}
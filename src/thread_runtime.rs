@@ -0,0 +1,362 @@
+use crate::{
+    CapturedOutput, Error, FunctionArguments, Module, ModuleHandle, Runtime, RuntimeOptions,
+};
+use deno_core::{
+    futures::{
+        channel::{mpsc, oneshot},
+        StreamExt,
+    },
+    serde_json, ModuleId,
+};
+use std::collections::HashMap;
+
+/// A handle to a module loaded into a `ThreadRuntime`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ThreadModuleHandle {
+    module_id: ModuleId,
+    module: Module,
+}
+
+impl ThreadModuleHandle {
+    /// Return this module's contents
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Return this module's ID
+    pub fn id(&self) -> ModuleId {
+        self.module_id
+    }
+}
+
+type Reply<T> = oneshot::Sender<Result<T, Error>>;
+
+/// Work sent to the runtime's thread
+enum Command {
+    LoadModule(Module, Reply<ModuleId>),
+    CallFunction {
+        module_id: ModuleId,
+        name: String,
+        args: Vec<serde_json::Value>,
+        capture_output: bool,
+        reply: Reply<(serde_json::Value, Option<CapturedOutput>)>,
+    },
+    GetValue {
+        module_id: ModuleId,
+        name: String,
+        reply: Reply<serde_json::Value>,
+    },
+}
+
+/// A runtime that lives on a dedicated thread, and can be shared between threads
+///
+/// `Runtime` cannot be sent across threads, since the underlying isolate is tied
+/// to the thread it was created on. A `ThreadRuntime` owns a `Runtime` on its own
+/// OS thread, and forwards work to it - so it is `Send` and `Sync`, and its methods
+/// return futures that are `Send`.
+///
+/// Arguments and results cross the thread boundary as `serde_json::Value`s
+///
+/// The thread stops once the `ThreadRuntime` is dropped
+pub struct ThreadRuntime {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl ThreadRuntime {
+    /// Start a new runtime on a dedicated thread
+    ///
+    /// # Arguments
+    /// * `options` - A function returning the options for the runtime.
+    ///   It is called on the runtime's thread, since options cannot be sent across threads
+    ///
+    /// # Returns
+    /// A `Result` containing either the runtime on success (`Ok`) or an error on failure (`Err`).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, ThreadRuntime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// # let tokio_runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    /// # tokio_runtime.block_on(async {
+    /// let runtime = ThreadRuntime::new(Default::default)?;
+    /// let module = runtime.load_module(&Module::new("test.js", "export const f = (n) => n * 2;")).await?;
+    /// let value: usize = runtime.call_function(&module, "f", json_args!(2)).await?;
+    /// assert_eq!(4, value);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn new<F>(options: F) -> Result<Self, Error>
+    where
+        F: FnOnce() -> RuntimeOptions + Send + 'static,
+    {
+        let (commands, receiver) = mpsc::unbounded();
+        let (ready, started) = std::sync::mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("rustyscript runtime".to_string())
+            .spawn(move || Self::run(options, receiver, ready))?;
+
+        // Wait for the runtime to start, so that creation errors are reported here
+        started
+            .recv()
+            .map_err(|_| Error::Runtime("The runtime thread stopped unexpectedly".to_string()))??;
+
+        Ok(Self { commands })
+    }
+
+    /// Load a module into the runtime
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading modules, executing the
+    /// module, or if the result cannot be deserialized.
+    pub async fn load_module(&self, module: &Module) -> Result<ThreadModuleHandle, Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::LoadModule(module.clone(), reply))?;
+
+        Ok(ThreadModuleHandle {
+            module_id: response.await??,
+            module: module.clone(),
+        })
+    }
+
+    /// Call a function in a module loaded into the runtime
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by `ThreadRuntime::load_module`
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    pub async fn call_function<T>(
+        &self,
+        module_context: &ThreadModuleHandle,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (value, _) = self.call(module_context, name, args, false).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Call a function in a module loaded into the runtime, capturing anything
+    /// it prints instead of writing it to stdout and stderr
+    ///
+    /// Only output printed during this call is captured
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by `ThreadRuntime::load_module`
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// and the captured output, or an error (`Error`) if the function cannot be found,
+    /// if there are issues with calling the function, or if the result cannot be deserialized.
+    pub async fn call_function_captured<T>(
+        &self,
+        module_context: &ThreadModuleHandle,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<(T, CapturedOutput), Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (value, output) = self.call(module_context, name, args, true).await?;
+        Ok((serde_json::from_value(value)?, output.unwrap_or_default()))
+    }
+
+    async fn call(
+        &self,
+        module_context: &ThreadModuleHandle,
+        name: &str,
+        args: &FunctionArguments,
+        capture_output: bool,
+    ) -> Result<(serde_json::Value, Option<CapturedOutput>), Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::CallFunction {
+            module_id: module_context.id(),
+            name: name.to_string(),
+            args: args.to_vec(),
+            capture_output,
+            reply,
+        })?;
+
+        response.await?
+    }
+
+    /// Get a value from a module loaded into the runtime
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by `ThreadRuntime::load_module`
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized value (`T`)
+    /// or an error (`Error`) if the value cannot be found, or if it cannot be deserialized.
+    pub async fn get_value<T>(
+        &self,
+        module_context: &ThreadModuleHandle,
+        name: &str,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::GetValue {
+            module_id: module_context.id(),
+            name: name.to_string(),
+            reply,
+        })?;
+
+        Ok(serde_json::from_value(response.await??)?)
+    }
+
+    fn send(&self, command: Command) -> Result<(), Error> {
+        self.commands
+            .unbounded_send(command)
+            .map_err(|_| Error::Runtime("The runtime thread has stopped".to_string()))
+    }
+
+    fn run<F>(
+        options: F,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+        ready: std::sync::mpsc::Sender<Result<(), Error>>,
+    ) where
+        F: FnOnce() -> RuntimeOptions,
+    {
+        let tokio_runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(tokio_runtime) => tokio_runtime,
+            Err(e) => {
+                ready.send(Err(e.into())).ok();
+                return;
+            }
+        };
+
+        tokio_runtime.block_on(async move {
+            let mut runtime = match Runtime::new(options()) {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    ready.send(Err(e)).ok();
+                    return;
+                }
+            };
+            ready.send(Ok(())).ok();
+
+            let mut modules: HashMap<ModuleId, ModuleHandle> = HashMap::new();
+            while let Some(command) = commands.next().await {
+                match command {
+                    Command::LoadModule(module, reply) => {
+                        let result = runtime.load_module(&module).await.map(|handle| {
+                            let module_id = handle.id();
+                            modules.insert(module_id, handle);
+                            module_id
+                        });
+                        reply.send(result).ok();
+                    }
+
+                    Command::CallFunction {
+                        module_id,
+                        name,
+                        args,
+                        capture_output,
+                        reply,
+                    } => {
+                        let Some(handle) = modules.get(&module_id) else {
+                            let e = Error::Runtime(format!("Module {module_id} is not loaded"));
+                            reply.send(Err(e)).ok();
+                            continue;
+                        };
+
+                        // Each call gets its own buffer, so output is never shared between calls
+                        if capture_output {
+                            if let Err(e) = runtime.start_output_capture() {
+                                reply.send(Err(e)).ok();
+                                continue;
+                            }
+                        }
+
+                        let result = runtime.call_function(handle, &name, &args).await;
+                        let output = capture_output.then(|| runtime.take_output());
+                        reply.send(result.map(|value| (value, output))).ok();
+                    }
+
+                    Command::GetValue {
+                        module_id,
+                        name,
+                        reply,
+                    } => {
+                        let result = match modules.get(&module_id) {
+                            Some(handle) => runtime.get_value(handle, &name).await,
+                            None => {
+                                Err(Error::Runtime(format!("Module {module_id} is not loaded")))
+                            }
+                        };
+                        reply.send(result).ok();
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test_thread_runtime {
+    use super::*;
+    use crate::json_args;
+
+    #[test]
+    fn test_call_from_other_thread() {
+        fn assert_send<T: Send + Sync>(_: &T) {}
+
+        let runtime = ThreadRuntime::new(Default::default).expect("Could not create the runtime");
+        assert_send(&runtime);
+
+        let result = std::thread::spawn(move || {
+            let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Could not create tokio runtime");
+            tokio_runtime.block_on(async {
+                let module = runtime
+                    .load_module(&Module::new(
+                        "test.js",
+                        "
+                        export const value = 3;
+                        export const add = (a, b) => a + b;
+                    ",
+                    ))
+                    .await
+                    .expect("Could not load module");
+
+                let value: usize = runtime
+                    .get_value(&module, "value")
+                    .await
+                    .expect("Could not get value");
+                let sum: usize = runtime
+                    .call_function(&module, "add", json_args!(value, 2))
+                    .await
+                    .expect("Could not call function");
+                sum
+            })
+        })
+        .join()
+        .expect("Thread panicked");
+
+        assert_eq!(5, result);
+    }
+}
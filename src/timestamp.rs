@@ -0,0 +1,167 @@
+use deno_core::{serde_v8, v8};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The largest distance from the Unix epoch, in milliseconds, that a JS `Date` can hold
+const MAX_MILLIS: f64 = 8.64e15;
+
+/// A point in time, read directly from a JS `Date`
+///
+/// A `Date` normally deserializes as an empty object, which loses its value.
+/// `Timestamp` reads the milliseconds since the Unix epoch instead, and also accepts
+/// a plain number of milliseconds. It is serialized back to JS as that number,
+/// which `new Date(value)` accepts
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ Module, Runtime, Timestamp };
+/// use std::time::UNIX_EPOCH;
+///
+/// # fn main() -> Result<(), rustyscript::Error> {
+/// # tokio_test::block_on(async {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("test.js", "export const epoch = new Date(0);");
+/// let module = runtime.load_module(&module).await?;
+/// let epoch: Timestamp = runtime.get_value(&module, "epoch").await?;
+/// assert_eq!(Some(UNIX_EPOCH), epoch.to_system_time());
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Timestamp(pub f64);
+
+impl Timestamp {
+    /// Returns the number of milliseconds since the Unix epoch
+    pub fn as_millis(&self) -> f64 {
+        self.0
+    }
+
+    /// Convert the timestamp to a `SystemTime`
+    /// Returns None if the timestamp cannot be represented as a `SystemTime` on this platform
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let offset = Duration::try_from_secs_f64(self.0.abs() / 1000.0).ok()?;
+        if self.0 < 0.0 {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        }
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => Self(duration.as_secs_f64() * 1000.0),
+            Err(e) => Self(-e.duration().as_secs_f64() * 1000.0),
+        }
+    }
+}
+
+impl TryFrom<Timestamp> for SystemTime {
+    type Error = crate::Error;
+
+    fn try_from(timestamp: Timestamp) -> Result<Self, Self::Error> {
+        timestamp.to_system_time().ok_or_else(|| {
+            crate::Error::Runtime(format!(
+                "{}ms is outside the range of a system time",
+                timestamp.0
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_v8::Value::deserialize(deserializer)?.v8_value;
+        let millis = if let Ok(date) = v8::Local::<v8::Date>::try_from(value) {
+            date.value_of()
+        } else if let Ok(number) = v8::Local::<v8::Number>::try_from(value) {
+            number.value()
+        } else {
+            return Err(D::Error::custom(
+                "expected a Date, or a number of milliseconds",
+            ));
+        };
+
+        // Also rejects NaN, which is how JS represents an invalid date
+        if millis.abs() <= MAX_MILLIS {
+            Ok(Self(millis))
+        } else {
+            Err(D::Error::custom("invalid date"))
+        }
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test_timestamp {
+    use super::*;
+    use crate::{json_args, Module, Runtime};
+
+    #[tokio::test]
+    async fn test_timestamp() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const epoch = () => new Date(0);
+            export const before = () => new Date(-1500);
+            export const invalid = () => new Date('not a date');
+            export const year = (ms) => new Date(ms).getUTCFullYear();
+            export const number = (ms) => ms;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_module(&module)
+            .await
+            .expect("Could not load module");
+
+        let epoch: Timestamp = runtime
+            .call_function(&module, "epoch", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(0.0, epoch.as_millis());
+        assert_eq!(Some(UNIX_EPOCH), epoch.to_system_time());
+
+        let before: Timestamp = runtime
+            .call_function(&module, "before", json_args!())
+            .await
+            .expect("Could not call function");
+        assert_eq!(
+            UNIX_EPOCH - Duration::from_millis(1500),
+            SystemTime::try_from(before).expect("Timestamp was out of range")
+        );
+
+        runtime
+            .call_function::<Timestamp>(&module, "invalid", json_args!())
+            .await
+            .expect_err("Did not detect an invalid date");
+
+        // Numbers beyond the range of a JS Date are rejected
+        runtime
+            .call_function::<Timestamp>(&module, "number", json_args!(1e20))
+            .await
+            .expect_err("Did not detect an out of range timestamp");
+        assert_eq!(None, Timestamp(f64::MAX).to_system_time());
+
+        let timestamp = Timestamp::from(UNIX_EPOCH + Duration::from_secs(86400 * 366));
+        let year: usize = runtime
+            .call_function(&module, "year", json_args!(timestamp.as_millis()))
+            .await
+            .expect("Could not call function");
+        assert_eq!(1971, year);
+    }
+}
@@ -4,15 +4,29 @@
 //!
 //! It will only transpile, not typecheck (like Deno's `--no-check` flag).
 
+use deno_ast::swc::ast::{
+    ArrowExpr, AssignExpr, AssignOp, AwaitExpr, BigInt, BinExpr, BinaryOp, CatchClause, ClassProp,
+    ForOfStmt, Function, ObjectLit, ObjectPat, ObjectPatProp, OptChainExpr, PrivateMethod,
+    PrivateProp, PropOrSpread, StaticBlock,
+};
+use deno_ast::swc::visit::{Visit, VisitWith};
 use deno_ast::MediaType;
 use deno_ast::ParseParams;
+use deno_ast::SourcePos;
+use deno_ast::SourceRangedForSpanned;
 use deno_ast::SourceTextInfo;
 use deno_core::anyhow::Error;
 use deno_core::ExtensionFileSource;
 use deno_core::ExtensionFileSourceCode;
 use deno_core::ModuleSpecifier;
 
-use crate::traits::ToModuleSpecifier;
+use crate::{error::SyntaxError, traits::ToModuleSpecifier, Diagnostic};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Mutex, OnceLock},
+};
 
 fn should_transpile(media_type: &MediaType) -> bool {
     match media_type {
@@ -31,9 +45,184 @@ fn should_transpile(media_type: &MediaType) -> bool {
     }
 }
 
+/// Options for transpiling a module without running it
+/// See `rustyscript::transpile_module`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TranspileOptions {
+    /// Include an inline source map in the output
+    pub source_map: bool,
+}
+
+impl Default for TranspileOptions {
+    fn default() -> Self {
+        Self { source_map: true }
+    }
+}
+
+/// An ECMAScript version that transpiled code is expected to run on
+/// Transpiling does not downlevel syntax, so code using newer features is passed through
+/// as-is - see `RuntimeOptions::transpile_target` to be warned about it
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum EsTarget {
+    /// ECMAScript 2015 (ES6)
+    Es2015,
+
+    /// ECMAScript 2016 - adds `**`
+    Es2016,
+
+    /// ECMAScript 2017 - adds async functions
+    Es2017,
+
+    /// ECMAScript 2018 - adds object spread and rest, and async iteration
+    Es2018,
+
+    /// ECMAScript 2019 - adds catch clauses without a binding
+    Es2019,
+
+    /// ECMAScript 2020 - adds optional chaining, `??` and BigInt
+    Es2020,
+
+    /// ECMAScript 2021 - adds logical assignment
+    Es2021,
+
+    /// ECMAScript 2022 - adds class fields, private members and static blocks
+    Es2022,
+}
+
+impl Display for EsTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let year = match self {
+            Self::Es2015 => 2015,
+            Self::Es2016 => 2016,
+            Self::Es2017 => 2017,
+            Self::Es2018 => 2018,
+            Self::Es2019 => 2019,
+            Self::Es2020 => 2020,
+            Self::Es2021 => 2021,
+            Self::Es2022 => 2022,
+        };
+        write!(f, "ES{year}")
+    }
+}
+
+///
+/// Determines if a module's contents will be altered by transpilation
+pub fn is_transpiled(module_specifier: &ModuleSpecifier) -> bool {
+    should_transpile(&MediaType::from_specifier(module_specifier))
+}
+
 ///
 /// Transpiles source code from TS to JS without typechecking
 pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<String, Error> {
+    transpile_with_options(module_specifier, code, TranspileOptions::default())
+}
+
+/// Maximum number of entries kept by `transpile_cached`
+/// The oldest entries are evicted once it is full
+const TRANSPILE_CACHE_SIZE: usize = 1024;
+
+/// A single cached transpilation, along with the input it was produced from
+#[derive(Debug)]
+struct CachedTranspile {
+    specifier: String,
+    source: String,
+    code: String,
+}
+
+/// A bounded cache of transpiled code
+/// Entries are found by a hash of the specifier and source, and a hit is only
+/// used if the stored specifier and source match exactly
+#[derive(Debug)]
+pub(crate) struct TranspileCache {
+    entries: HashMap<u64, CachedTranspile>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl TranspileCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn key(module_specifier: &ModuleSpecifier, source: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (module_specifier.as_str(), source).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Get the transpiled code for a module, if this exact source was cached
+    pub(crate) fn get(&self, module_specifier: &ModuleSpecifier, source: &str) -> Option<&str> {
+        self.entries
+            .get(&Self::key(module_specifier, source))
+            .filter(|e| e.specifier == module_specifier.as_str() && e.source == source)
+            .map(|e| e.code.as_str())
+    }
+
+    /// Cache the transpiled code for a module, evicting the oldest entry if full
+    pub(crate) fn insert(
+        &mut self,
+        module_specifier: &ModuleSpecifier,
+        source: &str,
+        code: String,
+    ) {
+        let key = Self::key(module_specifier, source);
+        let entry = CachedTranspile {
+            specifier: module_specifier.to_string(),
+            source: source.to_string(),
+            code,
+        };
+
+        if self.entries.insert(key, entry).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Transpiled code shared by all runtimes in the process
+pub(crate) fn transpile_cache() -> &'static Mutex<TranspileCache> {
+    static CACHE: OnceLock<Mutex<TranspileCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(TranspileCache::new(TRANSPILE_CACHE_SIZE)))
+}
+
+///
+/// Transpiles source code from TS to JS without typechecking, reusing the
+/// result of any earlier transpilation of the same code in this process
+pub fn transpile_cached(module_specifier: &ModuleSpecifier, code: &str) -> Result<String, Error> {
+    if !is_transpiled(module_specifier) {
+        return Ok(code.to_string());
+    }
+
+    if let Some(code) = transpile_cache()
+        .lock()
+        .ok()
+        .and_then(|c| c.get(module_specifier, code).map(str::to_string))
+    {
+        return Ok(code);
+    }
+
+    let transpiled = transpile_with_options(module_specifier, code, TranspileOptions::default())?;
+    if let Ok(mut cache) = transpile_cache().lock() {
+        cache.insert(module_specifier, code, transpiled.clone());
+    }
+    Ok(transpiled)
+}
+
+///
+/// Transpiles source code from TS to JS without typechecking, using the given options
+pub fn transpile_with_options(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    options: TranspileOptions,
+) -> Result<String, Error> {
     let media_type = MediaType::from_specifier(module_specifier);
     let should_transpile = should_transpile(&media_type);
 
@@ -53,8 +242,12 @@ pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<Strin
 
         let emit_options = deno_ast::EmitOptions {
             keep_comments: true,
-            source_map: deno_ast::SourceMapOption::Inline,
-            inline_sources: true,
+            source_map: if options.source_map {
+                deno_ast::SourceMapOption::Inline
+            } else {
+                deno_ast::SourceMapOption::None
+            },
+            inline_sources: options.source_map,
             ..Default::default()
         };
 
@@ -67,6 +260,238 @@ pub fn transpile(module_specifier: &ModuleSpecifier, code: &str) -> Result<Strin
     Ok(code)
 }
 
+/// Finds `await` expressions and `for await` loops that are not
+/// nested inside a function
+#[derive(Default)]
+struct TopLevelAwaitFinder {
+    found: bool,
+}
+
+impl Visit for TopLevelAwaitFinder {
+    fn visit_await_expr(&mut self, _: &AwaitExpr) {
+        self.found = true;
+    }
+
+    fn visit_for_of_stmt(&mut self, stmt: &ForOfStmt) {
+        if stmt.is_await {
+            self.found = true;
+        } else {
+            stmt.visit_children_with(self);
+        }
+    }
+
+    // Awaits inside of functions are not top-level
+    fn visit_function(&mut self, _: &Function) {}
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+}
+
+///
+/// Determines if a module uses `await` outside of any function
+pub fn uses_top_level_await(module_specifier: &ModuleSpecifier, code: &str) -> Result<bool, Error> {
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier: module_specifier.clone(),
+        text_info: SourceTextInfo::from_string(code.to_string()),
+        media_type: MediaType::from_specifier(module_specifier),
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })?;
+
+    let mut finder = TopLevelAwaitFinder::default();
+    parsed.module().visit_with(&mut finder);
+    Ok(finder.found)
+}
+
+/// Finds syntax that is newer than a target version
+struct TargetChecker<'a> {
+    target: EsTarget,
+    text_info: &'a SourceTextInfo,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl TargetChecker<'_> {
+    /// Report a feature, if it is newer than the target
+    fn require(&mut self, start: SourcePos, feature: &str, version: EsTarget) {
+        if version > self.target {
+            let position = self.text_info.line_and_column_display(start);
+            self.diagnostics.push(Diagnostic {
+                code: 0,
+                message: format!(
+                    "{feature} requires {version}, but the target is {}",
+                    self.target
+                ),
+                line: position.line_number,
+                column: position.column_number,
+            });
+        }
+    }
+}
+
+impl Visit for TargetChecker<'_> {
+    fn visit_bin_expr(&mut self, expr: &BinExpr) {
+        match expr.op {
+            BinaryOp::Exp => self.require(expr.start(), "The ** operator", EsTarget::Es2016),
+            BinaryOp::NullishCoalescing => {
+                self.require(expr.start(), "The ?? operator", EsTarget::Es2020);
+            }
+            _ => {}
+        }
+        expr.visit_children_with(self);
+    }
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) {
+        match expr.op {
+            AssignOp::ExpAssign => self.require(expr.start(), "The **= operator", EsTarget::Es2016),
+            AssignOp::AndAssign | AssignOp::OrAssign | AssignOp::NullishAssign => {
+                self.require(expr.start(), "Logical assignment", EsTarget::Es2021);
+            }
+            _ => {}
+        }
+        expr.visit_children_with(self);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        match (function.is_async, function.is_generator) {
+            (true, true) => self.require(function.start(), "An async generator", EsTarget::Es2018),
+            (true, false) => self.require(function.start(), "An async function", EsTarget::Es2017),
+            _ => {}
+        }
+        function.visit_children_with(self);
+    }
+
+    fn visit_arrow_expr(&mut self, arrow: &ArrowExpr) {
+        if arrow.is_async {
+            self.require(arrow.start(), "An async function", EsTarget::Es2017);
+        }
+        arrow.visit_children_with(self);
+    }
+
+    fn visit_for_of_stmt(&mut self, stmt: &ForOfStmt) {
+        if stmt.is_await {
+            self.require(stmt.start(), "A for await loop", EsTarget::Es2018);
+        }
+        stmt.visit_children_with(self);
+    }
+
+    fn visit_object_lit(&mut self, object: &ObjectLit) {
+        if object
+            .props
+            .iter()
+            .any(|prop| matches!(prop, PropOrSpread::Spread(_)))
+        {
+            self.require(object.start(), "Object spread", EsTarget::Es2018);
+        }
+        object.visit_children_with(self);
+    }
+
+    fn visit_object_pat(&mut self, pattern: &ObjectPat) {
+        if let Some(ObjectPatProp::Rest(rest)) = pattern
+            .props
+            .iter()
+            .find(|prop| matches!(prop, ObjectPatProp::Rest(_)))
+        {
+            self.require(rest.start(), "Object rest", EsTarget::Es2018);
+        }
+        pattern.visit_children_with(self);
+    }
+
+    fn visit_catch_clause(&mut self, clause: &CatchClause) {
+        if clause.param.is_none() {
+            self.require(
+                clause.start(),
+                "A catch without a binding",
+                EsTarget::Es2019,
+            );
+        }
+        clause.visit_children_with(self);
+    }
+
+    fn visit_opt_chain_expr(&mut self, expr: &OptChainExpr) {
+        self.require(expr.start(), "Optional chaining", EsTarget::Es2020);
+        expr.visit_children_with(self);
+    }
+
+    fn visit_big_int(&mut self, literal: &BigInt) {
+        self.require(literal.start(), "A BigInt literal", EsTarget::Es2020);
+    }
+
+    fn visit_class_prop(&mut self, prop: &ClassProp) {
+        self.require(prop.start(), "A class field", EsTarget::Es2022);
+        prop.visit_children_with(self);
+    }
+
+    fn visit_private_prop(&mut self, prop: &PrivateProp) {
+        self.require(prop.start(), "A private class field", EsTarget::Es2022);
+        prop.visit_children_with(self);
+    }
+
+    fn visit_private_method(&mut self, method: &PrivateMethod) {
+        self.require(method.start(), "A private method", EsTarget::Es2022);
+        method.visit_children_with(self);
+    }
+
+    fn visit_static_block(&mut self, block: &StaticBlock) {
+        self.require(block.start(), "A static block", EsTarget::Es2022);
+        block.visit_children_with(self);
+    }
+}
+
+///
+/// Finds syntax in transpiled code that is newer than the target version
+/// Each feature found is reported as a diagnostic with code 0
+pub fn check_target(
+    module_specifier: &ModuleSpecifier,
+    code: &str,
+    target: EsTarget,
+) -> Result<Vec<Diagnostic>, Error> {
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier: module_specifier.clone(),
+        text_info: SourceTextInfo::from_string(code.to_string()),
+        media_type: MediaType::JavaScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })?;
+
+    let mut checker = TargetChecker {
+        target,
+        text_info: parsed.text_info(),
+        diagnostics: Vec::new(),
+    };
+    parsed.module().visit_with(&mut checker);
+    Ok(checker.diagnostics)
+}
+
+///
+/// Parses a module, without transpiling or running it, to find syntax errors
+pub fn validate_syntax(module_specifier: &ModuleSpecifier, code: &str) -> Result<(), SyntaxError> {
+    let to_syntax_error = |diagnostic: &deno_ast::ParseDiagnostic| {
+        let position = diagnostic.display_position();
+        SyntaxError {
+            filename: module_specifier.to_string(),
+            line: position.line_number,
+            column: position.column_number,
+            message: diagnostic.message().to_string(),
+        }
+    };
+
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier: module_specifier.clone(),
+        text_info: SourceTextInfo::from_string(code.to_string()),
+        media_type: MediaType::from_specifier(module_specifier),
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .map_err(|e| to_syntax_error(&e))?;
+
+    // Some errors are recoverable, and reported alongside the parsed module
+    match parsed.diagnostics().first() {
+        Some(diagnostic) => Err(to_syntax_error(diagnostic)),
+        None => Ok(()),
+    }
+}
+
 ///
 /// Transpile an extension
 pub fn transpile_extension(source: &mut ExtensionFileSource) -> Result<(), Error> {
@@ -87,3 +512,74 @@ pub fn transpile_extension(source: &mut ExtensionFileSource) -> Result<(), Error
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test_transpiler {
+    use super::*;
+
+    #[test]
+    fn test_check_target() {
+        let specifier = ModuleSpecifier::parse("file:///test.js").unwrap();
+        let code = "const a = 2 ** 3;\nconst b = async () => a?.b;\nclass C { #x = 1; }";
+
+        let diagnostics =
+            check_target(&specifier, code, EsTarget::Es2015).expect("Could not check code");
+        let features: Vec<_> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.line,
+                    d.message.split(" requires ").next().unwrap_or_default(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (1, "The ** operator"),
+                (2, "An async function"),
+                (2, "Optional chaining"),
+                (3, "A private class field"),
+            ],
+            features
+        );
+        assert!(diagnostics.iter().all(|d| d.code == 0));
+
+        let diagnostics =
+            check_target(&specifier, code, EsTarget::Es2022).expect("Could not check code");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_cache_checks_source() {
+        let specifier = ModuleSpecifier::parse("file:///test.ts").unwrap();
+        let mut cache = TranspileCache::new(4);
+        cache.insert(&specifier, "a", "A".to_string());
+        assert_eq!(Some("A"), cache.get(&specifier, "a"));
+
+        // Store an entry under the key of another source, as a hash collision would
+        let key = TranspileCache::key(&specifier, "b");
+        cache.entries.insert(
+            key,
+            CachedTranspile {
+                specifier: specifier.to_string(),
+                source: "a".to_string(),
+                code: "A".to_string(),
+            },
+        );
+        assert_eq!(None, cache.get(&specifier, "b"));
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let specifier = ModuleSpecifier::parse("file:///test.ts").unwrap();
+        let mut cache = TranspileCache::new(2);
+        cache.insert(&specifier, "a", "A".to_string());
+        cache.insert(&specifier, "b", "B".to_string());
+        cache.insert(&specifier, "c", "C".to_string());
+
+        // Only the oldest entry is evicted
+        assert_eq!(None, cache.get(&specifier, "a"));
+        assert_eq!(Some("B"), cache.get(&specifier, "b"));
+        assert_eq!(Some("C"), cache.get(&specifier, "c"));
+    }
+}
@@ -0,0 +1,310 @@
+//! A lightweight type checker for TypeScript modules
+//!
+//! Full type checking needs the TypeScript compiler, which is not bundled.
+//! Instead, this catches the most common mistake - a value of the wrong
+//! primitive type being assigned to an annotated variable - using only the parser.
+use crate::{traits::ToModuleSpecifier, Error, Module};
+use deno_ast::swc::ast::{
+    ArrowExpr, BindingIdent, BlockStmt, CatchClause, ClassDecl, Expr, FnDecl, ForInStmt, ForOfStmt,
+    ForStmt, Function, Lit, Pat, TsKeywordTypeKind, TsType, UnaryOp, VarDecl, VarDeclKind,
+    VarDeclarator,
+};
+use deno_ast::swc::visit::{Visit, VisitWith};
+use deno_ast::{MediaType, ParseParams, SourceRangedForSpanned, SourceTextInfo};
+use std::{collections::HashMap, fmt::Display};
+
+/// A type error found by `rustyscript::type_check`, or a warning about a module
+/// as it is loaded - see `RuntimeOptions::on_diagnostic`
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Diagnostic {
+    /// The TypeScript error code, such as 2322
+    /// Warnings with no TypeScript equivalent, such as `RuntimeOptions::transpile_target`
+    /// mismatches, use 0
+    pub code: u32,
+
+    /// A description of the error
+    pub message: String,
+
+    /// 1-based line number of the start of the error
+    pub line: usize,
+
+    /// 1-based column number of the start of the error
+    pub column: usize,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TS{} [{}:{}]: {}",
+            self.code, self.line, self.column, self.message
+        )
+    }
+}
+
+/// The primitive types the checker can reason about
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Primitive {
+    Number,
+    String,
+    Boolean,
+    BigInt,
+}
+
+impl Display for Primitive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number => write!(f, "number"),
+            Self::String => write!(f, "string"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::BigInt => write!(f, "bigint"),
+        }
+    }
+}
+
+impl Primitive {
+    /// The primitive named by a type annotation, if it is one
+    fn from_annotation(ty: &TsType) -> Option<Self> {
+        match ty {
+            TsType::TsKeywordType(keyword) => match keyword.kind {
+                TsKeywordTypeKind::TsNumberKeyword => Some(Self::Number),
+                TsKeywordTypeKind::TsStringKeyword => Some(Self::String),
+                TsKeywordTypeKind::TsBooleanKeyword => Some(Self::Boolean),
+                TsKeywordTypeKind::TsBigIntKeyword => Some(Self::BigInt),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// The variables declared in a block or function
+/// A variable of unknown type is kept as None, so that it still hides outer declarations
+struct Scope {
+    variables: HashMap<String, Option<Primitive>>,
+    is_function: bool,
+}
+
+/// Collects the names bound by a pattern, such as a function's parameters
+#[derive(Default)]
+struct BoundNames(Vec<String>);
+
+impl Visit for BoundNames {
+    fn visit_binding_ident(&mut self, ident: &BindingIdent) {
+        self.0.push(ident.id.sym.to_string());
+    }
+}
+
+struct TypeChecker<'a> {
+    text_info: &'a SourceTextInfo,
+    scopes: Vec<Scope>,
+    var_kind: VarDeclKind,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeChecker<'_> {
+    /// The type of a variable, as declared in the innermost scope that has it
+    fn lookup(&self, name: &str) -> Option<Primitive> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name))
+            .copied()
+            .flatten()
+    }
+
+    /// Declare a variable - `var` declarations belong to the enclosing function
+    fn declare(&mut self, name: String, ty: Option<Primitive>, kind: VarDeclKind) {
+        let scope = match kind {
+            VarDeclKind::Var => self.scopes.iter_mut().rev().find(|s| s.is_function),
+            _ => self.scopes.last_mut(),
+        };
+        if let Some(scope) = scope {
+            scope.variables.insert(name, ty);
+        }
+    }
+
+    /// Visit a node inside a new scope, in which the given pattern's names are declared
+    fn scoped<'p, N: VisitWith<Self>>(
+        &mut self,
+        node: &N,
+        is_function: bool,
+        params: impl IntoIterator<Item = &'p Pat>,
+    ) {
+        self.scopes.push(Scope {
+            variables: HashMap::new(),
+            is_function,
+        });
+        for param in params {
+            let mut names = BoundNames::default();
+            param.visit_with(&mut names);
+            for name in names.0 {
+                self.declare(name, None, VarDeclKind::Let);
+            }
+        }
+        node.visit_children_with(self);
+        self.scopes.pop();
+    }
+
+    /// The type of an expression, if it is simple enough to know without inference
+    fn type_of(&self, expr: &Expr) -> Option<Primitive> {
+        match expr {
+            Expr::Lit(Lit::Num(_)) => Some(Primitive::Number),
+            Expr::Lit(Lit::Str(_)) | Expr::Tpl(_) => Some(Primitive::String),
+            Expr::Lit(Lit::Bool(_)) => Some(Primitive::Boolean),
+            Expr::Lit(Lit::BigInt(_)) => Some(Primitive::BigInt),
+            Expr::Unary(unary) => match unary.op {
+                UnaryOp::Bang => Some(Primitive::Boolean),
+                UnaryOp::TypeOf => Some(Primitive::String),
+                _ => None,
+            },
+            Expr::Ident(ident) => self.lookup(ident.sym.as_ref()),
+            Expr::Paren(paren) => self.type_of(&paren.expr),
+            _ => None,
+        }
+    }
+}
+
+impl Visit for TypeChecker<'_> {
+    fn visit_block_stmt(&mut self, block: &BlockStmt) {
+        self.scoped(block, false, []);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        self.scoped(function, true, function.params.iter().map(|p| &p.pat));
+    }
+
+    fn visit_arrow_expr(&mut self, arrow: &ArrowExpr) {
+        self.scoped(arrow, true, &arrow.params);
+    }
+
+    fn visit_catch_clause(&mut self, clause: &CatchClause) {
+        self.scoped(clause, false, &clause.param);
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) {
+        self.scoped(stmt, false, []);
+    }
+
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) {
+        self.scoped(stmt, false, []);
+    }
+
+    fn visit_for_of_stmt(&mut self, stmt: &ForOfStmt) {
+        self.scoped(stmt, false, []);
+    }
+
+    fn visit_fn_decl(&mut self, decl: &FnDecl) {
+        self.declare(decl.ident.sym.to_string(), None, VarDeclKind::Var);
+        decl.visit_children_with(self);
+    }
+
+    fn visit_class_decl(&mut self, decl: &ClassDecl) {
+        self.declare(decl.ident.sym.to_string(), None, VarDeclKind::Let);
+        decl.visit_children_with(self);
+    }
+
+    fn visit_var_decl(&mut self, decl: &VarDecl) {
+        let outer = std::mem::replace(&mut self.var_kind, decl.kind);
+        decl.visit_children_with(self);
+        self.var_kind = outer;
+    }
+
+    fn visit_var_declarator(&mut self, declarator: &VarDeclarator) {
+        declarator.visit_children_with(self);
+
+        let Pat::Ident(binding) = &declarator.name else {
+            return;
+        };
+        let name = binding.id.sym.to_string();
+        let declared = binding
+            .type_ann
+            .as_ref()
+            .and_then(|annotation| Primitive::from_annotation(&annotation.type_ann));
+        let actual = declarator.init.as_ref().and_then(|init| self.type_of(init));
+
+        if let (Some(declared), Some(actual), Some(init)) = (declared, actual, &declarator.init) {
+            if declared != actual {
+                let position = self.text_info.line_and_column_display(init.start());
+                self.diagnostics.push(Diagnostic {
+                    code: 2322,
+                    message: format!("Type '{actual}' is not assignable to type '{declared}'."),
+                    line: position.line_number,
+                    column: position.column_number,
+                });
+            }
+        }
+
+        // Later uses of the name in this scope refer to this declaration
+        self.declare(name, declared.or(actual), self.var_kind);
+    }
+}
+
+/// Check a module for type errors, without running it
+pub fn check(module: &Module) -> Result<Vec<Diagnostic>, Error> {
+    let specifier = module.filename().to_module_specifier()?;
+    let media_type = MediaType::from_specifier(&specifier);
+    let parsed = deno_ast::parse_module(ParseParams {
+        specifier,
+        text_info: SourceTextInfo::from_string(module.contents().to_string()),
+        media_type,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })
+    .map_err(|e| Error::Runtime(e.to_string()))?;
+
+    let mut checker = TypeChecker {
+        text_info: parsed.text_info(),
+        scopes: vec![Scope {
+            variables: HashMap::new(),
+            is_function: true,
+        }],
+        var_kind: VarDeclKind::Var,
+        diagnostics: Vec::new(),
+    };
+    parsed.module().visit_with(&mut checker);
+    Ok(checker.diagnostics)
+}
+
+#[cfg(test)]
+mod test_type_check {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let module = Module::new(
+            "test.ts",
+            "const name = 'test';
+let count: number = 'two';
+let label: string = name;
+let flag: boolean = !count;",
+        );
+
+        let diagnostics = check(&module).expect("Could not check module");
+        assert_eq!(
+            vec![Diagnostic {
+                code: 2322,
+                message: "Type 'string' is not assignable to type 'number'.".to_string(),
+                line: 2,
+                column: 21,
+            }],
+            diagnostics
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        let module = Module::new(
+            "test.ts",
+            "const x = 'a';
+{ const x = 1; }
+let y: string = x;
+function f(x) { let z: string = x; }
+const g = (y) => { var w = 1; { let v: number = w; } };",
+        );
+
+        let diagnostics = check(&module).expect("Could not check module");
+        assert_eq!(Vec::<Diagnostic>::new(), diagnostics);
+    }
+}
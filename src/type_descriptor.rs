@@ -0,0 +1,199 @@
+use crate::Error;
+use deno_core::serde_json::{Map, Value};
+use std::{collections::BTreeMap, fmt::Display};
+
+/// Describes the shape of a value at runtime, for when the expected
+/// type of a result is not known at compile time
+/// See `Runtime::call_function_dynamic`
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ serde_json::json, TypeDescriptor };
+///
+/// let user = TypeDescriptor::object([
+///     ("id", TypeDescriptor::Number),
+///     ("name", TypeDescriptor::String),
+/// ]);
+/// assert!(user.validate(json!({ "id": 1, "name": "test" })).is_ok());
+/// assert!(user.validate(json!({ "id": "1", "name": "test" })).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeDescriptor {
+    /// Any value at all
+    Any,
+
+    /// `null` or `undefined`
+    Null,
+
+    /// `true` or `false`
+    Boolean,
+
+    /// Any number
+    Number,
+
+    /// A number with no fractional part
+    Integer,
+
+    /// A string
+    String,
+
+    /// An array, where every element has the given type
+    Array(Box<TypeDescriptor>),
+
+    /// An object with the given properties
+    /// Properties not listed are removed from the validated value
+    Object(BTreeMap<String, TypeDescriptor>),
+
+    /// Either `null`, or a value of the given type
+    /// Optional object properties may also be missing
+    Optional(Box<TypeDescriptor>),
+}
+
+impl Display for TypeDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "any"),
+            Self::Null => write!(f, "null"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::Number => write!(f, "number"),
+            Self::Integer => write!(f, "integer"),
+            Self::String => write!(f, "string"),
+            Self::Array(inner) => write!(f, "{inner}[]"),
+            Self::Object(_) => write!(f, "object"),
+            Self::Optional(inner) => write!(f, "{inner} | null"),
+        }
+    }
+}
+
+impl TypeDescriptor {
+    /// Describe an array, where every element has the given type
+    pub fn array(element: TypeDescriptor) -> Self {
+        Self::Array(Box::new(element))
+    }
+
+    /// Describe an object with the given properties
+    pub fn object<'a>(properties: impl IntoIterator<Item = (&'a str, TypeDescriptor)>) -> Self {
+        Self::Object(
+            properties
+                .into_iter()
+                .map(|(name, descriptor)| (name.to_string(), descriptor))
+                .collect(),
+        )
+    }
+
+    /// Describe a value that may be `null`
+    pub fn optional(inner: TypeDescriptor) -> Self {
+        Self::Optional(Box::new(inner))
+    }
+
+    /// Check that a value matches this description
+    ///
+    /// # Arguments
+    /// * `value` - The value to check
+    ///
+    /// # Returns
+    /// A `Result` containing the value, with any object properties not in the
+    /// description removed, or an error (`Error::JsonDecode`) describing where
+    /// the value does not match
+    pub fn validate(&self, value: Value) -> Result<Value, Error> {
+        self.validate_at("$", value)
+    }
+
+    fn validate_at(&self, path: &str, value: Value) -> Result<Value, Error> {
+        match (self, value) {
+            (Self::Any, value) => Ok(value),
+            (Self::Null | Self::Optional(_), Value::Null) => Ok(Value::Null),
+            (Self::Optional(inner), value) => inner.validate_at(path, value),
+            (Self::Boolean, value @ Value::Bool(_)) => Ok(value),
+            (Self::Number, value @ Value::Number(_)) => Ok(value),
+            (Self::String, value @ Value::String(_)) => Ok(value),
+
+            (Self::Integer, Value::Number(n)) if n.is_i64() || n.is_u64() => Ok(Value::Number(n)),
+            (Self::Integer, Value::Number(n)) => match n.as_f64() {
+                // JS has no integer type, so whole floats are accepted
+                Some(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+                    Ok(Value::from(f as i64))
+                }
+                _ => Err(self.mismatch(path, &Value::Number(n))),
+            },
+
+            (Self::Array(element), Value::Array(items)) => items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| element.validate_at(&format!("{path}[{i}]"), item))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+
+            (Self::Object(properties), Value::Object(mut object)) => {
+                let mut validated = Map::new();
+                for (name, descriptor) in properties {
+                    let property_path = format!("{path}.{name}");
+                    match object.remove(name) {
+                        Some(value) => {
+                            validated.insert(
+                                name.clone(),
+                                descriptor.validate_at(&property_path, value)?,
+                            );
+                        }
+                        None if matches!(descriptor, Self::Optional(_) | Self::Any) => {}
+                        None => {
+                            return Err(Error::JsonDecode(format!(
+                                "{property_path} is missing; expected {descriptor}"
+                            )))
+                        }
+                    }
+                }
+                Ok(Value::Object(validated))
+            }
+
+            (_, value) => Err(self.mismatch(path, &value)),
+        }
+    }
+
+    fn mismatch(&self, path: &str, value: &Value) -> Error {
+        let found = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        Error::JsonDecode(format!("{path} should be {self}, but found {found}"))
+    }
+}
+
+#[cfg(test)]
+mod test_type_descriptor {
+    use super::*;
+    use deno_core::serde_json::json;
+
+    #[test]
+    fn test_validate() {
+        let descriptor = TypeDescriptor::object([
+            ("id", TypeDescriptor::Integer),
+            ("tags", TypeDescriptor::array(TypeDescriptor::String)),
+            ("parent", TypeDescriptor::optional(TypeDescriptor::Integer)),
+        ]);
+
+        let value = descriptor
+            .validate(json!({ "id": 2.0, "tags": ["a"], "extra": true }))
+            .expect("Valid value was rejected");
+        assert_eq!(json!({ "id": 2, "tags": ["a"] }), value);
+
+        let e = descriptor
+            .validate(json!({ "id": 2, "tags": ["a", 1] }))
+            .expect_err("Invalid value was accepted");
+        assert_eq!(
+            "value could not be deserialized: $.tags[1] should be string, but found number",
+            e.to_string()
+        );
+
+        descriptor
+            .validate(json!({ "tags": [] }))
+            .expect_err("Missing property was accepted");
+        descriptor
+            .validate(json!({ "id": 1.5, "tags": [] }))
+            .expect_err("Fractional integer was accepted");
+    }
+}
@@ -1,5 +1,8 @@
 use crate::traits::ToModuleSpecifier;
-use crate::{Error, Module, ModuleWrapper, Runtime};
+use crate::transpiler::{self, TranspileOptions};
+use crate::Diagnostic;
+use crate::{Error, Module, ModuleWrapper, Runtime, RuntimeOptions};
+use std::{path::Path, time::Duration};
 
 /// Evaluate a piece of non-ECMAScript-module JavaScript code
 /// Effects on the global scope will not persist
@@ -28,6 +31,37 @@ where
     runtime.eval(javascript)
 }
 
+/// Evaluate a piece of non-ECMAScript-module JavaScript code, with a time limit
+/// Safer than [evaluate] for untrusted code, since scripts that never finish,
+/// even ones stuck in a synchronous loop, are stopped once the timeout passes
+///
+/// # Arguments
+/// * `javascript` - A single javascript expression
+/// * `timeout` - The longest the expression may run for
+///
+/// # Returns
+/// A `Result` containing the deserialized result of the expression if successful,
+/// or an error if execution fails, times out (`Error::Timeout`), or the result cannot be deserialized.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// let result = rustyscript::evaluate_with_timeout::<i64>("while(true){}", Duration::from_millis(50));
+/// assert!(matches!(result, Err(rustyscript::Error::Timeout { .. })));
+/// ```
+pub fn evaluate_with_timeout<T>(javascript: &str, timeout: Duration) -> Result<T, Error>
+where
+    T: deno_core::serde::de::DeserializeOwned,
+{
+    let mut runtime = Runtime::new(RuntimeOptions {
+        timeout,
+        ..Default::default()
+    })?;
+    runtime.eval(javascript)
+}
+
 /// Validates the syntax of some JS
 ///
 /// # Arguments
@@ -55,6 +89,63 @@ pub async fn validate(javascript: &str) -> Result<bool, Error> {
     }
 }
 
+/// Transpiles a module from TS to JS without running it
+/// Modules that do not need transpilation are returned unchanged
+///
+/// # Arguments
+/// * `module` - The module to transpile
+/// * `options` - Options controlling the output
+///
+/// # Returns
+/// A `Result` containing a new `Module`, with a `.js` filename and the transpiled contents,
+/// or an error if the module could not be parsed
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Module, TranspileOptions };
+///
+/// let module = Module::new("test.ts", "export const x: number = 2;");
+/// let module = rustyscript::transpile_module(&module, TranspileOptions::default()).expect("Could not transpile");
+/// assert_eq!("test.js", module.filename());
+/// ```
+pub fn transpile_module(module: &Module, options: TranspileOptions) -> Result<Module, Error> {
+    let specifier = module.filename().to_module_specifier()?;
+    if !transpiler::is_transpiled(&specifier) {
+        return Ok(module.clone());
+    }
+
+    let contents = transpiler::transpile_with_options(&specifier, module.contents(), options)?;
+    let filename = Path::new(module.filename()).with_extension("js");
+    Ok(Module::new(&filename.to_string_lossy(), &contents))
+}
+
+/// Checks a TypeScript module for type errors, without running it
+///
+/// This is not a full type checker - only values of the wrong primitive type
+/// assigned to annotated variables are detected. It uses the same TS error codes
+/// as the TypeScript compiler, such as 2322
+///
+/// # Arguments
+/// * `module` - The module to check
+///
+/// # Returns
+/// A `Result` containing the type errors found, if any,
+/// or an error if the module could not be parsed
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::Module;
+///
+/// let module = Module::new("test.ts", "export const x: number = 'two';");
+/// let diagnostics = rustyscript::type_check(&module).expect("Could not parse");
+/// assert_eq!(2322, diagnostics[0].code);
+/// ```
+pub fn type_check(module: &Module) -> Result<Vec<Diagnostic>, Error> {
+    crate::type_check::check(module)
+}
+
 /// Imports a JS module into a new runtime
 ///
 /// # Arguments
@@ -92,6 +183,25 @@ pub fn resolve_path(path: &str) -> Result<String, Error> {
     Ok(path.to_module_specifier()?.to_string())
 }
 
+/// Resolve a path to an absolute path, relative to a base directory
+/// instead of the current working directory
+///
+/// # Arguments
+/// * `base` - The directory relative paths are resolved against
+/// * `path` - A path
+///
+/// # Example
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// let full_path = rustyscript::resolve_path_from(Path::new("/srv/app"), "test.js").expect("Something went wrong!");
+/// assert!(full_path.ends_with("/srv/app/test.js"));
+/// ```
+pub fn resolve_path_from(base: &Path, path: &str) -> Result<String, Error> {
+    Ok(deno_core::resolve_path(path, base)?.to_string())
+}
+
 #[macro_use]
 mod runtime_macros {
     /// Map a series of values to a slice of `serde_json::Value` objects
@@ -149,16 +259,70 @@ mod test_runtime {
             .expect("invalid expression");
     }
 
+    #[test]
+    fn test_evaluate_with_timeout() {
+        let timeout = Duration::from_millis(100);
+        assert_eq!(
+            5,
+            evaluate_with_timeout::<i64>("3 + 2", timeout).expect("invalid expression")
+        );
+
+        let e = evaluate_with_timeout::<i64>("while (true) {}", timeout)
+            .expect_err("Infinite expression did not time out");
+        assert!(matches!(e, Error::Timeout { .. }));
+    }
+
     #[tokio::test]
     async fn test_validate() {
         assert!(validate("3 + 2").await.expect("invalid expression"));
         assert!(!validate("5;+-").await.expect("invalid expression"));
     }
 
+    #[test]
+    fn test_transpile_module() {
+        let module = Module::new(
+            "test.ts",
+            "
+            export function first<T>(items: Array<T>): T | undefined {
+                return items[0];
+            }
+            const n: number = first<number>([1, 2]) as number;
+        ",
+        );
+
+        let transpiled = transpile_module(&module, TranspileOptions { source_map: false })
+            .expect("Could not transpile");
+        assert_eq!("test.js", transpiled.filename());
+        assert!(transpiled.contents().contains("function first(items)"));
+        assert!(!transpiled.contents().contains("<T>"));
+        assert!(!transpiled.contents().contains(": number"));
+        assert!(!transpiled.contents().contains("sourceMappingURL"));
+
+        let transpiled =
+            transpile_module(&module, Default::default()).expect("Could not transpile");
+        assert!(transpiled.contents().contains("sourceMappingURL"));
+    }
+
     #[tokio::test]
     async fn test_resolve_path() {
         assert!(resolve_path("test.js")
             .expect("invalid path")
             .ends_with("test.js"));
     }
+
+    #[test]
+    fn test_resolve_path_from() {
+        let base = std::env::temp_dir().join("rustyscript_base");
+        let expected = deno_core::ModuleSpecifier::from_file_path(base.join("a.js"))
+            .expect("invalid path")
+            .to_string();
+        assert_eq!(
+            expected,
+            resolve_path_from(&base, "a.js").expect("invalid path")
+        );
+        assert_ne!(
+            resolve_path("a.js").expect("invalid path"),
+            resolve_path_from(&base, "a.js").expect("invalid path")
+        );
+    }
 }
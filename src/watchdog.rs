@@ -0,0 +1,168 @@
+//! Enforces timeouts from a separate thread, so that even scripts
+//! stuck in a synchronous loop can be interrupted
+//!
+//! A single long-lived thread watches the deadlines of every runtime in the
+//! process, so arming a watchdog does not cost a thread of its own
+use deno_core::{futures::channel::oneshot, v8};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Default)]
+struct WatchdogState {
+    stopped: bool,
+    fired: bool,
+}
+
+/// A deadline being watched, and what to do once it passes
+struct ArmedDeadline {
+    isolate: v8::IsolateHandle,
+    state: Arc<Mutex<WatchdogState>>,
+    expired: oneshot::Sender<()>,
+}
+
+impl ArmedDeadline {
+    /// Terminate execution, unless the watchdog was stopped first
+    fn fire(self) {
+        let mut state = lock(&self.state);
+        if !state.stopped {
+            state.fired = true;
+            self.isolate.terminate_execution();
+            self.expired.send(()).ok();
+        }
+    }
+}
+
+/// The deadlines watched by the watchdog thread, by when they pass and a unique id
+struct WatchdogThread {
+    deadlines: Mutex<BTreeMap<(Instant, u64), ArmedDeadline>>,
+    changed: Condvar,
+    next_id: AtomicU64,
+}
+
+impl WatchdogThread {
+    /// The process's watchdog thread, started the first time it is needed
+    fn get() -> &'static Self {
+        static THREAD: OnceLock<WatchdogThread> = OnceLock::new();
+        THREAD.get_or_init(|| {
+            std::thread::Builder::new()
+                .name("rustyscript-watchdog".to_string())
+                .spawn(|| Self::get().run())
+                .expect("Could not start the watchdog thread");
+
+            Self {
+                deadlines: Mutex::new(BTreeMap::new()),
+                changed: Condvar::new(),
+                next_id: AtomicU64::new(0),
+            }
+        })
+    }
+
+    /// Fire deadlines as they pass, sleeping until the next one in between
+    fn run(&self) {
+        let mut deadlines = lock(&self.deadlines);
+        loop {
+            let now = Instant::now();
+            while let Some(entry) = deadlines.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                entry.remove().fire();
+            }
+
+            let next = deadlines.keys().next().map(|(deadline, _)| *deadline);
+            deadlines = match next {
+                Some(deadline) => {
+                    self.changed
+                        .wait_timeout(deadlines, deadline.saturating_duration_since(now))
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self
+                    .changed
+                    .wait(deadlines)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+}
+
+/// Lock a mutex - the state it guards stays consistent even if a holder panicked
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Terminates execution in an isolate once a deadline passes
+/// Dropping or stopping the watchdog before then disarms it
+pub struct Watchdog {
+    state: Arc<Mutex<WatchdogState>>,
+    expired: Option<oneshot::Receiver<()>>,
+    key: Option<(Instant, u64)>,
+}
+
+impl Watchdog {
+    /// Start watching an isolate
+    /// Nothing is watched if the timeout is too long to ever pass
+    pub fn start(timeout: Duration, isolate: v8::IsolateHandle) -> Self {
+        let state = Arc::new(Mutex::new(WatchdogState::default()));
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            return Self {
+                state,
+                expired: None,
+                key: None,
+            };
+        };
+
+        let (expired_tx, expired_rx) = oneshot::channel();
+        let thread = WatchdogThread::get();
+        let key = (deadline, thread.next_id.fetch_add(1, Ordering::Relaxed));
+        lock(&thread.deadlines).insert(
+            key,
+            ArmedDeadline {
+                isolate,
+                state: state.clone(),
+                expired: expired_tx,
+            },
+        );
+        thread.changed.notify_one();
+
+        Self {
+            state,
+            expired: Some(expired_rx),
+            key: Some(key),
+        }
+    }
+
+    /// Resolves once the deadline has passed
+    /// Unlike tokio's timer, this is not limited to millisecond resolution,
+    /// and is not delayed by other tasks on a busy runtime
+    pub async fn expired(&mut self) {
+        match self.expired.as_mut() {
+            Some(expired) if expired.await.is_ok() => {}
+            _ => std::future::pending().await,
+        }
+    }
+
+    /// Disarm the watchdog
+    ///
+    /// # Returns
+    /// True if the deadline had already passed, and execution was terminated
+    pub fn stop(self) -> bool {
+        let mut state = lock(&self.state);
+        state.stopped = true;
+        state.fired
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            lock(&WatchdogThread::get().deadlines).remove(&key);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    ext::worker::{worker, WorkerSender},
+    Error, Module, Runtime, RuntimeOptions, Undefined,
+};
+use deno_core::{
+    futures::{channel::mpsc, StreamExt},
+    serde_json, PollEventLoopOptions,
+};
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    task::Poll,
+    thread::JoinHandle,
+};
+
+/// A handle to a module running as a worker
+///
+/// The worker runs in its own isolate, on its own thread. Messages sent with
+/// `post` are delivered to the worker's global `onmessage` function, and messages
+/// the worker sends with `postMessage` can be retrieved with `recv`.
+///
+/// The worker stops once the handle is dropped
+pub struct WorkerHandle {
+    sender: mpsc::UnboundedSender<serde_json::Value>,
+    receiver: Receiver<serde_json::Value>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl WorkerHandle {
+    /// Start a new worker running the given module
+    pub(crate) fn new(module: &Module) -> Result<Self, Error> {
+        let (sender, inbox) = mpsc::unbounded();
+        let (outbox, receiver) = channel();
+
+        let module = module.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("rustyscript worker ({})", module.filename()))
+            .spawn(move || Self::run(module, inbox, outbox))?;
+
+        Ok(Self {
+            sender,
+            receiver,
+            thread: Some(thread),
+        })
+    }
+
+    /// Send a message to the worker's `onmessage` function
+    ///
+    /// # Arguments
+    /// * `message` - The message to send
+    ///
+    /// # Returns
+    /// A `Result` that is an error (`Error`) if the message could not be
+    /// serialized, or if the worker has stopped
+    pub fn post<T>(&self, message: &T) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+    {
+        let message = serde_json::to_value(message)?;
+        self.sender
+            .unbounded_send(message)
+            .map_err(|_| Error::Runtime("The worker has stopped".to_string()))
+    }
+
+    /// Wait for the next message posted by the worker
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized message, or an error (`Error`)
+    /// if the message could not be deserialized, or if the worker has stopped
+    pub fn recv<T>(&mut self) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self.receiver.recv() {
+            Ok(message) => Ok(serde_json::from_value(message)?),
+            Err(_) => Err(self.stop_reason()),
+        }
+    }
+
+    /// Get the next message posted by the worker, if one is waiting
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized message if there is one, or an error (`Error`)
+    /// if the message could not be deserialized
+    pub fn try_recv<T>(&mut self) -> Result<Option<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self.receiver.try_recv() {
+            Ok(message) => Ok(Some(serde_json::from_value(message)?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Get the error that stopped the worker
+    fn stop_reason(&mut self) -> Error {
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(Err(e))) => e,
+            Some(Err(_)) => Error::Runtime("The worker panicked".to_string()),
+            _ => Error::Runtime("The worker has stopped".to_string()),
+        }
+    }
+
+    fn run(
+        module: Module,
+        mut inbox: mpsc::UnboundedReceiver<serde_json::Value>,
+        outbox: Sender<serde_json::Value>,
+    ) -> Result<(), Error> {
+        let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        tokio_runtime.block_on(async move {
+            let mut runtime = Runtime::new(RuntimeOptions {
+                extensions: vec![worker::init_ops_and_esm()],
+                ..Default::default()
+            })?;
+            runtime.put(WorkerSender(outbox))?;
+
+            let module_context = runtime.load_module(&module).await?;
+            loop {
+                // Timers and promises keep running while the worker waits for a message
+                let message = std::future::poll_fn(|cx| {
+                    if let Poll::Ready(message) = inbox.poll_next_unpin(cx) {
+                        return Poll::Ready(Ok(message));
+                    }
+                    match runtime
+                        .deno_runtime()
+                        .poll_event_loop(cx, PollEventLoopOptions::default())
+                    {
+                        Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                        _ => Poll::Pending,
+                    }
+                })
+                .await?;
+
+                // The handle was dropped
+                let Some(message) = message else {
+                    return Ok(());
+                };
+                runtime
+                    .call_function::<Undefined>(&module_context, "onmessage", &[message])
+                    .await?;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_worker {
+    use super::*;
+
+    #[test]
+    fn test_echo() {
+        let module = Module::new(
+            "worker.js",
+            "
+            globalThis.onmessage = (message) => postMessage(message);
+        ",
+        );
+
+        let mut worker = WorkerHandle::new(&module).expect("Could not start worker");
+        worker.post(&"hello").expect("Could not post message");
+        worker.post(&2).expect("Could not post message");
+
+        let first: String = worker.recv().expect("Could not receive message");
+        let second: usize = worker.recv().expect("Could not receive message");
+        assert_eq!("hello", first);
+        assert_eq!(2, second);
+    }
+
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_timers_while_waiting() {
+        let module = Module::new(
+            "worker.js",
+            "
+            globalThis.onmessage = (message) => {
+                setTimeout(() => postMessage(message), 20);
+            };
+        ",
+        );
+
+        let mut worker = WorkerHandle::new(&module).expect("Could not start worker");
+        worker.post(&"later").expect("Could not post message");
+
+        // The timer fires while the worker is idle, waiting for more messages
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let message = loop {
+            if let Some(message) = worker.try_recv::<String>().expect("Could not receive") {
+                break message;
+            }
+            assert!(std::time::Instant::now() < deadline, "Timer never fired");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!("later", message);
+    }
+
+    #[test]
+    fn test_stopped_worker() {
+        let module = Module::new("worker.js", "throw new Error('failed');");
+
+        let mut worker = WorkerHandle::new(&module).expect("Could not start worker");
+        let e = worker
+            .recv::<usize>()
+            .expect_err("Did not detect stopped worker");
+        assert!(e.to_string().contains("failed"));
+    }
+}
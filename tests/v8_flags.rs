@@ -0,0 +1,30 @@
+//! V8 flags are process-global and must be set before the platform is initialized,
+//! so this runs in its own test binary, where no other runtime is created first
+use rustyscript::{Runtime, RuntimeOptions};
+
+#[test]
+fn test_v8_flags() {
+    let mut runtime = Runtime::new(RuntimeOptions {
+        v8_flags: vec!["--expose-gc".to_string()],
+        ..Default::default()
+    })
+    .expect("Could not create the runtime");
+
+    let gc: String = runtime
+        .eval("typeof globalThis.gc")
+        .expect("Could not eval");
+    assert_eq!("function", gc);
+
+    // Later runtimes can repeat the same flags, but not change them
+    Runtime::new(RuntimeOptions {
+        v8_flags: vec!["--expose-gc".to_string()],
+        ..Default::default()
+    })
+    .expect("Could not create a second runtime");
+
+    Runtime::new(RuntimeOptions {
+        v8_flags: vec!["--no-expose-gc".to_string()],
+        ..Default::default()
+    })
+    .expect_err("Conflicting flags were accepted");
+}